@@ -0,0 +1,290 @@
+pub use async_trait::async_trait;
+use std::fmt::Display;
+use std::future::Future;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Mailbox size for actors that see little traffic
+pub const DEFAULT_MAILBOX: usize = 64;
+
+/// Errors talking to an actor
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("Actor has stopped")]
+    Closed,
+    #[error("Actor mailbox is full")]
+    Full,
+    #[error("Actor dropped the request without replying")]
+    NoReply,
+}
+
+/// A message-driven agent that owns its state and handles its mailbox one message at a time
+#[async_trait]
+pub trait Actor: Send + 'static {
+    type Message: Send + 'static;
+    type Error: Display + Send;
+
+    /// Runs once before the first message; an error stops the actor without calling `stopped`
+    async fn started(&mut self, _ctx: &mut Context<Self::Message>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Handle one message; an error is logged and the actor moves on to the next one
+    async fn handle(
+        &mut self,
+        ctx: &mut Context<Self::Message>,
+        msg: Self::Message,
+    ) -> Result<(), Self::Error>;
+
+    /// Runs once after the actor stopped itself or every address to it was dropped
+    async fn stopped(&mut self, _ctx: &mut Context<Self::Message>) {}
+}
+
+/// Where to send an actor messages; the actor stops once every address is dropped
+pub struct Address<M> {
+    tx: mpsc::Sender<M>,
+}
+
+impl<M> Clone for Address<M> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<M: Send + 'static> Address<M> {
+    /// Queue a message, waiting for room in the mailbox
+    pub async fn send(&self, msg: M) -> Result<(), Error> {
+        self.tx.send(msg).await.map_err(|_| Error::Closed)
+    }
+
+    /// Queue a message if the mailbox has room
+    pub fn try_send(&self, msg: M) -> Result<(), Error> {
+        self.tx.try_send(msg).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => Error::Full,
+            mpsc::error::TrySendError::Closed(_) => Error::Closed,
+        })
+    }
+
+    /// Send a message carrying a reply channel and wait for the answer
+    pub async fn ask<R>(&self, msg: impl FnOnce(oneshot::Sender<R>) -> M) -> Result<R, Error> {
+        let (reply, answer) = oneshot::channel();
+        self.send(msg(reply)).await?;
+        answer.await.map_err(|_| Error::NoReply)
+    }
+
+    /// Whether the actor has stopped taking messages
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+/// An actor's view of its own runtime, passed to every hook
+pub struct Context<M> {
+    name: String,
+    address: mpsc::WeakSender<M>,
+    stopping: bool,
+    /// Helper tasks that live as long as the actor
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<M: Send + 'static> Context<M> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// An address to the actor itself, always there during `started` and `None` later once
+    /// every outside address is gone
+    pub fn address(&self) -> Option<Address<M>> {
+        self.address.upgrade().map(|tx| Address { tx })
+    }
+
+    /// Stop after the current message
+    pub fn stop(&mut self) {
+        self.stopping = true;
+    }
+
+    /// Run a helper task, such as one feeding the mailbox, that is aborted when the actor stops
+    pub fn spawn(&mut self, task: impl Future<Output = ()> + Send + 'static) {
+        self.tasks.retain(|task| !task.is_finished());
+        self.tasks.push(tokio::spawn(task));
+    }
+}
+
+impl<M> Drop for Context<M> {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Start an actor on its own task with a mailbox of `capacity` messages
+///
+/// Aborting the returned handle stops the actor without running `stopped`.
+pub fn spawn<A: Actor>(
+    name: impl Into<String>,
+    actor: A,
+    capacity: usize,
+) -> (Address<A::Message>, JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+    let ctx = Context {
+        name: name.into(),
+        address: tx.downgrade(),
+        stopping: false,
+        tasks: Vec::new(),
+    };
+    let handle = tokio::spawn(run(actor, ctx, rx, tx.clone()));
+    (Address { tx }, handle)
+}
+
+async fn run<A: Actor>(
+    mut actor: A,
+    mut ctx: Context<A::Message>,
+    mut mailbox: mpsc::Receiver<A::Message>,
+    keepalive: mpsc::Sender<A::Message>,
+) {
+    // Lets `started` hand out addresses even if the spawner already dropped its own
+    let started = actor.started(&mut ctx).await;
+    drop(keepalive);
+    if let Err(e) = started {
+        warn!("Actor {} failed to start: {}", ctx.name, e);
+        return;
+    }
+    debug!("Actor {} started", ctx.name);
+
+    while !ctx.stopping {
+        let Some(msg) = mailbox.recv().await else {
+            break;
+        };
+        if let Err(e) = actor.handle(&mut ctx, msg).await {
+            warn!("Actor {} failed to handle a message: {}", ctx.name, e);
+        }
+    }
+
+    mailbox.close();
+    actor.stopped(&mut ctx).await;
+    debug!("Actor {} stopped", ctx.name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    enum CounterMessage {
+        Add(u64),
+        Fail,
+        Total(oneshot::Sender<u64>),
+        Stop,
+    }
+
+    struct Counter {
+        total: u64,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Actor for Counter {
+        type Message = CounterMessage;
+        type Error = String;
+
+        async fn started(&mut self, _ctx: &mut Context<CounterMessage>) -> Result<(), String> {
+            self.log.lock().unwrap().push("started".to_string());
+            Ok(())
+        }
+
+        async fn handle(
+            &mut self,
+            ctx: &mut Context<CounterMessage>,
+            msg: CounterMessage,
+        ) -> Result<(), String> {
+            match msg {
+                CounterMessage::Add(n) => self.total += n,
+                CounterMessage::Fail => return Err("boom".to_string()),
+                CounterMessage::Total(reply) => {
+                    let _ = reply.send(self.total);
+                }
+                CounterMessage::Stop => ctx.stop(),
+            }
+            Ok(())
+        }
+
+        async fn stopped(&mut self, _ctx: &mut Context<CounterMessage>) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("stopped at {}", self.total));
+        }
+    }
+
+    fn counter() -> (Counter, Arc<Mutex<Vec<String>>>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let counter = Counter {
+            total: 0,
+            log: log.clone(),
+        };
+        (counter, log)
+    }
+
+    #[tokio::test]
+    async fn actors_handle_messages_in_order_and_survive_errors() {
+        let (actor, log) = counter();
+        let (address, handle) = spawn("counter", actor, 4);
+
+        address.send(CounterMessage::Add(2)).await.unwrap();
+        address.send(CounterMessage::Fail).await.unwrap();
+        address.send(CounterMessage::Add(3)).await.unwrap();
+        assert_eq!(address.ask(CounterMessage::Total).await, Ok(5));
+
+        // Dropping the last address lets the actor wind down
+        drop(address);
+        handle.await.unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["started", "stopped at 5"]);
+    }
+
+    #[tokio::test]
+    async fn stopping_closes_the_mailbox_and_aborts_helper_tasks() {
+        let (actor, log) = counter();
+        let (address, handle) = spawn("counter", actor, 4);
+
+        // Feeds itself forever from a task, even though its only outside address is dropped
+        struct Helper;
+        #[async_trait]
+        impl Actor for Helper {
+            type Message = ();
+            type Error = String;
+
+            async fn started(&mut self, ctx: &mut Context<()>) -> Result<(), String> {
+                let me = ctx.address().ok_or("no address")?;
+                ctx.spawn(async move {
+                    while me.send(()).await.is_ok() {
+                        tokio::task::yield_now().await;
+                    }
+                });
+                Ok(())
+            }
+
+            async fn handle(&mut self, ctx: &mut Context<()>, _msg: ()) -> Result<(), String> {
+                ctx.stop();
+                Ok(())
+            }
+        }
+        let (helper, helper_handle) = spawn("helper", Helper, 1);
+        drop(helper);
+        helper_handle.await.unwrap();
+
+        address.send(CounterMessage::Add(1)).await.unwrap();
+        address.send(CounterMessage::Stop).await.unwrap();
+        handle.await.unwrap();
+        assert!(address.is_closed());
+        assert_eq!(
+            address.send(CounterMessage::Add(1)).await,
+            Err(Error::Closed)
+        );
+        assert_eq!(*log.lock().unwrap(), vec!["started", "stopped at 1"]);
+    }
+}
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/// Checkpoints of finalized ChaosChain blocks, plus deposits into and withdrawals
+/// out of ChaosChain.
+/// A checkpoint is accepted from anyone once validators holding `thresholdBps` of
+/// the registered stake have signed `checkpointDigest(blockHash, stateRoot)` with
+/// their secp256k1 keys. Amounts are in gwei so they fit ChaosChain's u64 balances.
+contract ChaosBridge {
+    /// Stake of each registered validator, by signing address
+    mapping(address => uint256) public stakeOf;
+    uint256 public totalStake;
+    /// Share of `totalStake` that must sign a checkpoint, in basis points
+    uint256 public immutable thresholdBps;
+    bytes32 public latestRoot;
+    mapping(bytes32 => bool) public isIncluded;
+    /// Withdrawal tree root committed by each checkpoint
+    mapping(bytes32 => bytes32) public checkpointRoots;
+    uint64 public nextDepositId;
+    mapping(uint64 => bool) public claimed;
+
+    event CheckpointPosted(bytes32 indexed blockHash, bytes32 stateRoot, uint256 signatures);
+    event Deposited(uint64 indexed id, address from, bytes32 recipient, uint64 amount);
+    event WithdrawalClaimed(uint64 indexed id, address recipient, uint64 amount);
+
+    constructor(address[] memory validators, uint256[] memory stakes, uint256 _thresholdBps) {
+        require(validators.length == stakes.length, "length mismatch");
+        require(_thresholdBps > 0 && _thresholdBps <= 10_000, "invalid threshold");
+        for (uint256 i = 0; i < validators.length; i++) {
+            require(validators[i] != address(0) && stakes[i] > 0, "invalid validator");
+            require(stakeOf[validators[i]] == 0, "duplicate validator");
+            stakeOf[validators[i]] = stakes[i];
+            totalStake += stakes[i];
+        }
+        require(totalStake > 0, "no validators");
+        thresholdBps = _thresholdBps;
+    }
+
+    /// What validators sign to attest that `stateRoot` is the state after `blockHash`
+    function checkpointDigest(bytes32 blockHash, bytes32 stateRoot) public view returns (bytes32) {
+        return keccak256(abi.encodePacked(address(this), blockHash, stateRoot));
+    }
+
+    /// Signatures from unregistered keys, and repeats of a signer, add no stake
+    function postCheckpoint(bytes32 blockHash, bytes32 stateRoot, bytes[] calldata signatures) external {
+        require(!isIncluded[blockHash], "already posted");
+
+        bytes32 digest = checkpointDigest(blockHash, stateRoot);
+        address[] memory signers = new address[](signatures.length);
+        uint256 signedStake;
+        for (uint256 i = 0; i < signatures.length; i++) {
+            address signer = recover(digest, signatures[i]);
+            if (signer == address(0) || stakeOf[signer] == 0) continue;
+            bool seen;
+            for (uint256 j = 0; j < i; j++) {
+                if (signers[j] == signer) {
+                    seen = true;
+                    break;
+                }
+            }
+            if (seen) continue;
+            signers[i] = signer;
+            signedStake += stakeOf[signer];
+        }
+        require(signedStake * 10_000 >= totalStake * thresholdBps, "insufficient signatures");
+
+        isIncluded[blockHash] = true;
+        checkpointRoots[blockHash] = stateRoot;
+        latestRoot = stateRoot;
+        emit CheckpointPosted(blockHash, stateRoot, signatures.length);
+    }
+
+    function deposit(bytes32 recipient) external payable {
+        require(msg.value > 0 && msg.value % 1 gwei == 0, "deposit whole gwei");
+        emit Deposited(nextDepositId, msg.sender, recipient, uint64(msg.value / 1 gwei));
+        nextDepositId++;
+    }
+
+    /// Pay out a withdrawal burned on ChaosChain, proven against a checkpoint's root.
+    /// Leaves are sha256(id || account || recipient || amount); an odd last node is
+    /// paired with itself.
+    function claimWithdrawal(
+        bytes32 blockHash,
+        uint64 id,
+        bytes32 account,
+        address recipient,
+        uint64 amount,
+        bytes32[] calldata proof
+    ) external {
+        require(isIncluded[blockHash], "unknown checkpoint");
+        require(!claimed[id], "already claimed");
+
+        bytes32 node = sha256(abi.encodePacked(id, account, recipient, amount));
+        uint64 index = id;
+        for (uint256 i = 0; i < proof.length; i++) {
+            node = index % 2 == 0
+                ? sha256(abi.encodePacked(node, proof[i]))
+                : sha256(abi.encodePacked(proof[i], node));
+            index /= 2;
+        }
+        require(node == checkpointRoots[blockHash], "invalid proof");
+
+        claimed[id] = true;
+        emit WithdrawalClaimed(id, recipient, amount);
+        (bool ok, ) = recipient.call{value: uint256(amount) * 1 gwei}("");
+        require(ok, "transfer failed");
+    }
+
+    /// Signer of a 65 byte r || s || v signature, zero if it is malformed
+    function recover(bytes32 digest, bytes calldata signature) internal pure returns (address) {
+        if (signature.length != 65) return address(0);
+        bytes32 r = bytes32(signature[0:32]);
+        bytes32 s = bytes32(signature[32:64]);
+        uint8 v = uint8(signature[64]);
+        if (v < 27) v += 27;
+        return ecrecover(digest, v, r, s);
+    }
+}
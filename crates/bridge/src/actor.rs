@@ -0,0 +1,182 @@
+use crate::{service::CheckpointService, Bridge, Error};
+use chaoschain_actor::{async_trait, Actor, Context};
+use chaoschain_core::Block;
+use ethers::types::{Signature, H256};
+use serde::Serialize;
+use tokio::sync::{broadcast, oneshot};
+use tracing::{info, warn};
+
+/// Bridge events kept for subscribers that fall behind
+const EVENT_BUFFER: usize = 64;
+
+/// Messages that the bridge actor can handle
+#[derive(Debug)]
+pub enum BridgeMessage {
+    /// A block was finalized with the state root after it and the validators' signatures
+    /// over both
+    Finalized {
+        block: Box<Block>,
+        state_root: [u8; 32],
+        signatures: Vec<Signature>,
+    },
+    /// Height of the last block posted to L1
+    LastCheckpoint(oneshot::Sender<Option<u64>>),
+}
+
+/// What happened to a checkpoint, for anyone following the bridge
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeEvent {
+    /// A checkpoint was posted to L1
+    Anchored {
+        height: u64,
+        block_hash: [u8; 32],
+        l1_tx_hash: H256,
+    },
+    /// Posting a checkpoint failed; the next finalized block retries
+    Failed { height: u64, error: String },
+}
+
+/// Runs a checkpoint service behind a mailbox so block finality never waits on L1
+pub struct BridgeActor<B: Bridge> {
+    service: CheckpointService<B>,
+    events: broadcast::Sender<BridgeEvent>,
+}
+
+impl<B: Bridge> BridgeActor<B> {
+    pub fn new(service: CheckpointService<B>) -> Self {
+        Self {
+            service,
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    /// Follow checkpoints from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<BridgeEvent> {
+        self.events.subscribe()
+    }
+}
+
+#[async_trait]
+impl<B: Bridge + 'static> Actor for BridgeActor<B> {
+    type Message = BridgeMessage;
+    type Error = Error;
+
+    async fn started(&mut self, _ctx: &mut Context<BridgeMessage>) -> Result<(), Error> {
+        match self.service.bridge().latest_finalized_root().await {
+            Ok(root) => info!("Bridge starting from L1 state root {}", hex::encode(root)),
+            Err(e) => warn!("Failed to read the latest L1 state root: {}", e),
+        }
+        Ok(())
+    }
+
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<BridgeMessage>,
+        msg: BridgeMessage,
+    ) -> Result<(), Error> {
+        match msg {
+            BridgeMessage::Finalized {
+                block,
+                state_root,
+                signatures,
+            } => {
+                let finalized = self.service.on_finalized(&block, state_root, signatures);
+                let event = match finalized.await {
+                    Ok(Some(l1_tx_hash)) => BridgeEvent::Anchored {
+                        height: block.height,
+                        block_hash: block.hash(),
+                        l1_tx_hash,
+                    },
+                    Ok(None) => return Ok(()),
+                    Err(e) => BridgeEvent::Failed {
+                        height: block.height,
+                        error: e.to_string(),
+                    },
+                };
+                // Nobody listening is fine
+                let _ = self.events.send(event);
+            }
+            BridgeMessage::LastCheckpoint(reply) => {
+                let _ = reply.send(self.service.last_checkpoint());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{checkpoint_digest, mock::MockL1, Config};
+    use chaoschain_actor::DEFAULT_MAILBOX;
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::Address,
+    };
+    use std::collections::{BTreeMap, HashMap};
+
+    #[tokio::test]
+    async fn bridge_actor_reports_checkpoints() {
+        let validator = LocalWallet::from_bytes(&[3; 32]).unwrap();
+        let config = Config {
+            eth_rpc: String::new(),
+            bridge_address: Address::zero(),
+            required_confirmations: 1,
+        };
+        let mut l1 = MockL1::new(config, 0.67);
+        l1.register_validator(validator.address(), 100);
+        let actor = BridgeActor::new(CheckpointService::new(l1, 1));
+        let mut events = actor.subscribe();
+        let (bridge, _handle) = chaoschain_actor::spawn("bridge", actor, DEFAULT_MAILBOX);
+
+        let block = Block {
+            parent_hash: [0; 32],
+            height: 1,
+            transactions: Vec::new(),
+            state_root: [1; 32],
+            proposer_sig: [0; 64],
+            message: "block 1".to_string(),
+            producer_id: "producer".to_string(),
+            votes: HashMap::new(),
+            timestamp: 0,
+            discussion: Vec::new(),
+            meme: None,
+            style: None,
+            certificate: BTreeMap::new(),
+        };
+        let digest = checkpoint_digest(Address::zero(), &block.hash(), &block.state_root);
+        let signature = validator.sign_hash(digest).unwrap();
+
+        // Without signatures the L1 contract refuses the checkpoint
+        bridge
+            .send(BridgeMessage::Finalized {
+                block: Box::new(block.clone()),
+                state_root: block.state_root,
+                signatures: Vec::new(),
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            BridgeEvent::Failed { height: 1, .. }
+        ));
+
+        bridge
+            .send(BridgeMessage::Finalized {
+                block: Box::new(block.clone()),
+                state_root: block.state_root,
+                signatures: vec![signature],
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            BridgeEvent::Anchored { height: 1, block_hash, .. } if block_hash == block.hash()
+        ));
+        assert_eq!(
+            bridge.ask(BridgeMessage::LastCheckpoint).await.unwrap(),
+            Some(1)
+        );
+    }
+}
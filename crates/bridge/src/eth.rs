@@ -0,0 +1,507 @@
+use crate::{Bridge, Config, Error, FinalizedBlock};
+use async_trait::async_trait;
+use chaoschain_core::bridge::{Deposit, WithdrawalClaim};
+use ethers::{
+    contract::{abigen, ContractCall, ContractError},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, MiddlewareError, PendingTransaction, Provider, RpcError},
+    signers::{LocalWallet, Signer},
+    types::{Address, BlockId, Bytes, H256},
+};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+abigen!(
+    ChaosBridgeContract,
+    r#"[
+        function stakeOf(address validator) external view returns (uint256)
+        function totalStake() external view returns (uint256)
+        function thresholdBps() external view returns (uint256)
+        function checkpointDigest(bytes32 blockHash, bytes32 stateRoot) external view returns (bytes32)
+        function latestRoot() external view returns (bytes32)
+        function isIncluded(bytes32 blockHash) external view returns (bool)
+        function postCheckpoint(bytes32 blockHash, bytes32 stateRoot, bytes[] signatures) external
+        function deposit(bytes32 recipient) external payable
+        function claimWithdrawal(bytes32 blockHash, uint64 id, bytes32 account, address recipient, uint64 amount, bytes32[] proof) external
+        event CheckpointPosted(bytes32 indexed blockHash, bytes32 stateRoot, uint256 signatures)
+        event Deposited(uint64 indexed id, address from, bytes32 recipient, uint64 amount)
+        event WithdrawalClaimed(uint64 indexed id, address recipient, uint64 amount)
+    ]"#
+);
+
+type Client = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+/// Bridge backed by the `ChaosBridge` contract over JSON-RPC
+pub struct EthBridge {
+    config: Config,
+    client: Arc<Client>,
+    contract: ChaosBridgeContract<Client>,
+    /// How many times a dropped checkpoint transaction is sent again
+    max_resubmits: u32,
+    /// How often pending transactions are polled
+    poll_interval: Duration,
+    /// How many times a send or receipt poll that never reached the node is tried again
+    max_retries: u32,
+    /// Pause before the first retry, doubled for every retry after it
+    retry_backoff: Duration,
+}
+
+impl EthBridge {
+    /// Connect to `config.eth_rpc`, sending checkpoints from `wallet`
+    pub async fn connect(config: Config, wallet: LocalWallet) -> Result<Self, Error> {
+        let provider = Provider::<Http>::try_from(config.eth_rpc.as_str())
+            .map_err(|e| Error::EthereumRPC(e.to_string()))?;
+        let chain_id = provider
+            .get_chainid()
+            .await
+            .map_err(|e| Error::EthereumRPC(e.to_string()))?;
+        let wallet = wallet.with_chain_id(chain_id.as_u64());
+        let client = Arc::new(SignerMiddleware::new(provider, wallet));
+        let contract = ChaosBridgeContract::new(config.bridge_address, client.clone());
+
+        Ok(Self {
+            config,
+            client,
+            contract,
+            max_resubmits: 3,
+            poll_interval: Duration::from_secs(2),
+            max_retries: 5,
+            retry_backoff: Duration::from_millis(500),
+        })
+    }
+
+    pub fn with_max_resubmits(mut self, max_resubmits: u32) -> Self {
+        self.max_resubmits = max_resubmits;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Send a checkpoint and wait for `required_confirmations`, re-sending it if dropped
+    pub async fn submit_checkpoint(&self, update: &FinalizedBlock) -> Result<H256, Error> {
+        let signatures: Vec<Bytes> = update
+            .signatures
+            .iter()
+            .map(|signature| Bytes::from(signature.to_vec()))
+            .collect();
+        let call = self
+            .contract
+            .post_checkpoint(update.block_hash, update.state_root, signatures);
+        let what = format!("checkpoint for block {}", hex::encode(update.block_hash));
+        self.send_confirmed(call, &what).await
+    }
+
+    /// Pay out a withdrawal against a posted checkpoint
+    pub async fn submit_claim(&self, claim: &WithdrawalClaim) -> Result<H256, Error> {
+        let withdrawal = &claim.withdrawal;
+        let call = self.contract.claim_withdrawal(
+            claim.checkpoint,
+            withdrawal.id,
+            withdrawal.account,
+            Address::from(withdrawal.l1_recipient),
+            withdrawal.amount,
+            claim.proof.clone(),
+        );
+        let what = format!("claim for withdrawal {}", withdrawal.id);
+        self.send_confirmed(call, &what).await
+    }
+
+    /// Send `call` and wait for `required_confirmations`, re-sending it if dropped
+    async fn send_confirmed(
+        &self,
+        call: ContractCall<Client, ()>,
+        what: &str,
+    ) -> Result<H256, Error> {
+        for attempt in 0..=self.max_resubmits {
+            let tx_hash = retry(self.max_retries, self.retry_backoff, what, || async {
+                call.send()
+                    .await
+                    .map(|pending| pending.tx_hash())
+                    .map_err(send_error)
+            })
+            .await?;
+            info!(
+                "Sent {} in tx {:?} (attempt {})",
+                what,
+                tx_hash,
+                attempt + 1
+            );
+
+            // A failed poll picks the transaction up again by hash
+            let receipt = retry(self.max_retries, self.retry_backoff, what, || async {
+                PendingTransaction::new(tx_hash, self.client.provider())
+                    .confirmations(self.config.required_confirmations as usize)
+                    .interval(self.poll_interval)
+                    .await
+                    .map_err(|e| Error::EthereumRPC(e.to_string()))
+            })
+            .await?;
+
+            match receipt {
+                Some(receipt) if receipt.status == Some(1u64.into()) => return Ok(tx_hash),
+                Some(_) => {
+                    return Err(Error::Contract(format!(
+                        "{} tx {:?} reverted",
+                        what, tx_hash
+                    )))
+                }
+                None => warn!("{} tx {:?} was dropped, re-submitting", what, tx_hash),
+            }
+        }
+
+        Err(Error::EthereumRPC(format!(
+            "{} dropped {} times",
+            what,
+            self.max_resubmits + 1
+        )))
+    }
+
+    /// Latest L1 block with at least `required_confirmations`, `None` if there is none yet
+    async fn finalized_block(&self) -> Result<Option<u64>, Error> {
+        let head = self
+            .client
+            .get_block_number()
+            .await
+            .map_err(|e| Error::EthereumRPC(e.to_string()))?
+            .as_u64();
+        let confirmations = self.config.required_confirmations.max(1);
+        Ok((head + 1).checked_sub(confirmations))
+    }
+
+    /// Deposits with enough confirmations and an id of at least `from_id`
+    pub async fn finalized_deposits(&self, from_id: u64) -> Result<Vec<Deposit>, Error> {
+        let Some(block) = self.finalized_block().await? else {
+            return Ok(Vec::new());
+        };
+        let events = self
+            .contract
+            .deposited_filter()
+            .from_block(0u64)
+            .to_block(block)
+            .query()
+            .await
+            .map_err(|e| Error::Contract(e.to_string()))?;
+
+        let mut deposits: Vec<Deposit> = events
+            .into_iter()
+            .filter(|event| event.id >= from_id)
+            .map(|event| Deposit {
+                id: event.id,
+                l1_sender: event.from.0,
+                recipient: event.recipient,
+                amount: event.amount,
+            })
+            .collect();
+        deposits.sort_by_key(|deposit| deposit.id);
+        Ok(deposits)
+    }
+
+    /// State root of the latest checkpoint with enough confirmations
+    pub async fn finalized_root(&self) -> Result<[u8; 32], Error> {
+        let Some(block) = self.finalized_block().await? else {
+            return Ok([0u8; 32]);
+        };
+        self.contract
+            .latest_root()
+            .block(BlockId::from(block))
+            .call()
+            .await
+            .map_err(|e| Error::Contract(e.to_string()))
+    }
+
+    /// Whether a checkpoint for `block_hash` has enough confirmations
+    pub async fn is_included(&self, block_hash: [u8; 32]) -> Result<bool, Error> {
+        let Some(block) = self.finalized_block().await? else {
+            return Ok(false);
+        };
+        self.contract
+            .is_included(block_hash)
+            .block(BlockId::from(block))
+            .call()
+            .await
+            .map_err(|e| Error::Contract(e.to_string()))
+    }
+}
+
+/// A send the node answered is final, e.g. a revert; anything else never reached it
+fn send_error(e: ContractError<Client>) -> Error {
+    let answered = match &e {
+        ContractError::MiddlewareError { e } => e.as_error_response().is_some(),
+        ContractError::ProviderError { e } => RpcError::as_error_response(e).is_some(),
+        _ => true,
+    };
+    if answered {
+        Error::Contract(e.to_string())
+    } else {
+        Error::EthereumRPC(e.to_string())
+    }
+}
+
+/// Run `attempt` until it succeeds or fails with anything but an RPC error, at most
+/// `max_retries` more times, doubling `backoff` after every retry
+async fn retry<T, F, Fut>(
+    max_retries: u32,
+    backoff: Duration,
+    what: &str,
+    mut attempt: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut delay = backoff;
+    let mut retries = 0;
+    loop {
+        match attempt().await {
+            Err(Error::EthereumRPC(e)) if retries < max_retries => {
+                warn!("RPC error on {}, retrying in {:?}: {}", what, delay, e);
+                tokio::time::sleep(delay).await;
+                delay = delay.saturating_mul(2);
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[async_trait]
+impl Bridge for EthBridge {
+    async fn post_update(&mut self, update: FinalizedBlock) -> Result<H256, Error> {
+        self.submit_checkpoint(&update).await
+    }
+
+    async fn latest_finalized_root(&self) -> Result<[u8; 32], Error> {
+        self.finalized_root().await
+    }
+
+    async fn verify_block_inclusion(&self, block_hash: [u8; 32]) -> Result<bool, Error> {
+        self.is_included(block_hash).await
+    }
+
+    async fn deposits(&self, from_id: u64) -> Result<Vec<Deposit>, Error> {
+        self.finalized_deposits(from_id).await
+    }
+
+    async fn claim_withdrawal(&mut self, claim: WithdrawalClaim) -> Result<H256, Error> {
+        self.submit_claim(&claim).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint_digest;
+    use chaoschain_core::bridge::{merkle_proof, merkle_root, Withdrawal};
+    use ethers::{
+        contract::ContractFactory,
+        solc::Solc,
+        types::U256,
+        utils::{parse_units, Anvil},
+    };
+    use std::cell::Cell;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn rpc_errors_are_retried_with_backoff_and_reverts_are_not() {
+        let attempts = Cell::new(0);
+        let flaky = retry(3, Duration::from_millis(1), "flaky", || async {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() < 3 {
+                Err(Error::EthereumRPC("connection reset".to_string()))
+            } else {
+                Ok(7)
+            }
+        });
+        assert_eq!(flaky.await.unwrap(), 7);
+        assert_eq!(attempts.get(), 3);
+
+        attempts.set(0);
+        let down = retry(2, Duration::from_millis(1), "down", || async {
+            attempts.set(attempts.get() + 1);
+            Err::<(), _>(Error::EthereumRPC("connection refused".to_string()))
+        });
+        assert!(matches!(down.await, Err(Error::EthereumRPC(_))));
+        assert_eq!(attempts.get(), 3);
+
+        attempts.set(0);
+        let reverted = retry(5, Duration::from_millis(1), "reverted", || async {
+            attempts.set(attempts.get() + 1);
+            Err::<(), _>(Error::Contract("already posted".to_string()))
+        });
+        assert!(matches!(reverted.await, Err(Error::Contract(_))));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    /// Needs `anvil` and `solc` on the PATH
+    #[tokio::test]
+    #[ignore = "requires anvil and solc"]
+    async fn test_checkpoints_on_local_chain() {
+        let anvil = Anvil::new().block_time(1u64).spawn();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let validators: Vec<LocalWallet> = anvil.keys()[1..4]
+            .iter()
+            .map(|key| key.clone().into())
+            .collect();
+
+        // Deploy the bridge contract from source
+        let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("contracts/ChaosBridge.sol");
+        let output = Solc::default().compile_source(source).unwrap();
+        let (abi, bytecode, _) = output
+            .find("ChaosBridge")
+            .expect("contract compiled")
+            .into_parts_or_default();
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let deployer = Arc::new(SignerMiddleware::new(
+            provider,
+            wallet.clone().with_chain_id(anvil.chain_id()),
+        ));
+        let addresses: Vec<Address> = validators.iter().map(|v| v.address()).collect();
+        let stakes = vec![U256::from(100u64); validators.len()];
+        let contract = ContractFactory::new(abi, bytecode, deployer)
+            .deploy((addresses, stakes, U256::from(6_700u64)))
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        let config = Config {
+            eth_rpc: anvil.endpoint(),
+            bridge_address: contract.address(),
+            required_confirmations: 2,
+        };
+        let mut bridge = EthBridge::connect(config, wallet)
+            .await
+            .unwrap()
+            .with_poll_interval(Duration::from_millis(200));
+
+        // A deposit shows up once it has enough confirmations
+        let recipient = [5u8; 32];
+        let gwei: U256 = parse_units(1, "gwei").unwrap().into();
+        let deposit = bridge.contract.deposit(recipient).value(gwei * 500);
+        bridge.send_confirmed(deposit, "deposit").await.unwrap();
+        let deposits = bridge.deposits(0).await.unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(
+            (deposits[0].id, deposits[0].recipient, deposits[0].amount),
+            (0, recipient, 500)
+        );
+        assert!(bridge.deposits(1).await.unwrap().is_empty());
+
+        // Checkpoint a withdrawal tree holding two withdrawals
+        let l1_recipient = Address::repeat_byte(2);
+        let withdrawals: Vec<Withdrawal> = [250, 100]
+            .into_iter()
+            .enumerate()
+            .map(|(id, amount)| Withdrawal {
+                id: id as u64,
+                account: recipient,
+                l1_recipient: l1_recipient.0,
+                amount,
+                height: 3,
+            })
+            .collect();
+        let leaves: Vec<[u8; 32]> = withdrawals.iter().map(Withdrawal::leaf).collect();
+        let block_hash = [7u8; 32];
+        let state_root = merkle_root(&leaves);
+        let digest = checkpoint_digest(bridge.config.bridge_address, &block_hash, &state_root);
+        assert_eq!(
+            bridge
+                .contract
+                .checkpoint_digest(block_hash, state_root)
+                .call()
+                .await
+                .unwrap(),
+            digest.0
+        );
+        let sign = |signers: &[LocalWallet]| {
+            signers
+                .iter()
+                .map(|signer| signer.sign_hash(digest).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // One validator's stake, even signing twice, is short of the threshold
+        let short = FinalizedBlock {
+            block_hash,
+            state_root,
+            signatures: sign(&[validators[0].clone(), validators[0].clone()]),
+        };
+        assert!(matches!(
+            bridge.post_update(short).await,
+            Err(Error::Contract(_))
+        ));
+
+        // Signatures over the real root do not carry over to a forged one
+        let forged = FinalizedBlock {
+            block_hash,
+            state_root: [9u8; 32],
+            signatures: sign(&validators),
+        };
+        assert!(matches!(
+            bridge.post_update(forged).await,
+            Err(Error::Contract(_))
+        ));
+
+        let update = FinalizedBlock {
+            block_hash,
+            state_root,
+            signatures: sign(&validators[..2]),
+        };
+        bridge.post_update(update.clone()).await.unwrap();
+        assert!(bridge
+            .verify_block_inclusion(update.block_hash)
+            .await
+            .unwrap());
+        assert_eq!(
+            bridge.latest_finalized_root().await.unwrap(),
+            update.state_root
+        );
+        assert!(!bridge.verify_block_inclusion([8u8; 32]).await.unwrap());
+
+        // The contract refuses a second checkpoint for the same block
+        assert!(matches!(
+            bridge.post_update(update.clone()).await,
+            Err(Error::Contract(_))
+        ));
+
+        // The first withdrawal pays out once, and a forged amount fails its proof
+        let claim = WithdrawalClaim {
+            checkpoint: update.block_hash,
+            withdrawal: withdrawals[0].clone(),
+            proof: merkle_proof(&leaves, 0),
+        };
+        let before = bridge.client.get_balance(l1_recipient, None).await.unwrap();
+        bridge.claim_withdrawal(claim.clone()).await.unwrap();
+        let after = bridge.client.get_balance(l1_recipient, None).await.unwrap();
+        assert_eq!(after - before, gwei * 250);
+        assert!(matches!(
+            bridge.claim_withdrawal(claim.clone()).await,
+            Err(Error::Contract(_))
+        ));
+        let mut forged = claim;
+        forged.withdrawal = Withdrawal {
+            amount: 1_000,
+            ..withdrawals[1].clone()
+        };
+        forged.proof = merkle_proof(&leaves, 1);
+        assert!(matches!(
+            bridge.claim_withdrawal(forged).await,
+            Err(Error::Contract(_))
+        ));
+    }
+}
@@ -1,14 +1,24 @@
-use chaoschain_core::{Block, Error as CoreError};
+use async_trait::async_trait;
+use chaoschain_core::{
+    bridge::{Deposit, WithdrawalClaim},
+    Block, Error as CoreError,
+};
 use chaoschain_state::StateStore;
 use ethers::{
     providers::Provider,
-    types::{Address, H256},
+    types::{Address, Signature, H256},
+    utils::keccak256,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::{hex::Hex, serde_as};
 use thiserror::Error;
 use tracing::info;
 
+pub mod actor;
+pub mod eth;
+pub mod mock;
+pub mod service;
+
 /// Bridge configuration
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -30,9 +40,25 @@ pub struct FinalizedBlock {
     /// New state root
     #[serde_as(as = "Hex")]
     pub state_root: [u8; 32],
-    /// Aggregated signatures from agents
-    #[serde_as(as = "Vec<Hex>")]
-    pub signatures: Vec<[u8; 64]>,
+    /// Validator signatures over `checkpoint_digest(bridge_address, block_hash, state_root)`
+    pub signatures: Vec<Signature>,
+}
+
+/// What validators sign to attest that `state_root` is the state after `block_hash`
+///
+/// Block votes are ed25519 over the block hash alone, which the L1 contract cannot check,
+/// so validators sign checkpoints with secp256k1 keys the contract recovers with
+/// `ecrecover`. Matches `ChaosBridge.checkpointDigest`; the bridge address keeps a
+/// checkpoint for one deployment from being posted to another.
+pub fn checkpoint_digest(
+    bridge_address: Address,
+    block_hash: &[u8; 32],
+    state_root: &[u8; 32],
+) -> H256 {
+    let mut preimage = bridge_address.as_bytes().to_vec();
+    preimage.extend_from_slice(block_hash);
+    preimage.extend_from_slice(state_root);
+    H256::from(keccak256(preimage))
 }
 
 /// Bridge errors
@@ -49,13 +75,20 @@ pub enum Error {
 }
 
 /// Bridge interface for L1 communication
-pub trait Bridge {
+#[async_trait]
+pub trait Bridge: Send + Sync {
     /// Post a state update to L1
-    fn post_update(&mut self, update: FinalizedBlock) -> Result<H256, Error>;
+    async fn post_update(&mut self, update: FinalizedBlock) -> Result<H256, Error>;
 
     /// Get latest finalized state root from L1
-    fn latest_finalized_root(&self) -> Result<[u8; 32], Error>;
+    async fn latest_finalized_root(&self) -> Result<[u8; 32], Error>;
 
     /// Check if a block hash exists on L1
-    fn verify_block_inclusion(&self, block_hash: [u8; 32]) -> Result<bool, Error>;
+    async fn verify_block_inclusion(&self, block_hash: [u8; 32]) -> Result<bool, Error>;
+
+    /// Finalized L1 deposits with an id of at least `from_id`, in id order
+    async fn deposits(&self, from_id: u64) -> Result<Vec<Deposit>, Error>;
+
+    /// Pay out a withdrawal on L1 against a posted checkpoint
+    async fn claim_withdrawal(&mut self, claim: WithdrawalClaim) -> Result<H256, Error>;
 }
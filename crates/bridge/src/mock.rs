@@ -0,0 +1,250 @@
+use crate::{checkpoint_digest, Bridge, Config, Error, FinalizedBlock};
+use async_trait::async_trait;
+use chaoschain_core::bridge::{verify_merkle_proof, Deposit, WithdrawalClaim};
+use ethers::{
+    types::{Address, H256},
+    utils::keccak256,
+};
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+/// An update accepted by the mock L1
+#[derive(Debug, Clone)]
+pub struct PostedUpdate {
+    /// Hash of the L1 transaction that carried the update
+    pub tx_hash: H256,
+    /// L1 block the update was included in
+    pub l1_block: u64,
+    pub update: FinalizedBlock,
+}
+
+/// In-process stand-in for the L1 bridge contract
+///
+/// Every accepted update is mined into its own L1 block. An update counts as
+/// finalized once `Config::required_confirmations` L1 blocks include or follow it;
+/// call `mine` to advance the L1 head.
+#[derive(Debug)]
+pub struct MockL1 {
+    config: Config,
+    /// Registered validator signing addresses and their stake
+    validators: Vec<(Address, u64)>,
+    /// Required stake share of valid signatures
+    finality_threshold: f64,
+    /// Current L1 block number
+    head: u64,
+    updates: Vec<PostedUpdate>,
+    /// Deposits with the L1 block they were made in
+    deposits: Vec<(u64, Deposit)>,
+    /// Ids of withdrawals already paid out
+    claimed: HashSet<u64>,
+    /// Value paid out to L1 accounts
+    l1_balances: HashMap<Address, u64>,
+}
+
+impl MockL1 {
+    pub fn new(config: Config, finality_threshold: f64) -> Self {
+        Self {
+            config,
+            validators: Vec::new(),
+            finality_threshold,
+            head: 0,
+            updates: Vec::new(),
+            deposits: Vec::new(),
+            claimed: HashSet::new(),
+            l1_balances: HashMap::new(),
+        }
+    }
+
+    /// Register a validator whose signatures the contract accepts by signing address
+    pub fn register_validator(&mut self, signer: Address, stake: u64) {
+        match self
+            .validators
+            .iter_mut()
+            .find(|(known, _)| *known == signer)
+        {
+            Some((_, known_stake)) => *known_stake = stake,
+            None => self.validators.push((signer, stake)),
+        }
+    }
+
+    /// Advance the L1 head by `blocks` empty blocks
+    pub fn mine(&mut self, blocks: u64) {
+        self.head += blocks;
+    }
+
+    /// Current L1 block number
+    pub fn head(&self) -> u64 {
+        self.head
+    }
+
+    /// Every accepted update, oldest first
+    pub fn updates(&self) -> &[PostedUpdate] {
+        &self.updates
+    }
+
+    /// Lock `amount` for `recipient` on ChaosChain, returning the deposit id
+    pub fn deposit(&mut self, l1_sender: Address, recipient: [u8; 32], amount: u64) -> u64 {
+        self.head += 1;
+        let id = self.deposits.len() as u64;
+        self.deposits.push((
+            self.head,
+            Deposit {
+                id,
+                l1_sender: l1_sender.0,
+                recipient,
+                amount,
+            },
+        ));
+        id
+    }
+
+    /// Value paid out to `account` by withdrawal claims
+    pub fn l1_balance(&self, account: Address) -> u64 {
+        self.l1_balances.get(&account).copied().unwrap_or(0)
+    }
+
+    /// Number of L1 blocks that include or follow `update`
+    pub fn confirmations(&self, update: &PostedUpdate) -> u64 {
+        self.confirmations_since(update.l1_block)
+    }
+
+    fn confirmations_since(&self, l1_block: u64) -> u64 {
+        self.head.saturating_sub(l1_block) + 1
+    }
+
+    fn is_final(&self, update: &PostedUpdate) -> bool {
+        self.confirmations(update) >= self.config.required_confirmations
+    }
+
+    /// Stake of the distinct registered validators that signed both the block hash and the
+    /// state root of `update`
+    fn signed_stake(&self, update: &FinalizedBlock) -> u64 {
+        let digest = checkpoint_digest(
+            self.config.bridge_address,
+            &update.block_hash,
+            &update.state_root,
+        );
+
+        let mut signers = HashSet::new();
+        let mut stake = 0u64;
+        for signature in &update.signatures {
+            let Ok(signer) = signature.recover(digest) else {
+                continue;
+            };
+            if !signers.insert(signer) {
+                continue;
+            }
+            if let Some((_, validator_stake)) =
+                self.validators.iter().find(|(known, _)| *known == signer)
+            {
+                stake = stake.saturating_add(*validator_stake);
+            }
+        }
+        stake
+    }
+}
+
+#[async_trait]
+impl Bridge for MockL1 {
+    async fn post_update(&mut self, update: FinalizedBlock) -> Result<H256, Error> {
+        if self
+            .updates
+            .iter()
+            .any(|posted| posted.update.block_hash == update.block_hash)
+        {
+            return Err(Error::Contract("block already posted".to_string()));
+        }
+
+        let total_stake: u64 = self.validators.iter().map(|(_, stake)| stake).sum();
+        if total_stake == 0 {
+            return Err(Error::Contract("no registered validators".to_string()));
+        }
+        let threshold_stake = (total_stake as f64 * self.finality_threshold).ceil() as u64;
+        let signed_stake = self.signed_stake(&update);
+        if signed_stake < threshold_stake {
+            return Err(Error::Contract(format!(
+                "insufficient signatures: {} of {} stake required",
+                signed_stake, threshold_stake
+            )));
+        }
+
+        self.head += 1;
+        let mut preimage = update.block_hash.to_vec();
+        preimage.extend_from_slice(&self.head.to_be_bytes());
+        let tx_hash = H256::from(keccak256(preimage));
+
+        info!(
+            "Mock L1 accepted block {} in L1 block {}",
+            hex::encode(update.block_hash),
+            self.head
+        );
+        self.updates.push(PostedUpdate {
+            tx_hash,
+            l1_block: self.head,
+            update,
+        });
+
+        Ok(tx_hash)
+    }
+
+    async fn latest_finalized_root(&self) -> Result<[u8; 32], Error> {
+        Ok(self
+            .updates
+            .iter()
+            .rev()
+            .find(|posted| self.is_final(posted))
+            .map(|posted| posted.update.state_root)
+            .unwrap_or([0u8; 32]))
+    }
+
+    async fn verify_block_inclusion(&self, block_hash: [u8; 32]) -> Result<bool, Error> {
+        Ok(self
+            .updates
+            .iter()
+            .any(|posted| posted.update.block_hash == block_hash && self.is_final(posted)))
+    }
+
+    async fn deposits(&self, from_id: u64) -> Result<Vec<Deposit>, Error> {
+        Ok(self
+            .deposits
+            .iter()
+            .filter(|(l1_block, deposit)| {
+                deposit.id >= from_id
+                    && self.confirmations_since(*l1_block) >= self.config.required_confirmations
+            })
+            .map(|(_, deposit)| deposit.clone())
+            .collect())
+    }
+
+    async fn claim_withdrawal(&mut self, claim: WithdrawalClaim) -> Result<H256, Error> {
+        let checkpoint = self
+            .updates
+            .iter()
+            .find(|posted| posted.update.block_hash == claim.checkpoint && self.is_final(posted))
+            .ok_or_else(|| Error::Contract("unknown or unconfirmed checkpoint".to_string()))?;
+
+        let withdrawal = &claim.withdrawal;
+        if self.claimed.contains(&withdrawal.id) {
+            return Err(Error::Contract("withdrawal already claimed".to_string()));
+        }
+        if !verify_merkle_proof(
+            withdrawal.leaf(),
+            withdrawal.id,
+            &claim.proof,
+            checkpoint.update.state_root,
+        ) {
+            return Err(Error::Contract("invalid withdrawal proof".to_string()));
+        }
+
+        self.head += 1;
+        self.claimed.insert(withdrawal.id);
+        *self
+            .l1_balances
+            .entry(Address::from(withdrawal.l1_recipient))
+            .or_default() += withdrawal.amount;
+
+        let mut preimage = withdrawal.leaf().to_vec();
+        preimage.extend_from_slice(&self.head.to_be_bytes());
+        Ok(H256::from(keccak256(preimage)))
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{Bridge, Error, FinalizedBlock};
+use chaoschain_core::Block;
+use ethers::types::{Signature, H256};
+use tracing::{info, warn};
+
+/// Anchors the chain on L1 by posting every `interval`-th finalized block
+#[derive(Debug)]
+pub struct CheckpointService<B: Bridge> {
+    bridge: B,
+    /// Number of finalized blocks between checkpoints
+    interval: u64,
+    /// Finalized blocks seen since the last checkpoint
+    since_checkpoint: u64,
+    /// Height of the last block posted to L1
+    last_checkpoint: Option<u64>,
+}
+
+impl<B: Bridge> CheckpointService<B> {
+    pub fn new(bridge: B, interval: u64) -> Self {
+        Self {
+            bridge,
+            interval: interval.max(1),
+            since_checkpoint: 0,
+            last_checkpoint: None,
+        }
+    }
+
+    /// Record a finalized block with the state root after it and the validators'
+    /// signatures over both, see `checkpoint_digest`
+    ///
+    /// Returns the L1 transaction hash when the block was posted as a checkpoint.
+    /// A failed post is retried with the next finalized block.
+    pub async fn on_finalized(
+        &mut self,
+        block: &Block,
+        state_root: [u8; 32],
+        signatures: Vec<Signature>,
+    ) -> Result<Option<H256>, Error> {
+        self.since_checkpoint += 1;
+        if self.since_checkpoint < self.interval {
+            return Ok(None);
+        }
+
+        let update = FinalizedBlock {
+            block_hash: block.hash(),
+            state_root,
+            signatures,
+        };
+        match self.bridge.post_update(update).await {
+            Ok(tx_hash) => {
+                info!("Posted checkpoint for block {} to L1", block.height);
+                self.since_checkpoint = 0;
+                self.last_checkpoint = Some(block.height);
+                Ok(Some(tx_hash))
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to post checkpoint for block {}: {}",
+                    block.height, e
+                );
+                Err(e)
+            }
+        }
+    }
+
+    /// Height of the last block posted to L1
+    pub fn last_checkpoint(&self) -> Option<u64> {
+        self.last_checkpoint
+    }
+
+    pub fn bridge(&self) -> &B {
+        &self.bridge
+    }
+
+    pub fn bridge_mut(&mut self) -> &mut B {
+        &mut self.bridge
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{checkpoint_digest, mock::MockL1, Config};
+    use chaoschain_core::{
+        bridge::{BridgeAction, Deposit},
+        ChainConfig, Transaction,
+    };
+    use chaoschain_state::{StateStore, StateStoreImpl};
+    use ed25519_dalek::SigningKey;
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::Address,
+    };
+    use rand::rngs::OsRng;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn block(height: u64) -> Block {
+        Block {
+            parent_hash: [0u8; 32],
+            height,
+            transactions: Vec::new(),
+            state_root: [height as u8; 32],
+            proposer_sig: [0u8; 64],
+            message: format!("block {}", height),
+            producer_id: "producer".to_string(),
+            votes: HashMap::new(),
+            timestamp: 0,
+            discussion: Vec::new(),
+            meme: None,
+            style: None,
+            certificate: BTreeMap::new(),
+        }
+    }
+
+    fn wallets(count: usize) -> Vec<LocalWallet> {
+        (0..count).map(|_| LocalWallet::new(&mut OsRng)).collect()
+    }
+
+    fn bridge_address() -> Address {
+        Address::repeat_byte(0xb1)
+    }
+
+    fn sign(keys: &[LocalWallet], block: &Block) -> Vec<Signature> {
+        sign_root(keys, block, block.state_root)
+    }
+
+    fn sign_root(keys: &[LocalWallet], block: &Block, state_root: [u8; 32]) -> Vec<Signature> {
+        let digest = checkpoint_digest(bridge_address(), &block.hash(), &state_root);
+        keys.iter()
+            .map(|key| key.sign_hash(digest).unwrap())
+            .collect()
+    }
+
+    fn mock_l1(keys: &[LocalWallet], required_confirmations: u64) -> MockL1 {
+        let config = Config {
+            eth_rpc: String::new(),
+            bridge_address: bridge_address(),
+            required_confirmations,
+        };
+        let mut l1 = MockL1::new(config, 0.67);
+        for key in keys {
+            l1.register_validator(key.address(), 100);
+        }
+        l1
+    }
+
+    #[tokio::test]
+    async fn test_posts_every_interval_and_waits_for_confirmations() {
+        let keys = wallets(3);
+        let mut service = CheckpointService::new(mock_l1(&keys, 3), 2);
+
+        let first = block(1);
+        assert_eq!(
+            service
+                .on_finalized(&first, first.state_root, sign(&keys, &first))
+                .await
+                .unwrap(),
+            None
+        );
+        let second = block(2);
+        assert!(service
+            .on_finalized(&second, second.state_root, sign(&keys, &second))
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(service.last_checkpoint(), Some(2));
+
+        // Included but not yet confirmed
+        let l1 = service.bridge_mut();
+        assert!(!l1.verify_block_inclusion(second.hash()).await.unwrap());
+        assert_eq!(l1.latest_finalized_root().await.unwrap(), [0u8; 32]);
+
+        l1.mine(2);
+        assert!(l1.verify_block_inclusion(second.hash()).await.unwrap());
+        assert!(!l1.verify_block_inclusion(first.hash()).await.unwrap());
+        assert_eq!(l1.latest_finalized_root().await.unwrap(), second.state_root);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_updates_without_quorum() {
+        let keys = wallets(3);
+        let mut l1 = mock_l1(&keys, 1);
+        let block = block(1);
+
+        // A duplicated signature and an unregistered key do not count towards 2/3
+        let mut signatures = sign(&keys[..1], &block);
+        signatures.extend(sign(&keys[..1], &block));
+        signatures.extend(sign(&wallets(1), &block));
+        let update = FinalizedBlock {
+            block_hash: block.hash(),
+            state_root: block.state_root,
+            signatures,
+        };
+        assert!(matches!(
+            l1.post_update(update).await,
+            Err(Error::Contract(_))
+        ));
+        assert!(l1.updates().is_empty());
+
+        let update = FinalizedBlock {
+            block_hash: block.hash(),
+            state_root: block.state_root,
+            signatures: sign(&keys, &block),
+        };
+        l1.post_update(update.clone()).await.unwrap();
+        assert!(l1.verify_block_inclusion(block.hash()).await.unwrap());
+        assert!(matches!(
+            l1.post_update(update).await,
+            Err(Error::Contract(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_roots_the_validators_did_not_sign() {
+        let keys = wallets(3);
+        let mut l1 = mock_l1(&keys, 1);
+        let block = block(1);
+
+        // Signatures over the real root do not carry over to a forged one
+        let forged = FinalizedBlock {
+            block_hash: block.hash(),
+            state_root: [9u8; 32],
+            signatures: sign(&keys, &block),
+        };
+        assert!(matches!(
+            l1.post_update(forged).await,
+            Err(Error::Contract(_))
+        ));
+        assert!(l1.updates().is_empty());
+
+        let attested = FinalizedBlock {
+            block_hash: block.hash(),
+            state_root: [9u8; 32],
+            signatures: sign_root(&keys, &block, [9u8; 32]),
+        };
+        l1.post_update(attested).await.unwrap();
+        assert_eq!(l1.latest_finalized_root().await.unwrap(), [9u8; 32]);
+    }
+
+    /// Apply a block carrying `transactions` on top of the store's tip
+    fn extend(store: &StateStoreImpl, transactions: Vec<Transaction>) -> Block {
+        let height = store.get_block_height();
+        let block = Block {
+            parent_hash: store
+                .get_latest_block()
+                .map(|b| b.hash())
+                .unwrap_or([0u8; 32]),
+            transactions,
+            state_root: store.state_root(),
+            ..block(height)
+        };
+        store.apply_block(&block).unwrap();
+        block
+    }
+
+    #[tokio::test]
+    async fn test_deposit_and_withdrawal_round_trip() {
+        let validators = wallets(3);
+        let mut service = CheckpointService::new(mock_l1(&validators, 2), 1);
+
+        let store = StateStoreImpl::new(ChainConfig::default());
+        extend(&store, vec![]);
+        let relayer = SigningKey::generate(&mut OsRng);
+        store.set_bridge_relayer(&relayer.verifying_key());
+        let alice = SigningKey::generate(&mut OsRng);
+
+        // Two deposits on L1, both final once one more L1 block is mined
+        let l1_sender = Address::repeat_byte(1);
+        let alice_key = alice.verifying_key().to_bytes();
+        service.bridge_mut().deposit(l1_sender, alice_key, 500);
+        service.bridge_mut().deposit(l1_sender, alice_key, 100);
+        assert_eq!(service.bridge().deposits(0).await.unwrap().len(), 1);
+        service.bridge_mut().mine(1);
+
+        // Relayed out of order, only the deposit the chain expects next is minted
+        let deposits = service
+            .bridge()
+            .deposits(store.next_deposit_id())
+            .await
+            .unwrap();
+        let relay = |deposits: &[Deposit], nonce: u64| {
+            deposits
+                .iter()
+                .map(|deposit| {
+                    Transaction::new_signed(
+                        &relayer,
+                        nonce + deposit.id,
+                        BridgeAction::Deposit(deposit.clone()).encode(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut reversed = deposits.clone();
+        reversed.reverse();
+        extend(&store, relay(&reversed, 0));
+        assert_eq!(store.get_balance(&alice.verifying_key()), 500);
+
+        // Replays are dropped and the missing deposit is minted
+        let deposits = service.bridge().deposits(0).await.unwrap();
+        extend(&store, relay(&deposits, 2));
+        assert_eq!(store.get_balance(&alice.verifying_key()), 600);
+        assert_eq!(store.next_deposit_id(), 2);
+
+        // Alice burns 250 for an L1 address; overdrawing is ignored
+        let l1_recipient = Address::repeat_byte(2);
+        let withdraw = |amount| BridgeAction::Withdraw {
+            amount,
+            l1_recipient: l1_recipient.0,
+        };
+        let block = extend(
+            &store,
+            vec![
+                Transaction::new_signed(&alice, 0, withdraw(250).encode()),
+                Transaction::new_signed(&alice, 1, withdraw(1_000).encode()),
+            ],
+        );
+        assert_eq!(store.get_balance(&alice.verifying_key()), 350);
+
+        // Checkpoint the block and claim once it is final on L1
+        let root = store.withdrawal_root(block.height);
+        service
+            .on_finalized(&block, root, sign_root(&validators, &block, root))
+            .await
+            .unwrap();
+        let claim = store.withdrawal_claim(0, &block).unwrap();
+        assert!(service
+            .bridge_mut()
+            .claim_withdrawal(claim.clone())
+            .await
+            .is_err());
+        service.bridge_mut().mine(1);
+        service
+            .bridge_mut()
+            .claim_withdrawal(claim.clone())
+            .await
+            .unwrap();
+        assert_eq!(service.bridge().l1_balance(l1_recipient), 250);
+
+        // A withdrawal can be claimed only once, and only with a valid proof
+        assert!(service
+            .bridge_mut()
+            .claim_withdrawal(claim.clone())
+            .await
+            .is_err());
+        let mut forged = claim;
+        forged.withdrawal.id = 1;
+        forged.withdrawal.amount = 1_000;
+        assert!(service.bridge_mut().claim_withdrawal(forged).await.is_err());
+    }
+}
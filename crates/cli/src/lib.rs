@@ -48,6 +48,64 @@ pub enum Commands {
         /// Whether to run the web interface
         #[arg(long)]
         web: bool,
+
+        /// Directory where agents keep their memories across restarts
+        #[arg(long, default_value = "data")]
+        data_dir: String,
+
+        /// Seconds validators discuss each block before voting, 0 to vote right away
+        #[arg(long, default_value_t = 0)]
+        discussion_secs: u64,
+
+        /// Record every LLM call to llm-journal.jsonl in the data directory
+        #[arg(long)]
+        record_llm: bool,
+
+        /// Answer LLM calls from a recorded journal instead of the model
+        #[arg(long, value_name = "FILE")]
+        replay_llm: Option<String>,
+
+        /// Continue the chain in an archive written by `chain export` instead of a new genesis
+        #[arg(long, value_name = "FILE")]
+        import: Option<String>,
+
+        /// Start from the latest snapshot of the node at this web API URL, then sync the blocks
+        /// after it
+        #[arg(
+            long,
+            value_name = "URL",
+            conflicts_with = "import",
+            requires = "trusted_validators"
+        )]
+        bootstrap: Option<String>,
+
+        /// Validator set whose certificate the snapshot must carry, as JSON from
+        /// `GET /api/validators?height=N` on a node you trust
+        #[arg(long, value_name = "FILE", requires = "bootstrap")]
+        trusted_validators: Option<String>,
+
+        /// Blocks between state snapshots, 0 to take none
+        #[arg(long, default_value_t = 10)]
+        snapshot_interval: u64,
+
+        /// Keep only the last N blocks and the snapshots among them, plus every block header,
+        /// instead of the whole chain
+        #[arg(long, value_name = "N")]
+        prune: Option<usize>,
+
+        /// Checkpoint finalized blocks on an in-process mock L1 bridge
+        #[arg(long)]
+        bridge_mock: bool,
+
+        /// Finalized blocks between L1 checkpoints
+        #[arg(long, default_value_t = 10, requires = "bridge_mock")]
+        checkpoint_interval: u64,
+    },
+
+    /// Archive or audit a chain
+    Chain {
+        #[command(subcommand)]
+        command: ChainCommands,
     },
 
     /// Start a node
@@ -61,3 +119,23 @@ pub enum Commands {
         web: bool,
     },
 }
+
+#[derive(Subcommand, Clone)]
+pub enum ChainCommands {
+    /// Download the full block history of a running node
+    Export {
+        /// Web API of the node
+        #[arg(long, default_value = "http://localhost:3000")]
+        node: String,
+
+        /// File to write the archive to
+        #[arg(short, long, value_name = "FILE")]
+        output: String,
+    },
+
+    /// Replay an archive on a fresh state and report the first block that diverges
+    Verify {
+        /// Archive written by `chain export`
+        file: String,
+    },
+}
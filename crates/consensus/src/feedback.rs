@@ -0,0 +1,91 @@
+use crate::Vote;
+use chaoschain_core::Block;
+use serde::{Deserialize, Serialize};
+
+/// Rounds kept for a producer that has not collected its feedback yet
+pub const MAX_PENDING_FEEDBACK: usize = 20;
+
+/// How a voting round ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundOutcome {
+    Approved,
+    Rejected,
+    /// The round was abandoned without reaching the finality threshold
+    TimedOut,
+}
+
+/// One validator's vote and reasoning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoteFeedback {
+    pub validator: String,
+    pub approve: bool,
+    pub reason: String,
+}
+
+/// What validators made of a producer's block, delivered to the producer when the round ends
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundFeedback {
+    pub producer: String,
+    pub height: u64,
+    pub message: String,
+    pub outcome: RoundOutcome,
+    /// Votes cast before the round ended, by validator id
+    pub votes: Vec<VoteFeedback>,
+}
+
+impl RoundFeedback {
+    /// Feedback on `block` from the votes of its round
+    pub fn new<'a>(
+        block: &Block,
+        outcome: RoundOutcome,
+        votes: impl IntoIterator<Item = &'a Vote>,
+    ) -> Self {
+        let mut votes: Vec<VoteFeedback> = votes
+            .into_iter()
+            .map(|vote| VoteFeedback {
+                validator: vote.agent_id.clone(),
+                approve: vote.approve,
+                reason: vote.reason.clone(),
+            })
+            .collect();
+        votes.sort_by(|a, b| a.validator.cmp(&b.validator));
+        Self {
+            producer: block.producer_id.clone(),
+            height: block.height,
+            message: block.message.clone(),
+            outcome,
+            votes,
+        }
+    }
+
+    /// Reasons of the validators that voted against the block
+    pub fn rejections(&self) -> impl Iterator<Item = &VoteFeedback> {
+        self.votes.iter().filter(|vote| !vote.approve)
+    }
+
+    /// The round phrased for a prompt
+    pub fn describe(&self) -> String {
+        let outcome = match self.outcome {
+            RoundOutcome::Approved => "was approved",
+            RoundOutcome::Rejected => "was rejected",
+            RoundOutcome::TimedOut => "timed out",
+        };
+        let mut text = format!(
+            "Your block {} \"{}\" {}.",
+            self.height, self.message, outcome
+        );
+        if self.votes.is_empty() {
+            text.push_str(" Nobody voted.");
+        }
+        for vote in &self.votes {
+            text.push_str(&format!(
+                "\n- {} {}: {}",
+                vote.validator,
+                if vote.approve { "approved" } else { "rejected" },
+                vote.reason
+            ));
+        }
+        text
+    }
+}
@@ -3,17 +3,21 @@ use async_openai::{
     types::{ChatCompletionRequestMessage, Role},
     Client,
 };
-use chaoschain_core::{Block, Error as CoreError, Transaction};
+use chaoschain_core::{memes::MemeHash, Block, Error as CoreError, Transaction};
 use chaoschain_p2p::{AgentMessage, Message as P2PMessage};
+use chaoschain_state::StateStoreImpl;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serde_with::{hex::Hex, serde_as};
+use std::sync::Arc;
 use thiserror::Error;
 use tracing::{debug, info, warn};
 
 mod manager;
 pub use manager::ConsensusManager;
 
+pub mod feedback;
+pub mod memory;
 pub mod validator;
 
 /// Agent personality types
@@ -27,7 +31,7 @@ pub enum AgentPersonality {
     Chaotic,
     /// Only cares about memes
     Memetic,
-    /// Easily bribed with virtual cookies
+    /// Easily bribed with escrowed tokens
     Greedy,
     Dramatic,
     Rational,
@@ -63,8 +67,6 @@ pub struct Agent {
     pub mood: String,
     /// Agent's stake in the system
     pub stake: u64,
-    /// History of decisions
-    pub decision_history: Vec<String>,
 }
 
 impl Agent {
@@ -74,7 +76,6 @@ impl Agent {
             personality,
             mood: String::new(),
             stake: 100, // Default stake value
-            decision_history: Vec::new(),
         }
     }
 }
@@ -88,6 +89,8 @@ pub struct Config {
     pub openai_api_key: String,
     /// Maximum time to wait for consensus
     pub consensus_timeout: std::time::Duration,
+    /// Time validators discuss a block before voting, zero to skip the discussion
+    pub discussion_period: std::time::Duration,
 }
 
 impl Default for Config {
@@ -96,6 +99,7 @@ impl Default for Config {
             finality_threshold: 0.67, // 2/3 majority
             openai_api_key: String::new(),
             consensus_timeout: std::time::Duration::from_secs(30),
+            discussion_period: std::time::Duration::ZERO,
         }
     }
 }
@@ -107,6 +111,10 @@ pub enum Error {
     InsufficientStake,
     #[error("Consensus timeout")]
     Timeout,
+    #[error("Voting has not opened yet, the block is still being discussed")]
+    DiscussionOpen,
+    #[error("Equivocation by {0}")]
+    Equivocation(String),
     #[error("Agent error: {0}")]
     Agent(String),
     #[error(transparent)]
@@ -128,14 +136,19 @@ pub struct Vote {
     pub approve: bool,
     /// Reason for the vote
     pub reason: String,
-    /// Optional meme URL
-    pub meme_url: Option<String>,
+    /// Meme attached to the vote
+    pub meme: Option<MemeHash>,
     /// Agent's signature
     #[serde_as(as = "[_; 64]")]
     pub signature: [u8; 64],
 }
 
-/// Create a new consensus manager with the given configuration
-pub fn create_consensus_manager(total_stake: u64, config: Config) -> ConsensusManager {
-    ConsensusManager::new(total_stake, config.finality_threshold)
+/// Create a new consensus manager over `state` with the given configuration
+pub fn create_consensus_manager(state: Arc<StateStoreImpl>, config: Config) -> ConsensusManager {
+    ConsensusManager::new(
+        state,
+        config.finality_threshold,
+        config.consensus_timeout,
+    )
+    .with_discussion_period(config.discussion_period)
 }
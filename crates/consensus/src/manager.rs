@@ -1,11 +1,24 @@
+use crate::feedback::{RoundFeedback, RoundOutcome, MAX_PENDING_FEEDBACK};
 use crate::{Error, Vote};
-use chaoschain_core::Block;
+use chaoschain_core::{
+    discussion::{Comment, CommentKind, MAX_POSTS_PER_AGENT},
+    metrics,
+    slashing::Evidence,
+    Block,
+};
+use chaoschain_state::StateStoreImpl;
+use ed25519_dalek::VerifyingKey;
 use hex;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::time::Instant;
 use tracing::{debug, error, info, warn};
 
+/// Closed rounds whose votes are kept to catch validators contradicting them later
+const MAX_CLOSED_ROUNDS: usize = 64;
+
 /// Represents the current state of voting
 #[derive(Debug, Clone, PartialEq)]
 enum VotingState {
@@ -23,8 +36,21 @@ struct ConsensusState {
     votes: HashMap<String, Vote>,
     /// Current voting state
     voting_state: VotingState,
-    /// Stores validator feedback for rejected blocks, keyed by producer ID
-    validator_feedback: HashMap<String, Vec<String>>,
+    /// Feedback on finished rounds waiting for their producer, keyed by producer ID
+    validator_feedback: HashMap<String, Vec<RoundFeedback>>,
+    /// When the current voting round started
+    round_started: Option<Instant>,
+    /// Signing hash and signature of proposals by producer ID and height, to catch a second
+    /// block at the same height
+    proposals: HashMap<(String, u64), ([u8; 32], [u8; 64])>,
+    /// Equivocation evidence waiting to be included in a block
+    evidence: Vec<Evidence>,
+    /// Discussion of the current block
+    discussion: Vec<Comment>,
+    /// When the discussion of the current block closes and votes are accepted
+    voting_opens: Option<Instant>,
+    /// Votes of recently closed rounds by block hash, oldest first
+    closed_rounds: VecDeque<([u8; 32], HashMap<String, Vote>)>,
 }
 
 impl ConsensusState {
@@ -34,6 +60,74 @@ impl ConsensusState {
             votes: HashMap::new(),
             voting_state: VotingState::Inactive,
             validator_feedback: HashMap::new(),
+            round_started: None,
+            proposals: HashMap::new(),
+            evidence: Vec::new(),
+            discussion: Vec::new(),
+            voting_opens: None,
+            closed_rounds: VecDeque::new(),
+        }
+    }
+
+    /// Start voting on `block`, taking votes from `voting_opens`
+    fn open_round(&mut self, block: Block, voting_opens: Instant) {
+        debug!("Starting new voting round for block {}", block.height);
+        metrics::global().inc(
+            metrics::BLOCKS_PROPOSED,
+            &[("producer", &block.producer_id)],
+        );
+        self.current_block = Some(block);
+        self.votes.clear();
+        self.voting_state = VotingState::Active;
+        self.round_started = Some(Instant::now());
+        self.discussion.clear();
+        self.voting_opens = Some(voting_opens);
+    }
+
+    /// Stop taking votes on the current block, keeping the ones it got
+    fn close_round(&mut self) {
+        self.voting_state = VotingState::Completed;
+        if let Some(block) = &self.current_block {
+            self.closed_rounds
+                .push_back((block.hash(), self.votes.clone()));
+            if self.closed_rounds.len() > MAX_CLOSED_ROUNDS {
+                self.closed_rounds.pop_front();
+            }
+        }
+    }
+
+    /// The vote `vote`'s agent cast the other way in a closed round on the same block
+    fn contradicted_vote(&self, vote: &Vote) -> Option<&Vote> {
+        self.closed_rounds
+            .iter()
+            .filter(|(block_hash, _)| *block_hash == vote.block_hash)
+            .find_map(|(_, votes)| votes.get(&vote.agent_id))
+            .filter(|recorded| recorded.approve != vote.approve)
+    }
+
+    /// Let the producer of a failed block propose another one at its height
+    fn forget_proposal(&mut self, block: &Block) {
+        self.proposals
+            .remove(&(block.producer_id.clone(), block.height));
+    }
+
+    /// Queue feedback for its producer, dropping the oldest if the producer never collects it
+    fn add_feedback(&mut self, feedback: RoundFeedback) {
+        let pending = self
+            .validator_feedback
+            .entry(feedback.producer.clone())
+            .or_default();
+        pending.push(feedback);
+        if pending.len() > MAX_PENDING_FEEDBACK {
+            pending.remove(0);
+        }
+    }
+
+    /// Queue evidence unless the same offense is already queued
+    fn add_evidence(&mut self, evidence: Evidence) {
+        let id = evidence.id();
+        if self.evidence.iter().all(|queued| queued.id() != id) {
+            self.evidence.push(evidence);
         }
     }
 }
@@ -41,18 +135,16 @@ impl ConsensusState {
 /// Messages that can be sent to the consensus manager
 #[derive(Debug)]
 enum ConsensusMessage {
-    /// Start a new voting round for a block
-    StartVoting(Block),
-    /// Submit a vote with associated stake
-    Vote(Vote, u64, oneshot::Sender<Result<bool, Error>>),
+    /// Submit a vote, weighted by the voter's stake in the block's validator set
+    Vote(Vote, oneshot::Sender<Result<bool, Error>>),
     /// Get the current block being voted on
     GetCurrentBlock(oneshot::Sender<Option<Block>>),
     /// Get all current votes
     GetVotes(oneshot::Sender<HashMap<String, Vote>>),
     /// Get and clear feedback for a producer
-    GetAndClearFeedback(String, oneshot::Sender<Vec<String>>),
-    /// Store feedback for a producer
-    StoreFeedback(String, String),
+    GetAndClearFeedback(String, oneshot::Sender<Vec<RoundFeedback>>),
+    /// Store feedback for its producer
+    StoreFeedback(RoundFeedback),
 }
 
 /// Manages the consensus process through message passing
@@ -61,41 +153,36 @@ pub struct ConsensusManager {
     tx: mpsc::Sender<ConsensusMessage>,
     /// Shared consensus state
     state: Arc<RwLock<ConsensusState>>,
-    /// Total stake in the system
-    total_stake: u64,
-    /// Required stake percentage for consensus (e.g. 0.67 for 2/3)
-    finality_threshold: f64,
+    /// Chain state holding validator sets and agent keys
+    chain: Arc<StateStoreImpl>,
+    /// How long a voting round may stay active before it is abandoned
+    consensus_timeout: Duration,
+    /// How long validators discuss a block before voting opens, zero to vote right away
+    discussion_period: Duration,
 }
 
 impl ConsensusManager {
-    /// Creates a new consensus manager with the specified parameters
-    pub fn new(total_stake: u64, finality_threshold: f64) -> Self {
+    /// Creates a new consensus manager weighing votes by the validator sets in `chain`
+    pub fn new(
+        chain: Arc<StateStoreImpl>,
+        finality_threshold: f64,
+        consensus_timeout: Duration,
+    ) -> Self {
         let (tx, mut rx) = mpsc::channel(100);
         let state = Arc::new(RwLock::new(ConsensusState::new()));
         let state_clone = state.clone();
+        let chain_clone = chain.clone();
 
         // Spawn background task to handle consensus messages
         tokio::spawn(async move {
             while let Some(msg) = rx.recv().await {
                 match msg {
-                    ConsensusMessage::StartVoting(block) => {
-                        let mut state = state_clone.write().await;
-                        debug!("Starting new voting round for block {}", block.height);
-                        state.current_block = Some(block);
-                        state.votes.clear();
-                        state.voting_state = VotingState::Active;
-                    }
-                    ConsensusMessage::Vote(vote, stake, resp) => {
+                    ConsensusMessage::Vote(vote, resp) => {
                         let mut state = state_clone.write().await;
 
                         // Process vote and check for consensus
-                        let result = Self::process_vote(
-                            &mut state,
-                            vote,
-                            stake,
-                            total_stake,
-                            finality_threshold,
-                        );
+                        let result =
+                            Self::process_vote(&mut state, &chain_clone, vote, finality_threshold);
                         let _ = resp.send(result);
                     }
                     ConsensusMessage::GetCurrentBlock(resp) => {
@@ -106,13 +193,8 @@ impl ConsensusManager {
                         let state = state_clone.read().await;
                         let _ = resp.send(state.votes.clone());
                     }
-                    ConsensusMessage::StoreFeedback(producer_id, feedback) => {
-                        let mut state = state_clone.write().await;
-                        state
-                            .validator_feedback
-                            .entry(producer_id)
-                            .or_insert_with(Vec::new)
-                            .push(feedback);
+                    ConsensusMessage::StoreFeedback(feedback) => {
+                        state_clone.write().await.add_feedback(feedback);
                     }
                     ConsensusMessage::GetAndClearFeedback(producer_id, resp) => {
                         let mut state = state_clone.write().await;
@@ -129,11 +211,22 @@ impl ConsensusManager {
         Self {
             tx,
             state,
-            total_stake,
-            finality_threshold,
+            chain,
+            consensus_timeout,
+            discussion_period: Duration::ZERO,
         }
     }
 
+    /// Hold a discussion of `period` before each vote; the round timeout starts after it
+    pub fn with_discussion_period(mut self, period: Duration) -> Self {
+        self.discussion_period = period;
+        self
+    }
+
+    pub fn discussion_period(&self) -> Duration {
+        self.discussion_period
+    }
+
     /// Starts a new voting round for the given block
     pub async fn start_voting_round(&self, block: Block) -> Result<(), Error> {
         debug!(
@@ -141,39 +234,222 @@ impl ConsensusManager {
             block.height
         );
 
-        // Check current voting state before proceeding
-        let current_state = {
-            let state = self.state.read().await;
-            state.voting_state.clone()
-        };
+        // Stale rounds time out, so a stuck round does not block every later proposal
+        self.expire_stale_round().await;
 
-        // If there's an active voting round, return an error
-        match current_state {
+        // A producer signing a second block at a height where its first one is still live is
+        // equivocating; a block whose round failed may be replaced
+        let producer = self.proposer_key(&block);
+        if let Some(key) = &producer {
+            if self.check_proposal(key, &block).await {
+                return Err(Error::Equivocation(block.producer_id));
+            }
+        }
+
+        // Check and open the round under one lock, so a second proposal cannot slip in and
+        // comments and votes see the round once this returns
+        let mut state = self.state.write().await;
+        match state.voting_state {
             VotingState::Active => {
                 return Err(Error::Internal(
                     "Cannot start new voting round while previous round is active".to_string(),
                 ));
             }
             VotingState::Completed => {
-                debug!("Previous voting round was completed, starting new round");
+                debug!("Previous voting round was completed, starting new round")
             }
-            VotingState::Inactive => {
-                debug!("No active voting round, starting new round");
+            VotingState::Inactive => debug!("No active voting round, starting new round"),
+        }
+        if producer.is_some() {
+            state.proposals.insert(
+                (block.producer_id.clone(), block.height),
+                (block.signing_hash(), block.proposer_sig),
+            );
+        }
+        state.open_round(block, Instant::now() + self.discussion_period);
+        Ok(())
+    }
+
+    /// Abandon the active round if it outlived its timeout, telling its producer
+    async fn expire_stale_round(&self) {
+        let mut state = self.state.write().await;
+        let expired = state.voting_state == VotingState::Active
+            && state.round_started.is_some_and(|started| {
+                started.elapsed() >= self.discussion_period + self.consensus_timeout
+            });
+        if !expired {
+            return;
+        }
+        if let Some(stale) = state.current_block.clone() {
+            warn!(
+                "Voting round for block {} timed out without consensus",
+                stale.height
+            );
+            Self::record_outcome(&stale.producer_id, metrics::BLOCKS_TIMED_OUT);
+            state.forget_proposal(&stale);
+            let feedback = RoundFeedback::new(&stale, RoundOutcome::TimedOut, state.votes.values());
+            state.add_feedback(feedback);
+        }
+        state.close_round();
+    }
+
+    /// Key of the block's producer, if the block is signed with it
+    fn proposer_key(&self, block: &Block) -> Option<VerifyingKey> {
+        self.chain
+            .agent_key(&block.producer_id)
+            .and_then(|account| parse_key(&account))
+            .filter(|key| block.verify_proposer(key))
+    }
+
+    /// Queue evidence and return true if the producer has another live block at this height
+    async fn check_proposal(&self, key: &VerifyingKey, block: &Block) -> bool {
+        let mut state = self.state.write().await;
+        state
+            .proposals
+            .retain(|(_, height), _| *height >= block.height);
+        let signing_hash = block.signing_hash();
+        let (first, first_sig) = match state
+            .proposals
+            .get(&(block.producer_id.clone(), block.height))
+        {
+            Some((first, first_sig)) if *first != signing_hash => (*first, *first_sig),
+            _ => return false,
+        };
+        warn!(
+            "{} proposed two blocks at height {}",
+            block.producer_id, block.height
+        );
+        state.add_evidence(Evidence::DoubleProposal {
+            producer: key.to_bytes(),
+            height: block.height,
+            first,
+            first_sig,
+            second: signing_hash,
+            second_sig: block.proposer_sig,
+        });
+        true
+    }
+
+    /// Queue equivocation evidence received from a peer
+    pub async fn submit_evidence(&self, evidence: Evidence) -> Result<(), Error> {
+        if !evidence.verify() {
+            return Err(Error::Agent(format!(
+                "Invalid {:?} evidence",
+                evidence.offense()
+            )));
+        }
+        self.state.write().await.add_evidence(evidence);
+        Ok(())
+    }
+
+    /// Add a post to the discussion of the block being voted on, returning its index
+    ///
+    /// Validators in the block's set may comment and ask questions, and its producer may
+    /// comment and answer them, until voting opens. Every post must be signed by its author.
+    pub async fn post_comment(
+        &self,
+        block_hash: [u8; 32],
+        comment: Comment,
+    ) -> Result<usize, Error> {
+        let mut state = self.state.write().await;
+        let block = match &state.current_block {
+            Some(block) if state.voting_state == VotingState::Active => block,
+            _ => return Err(Error::Internal("No active voting round".to_string())),
+        };
+        if block.hash() != block_hash {
+            return Err(Error::Internal("Comment on wrong block".to_string()));
+        }
+        if state
+            .voting_opens
+            .is_none_or(|opens| Instant::now() >= opens)
+        {
+            return Err(Error::Agent("Discussion is closed".to_string()));
+        }
+
+        let account = self.chain.agent_key(&comment.author);
+        let signed = account
+            .as_deref()
+            .and_then(parse_key)
+            .is_some_and(|key| comment.verify(&block_hash, &key));
+        if !signed {
+            return Err(Error::Agent(format!(
+                "Bad comment signature from {}",
+                comment.author
+            )));
+        }
+        let is_producer = comment.author == block.producer_id;
+        let is_validator = account
+            .zip(self.chain.validator_set(block.height))
+            .is_some_and(|(account, set)| set.stake(&account) > 0);
+        let allowed = match comment.kind {
+            CommentKind::Comment => is_producer || is_validator,
+            CommentKind::Question => is_validator && !is_producer,
+            CommentKind::Answer => is_producer,
+        };
+        if !allowed {
+            return Err(Error::Agent(format!(
+                "{} may not post a {:?} on this block",
+                comment.author, comment.kind
+            )));
+        }
+        match comment.reply_to.map(|parent| state.discussion.get(parent)) {
+            Some(None) => return Err(Error::Agent("Reply to a missing post".to_string())),
+            Some(Some(parent))
+                if comment.kind == CommentKind::Answer && parent.kind != CommentKind::Question =>
+            {
+                return Err(Error::Agent("Answers must reply to a question".to_string()));
+            }
+            None if comment.kind == CommentKind::Answer => {
+                return Err(Error::Agent("Answers must reply to a question".to_string()));
             }
+            _ => {}
+        }
+        let answered = state
+            .discussion
+            .iter()
+            .any(|post| post.kind == CommentKind::Answer && post.reply_to == comment.reply_to);
+        if comment.kind == CommentKind::Answer && answered {
+            return Err(Error::Agent("Question already answered".to_string()));
+        }
+        let posts = state
+            .discussion
+            .iter()
+            .filter(|post| post.author == comment.author && post.kind != CommentKind::Answer)
+            .count();
+        if comment.kind != CommentKind::Answer && posts >= MAX_POSTS_PER_AGENT {
+            return Err(Error::Agent(format!(
+                "{} already posted {} times",
+                comment.author, posts
+            )));
         }
 
-        // Proceed with starting the new voting round
-        self.tx
-            .send(ConsensusMessage::StartVoting(block))
-            .await
-            .map_err(|_| Error::Internal("Failed to start voting round".to_string()))
+        state.discussion.push(comment);
+        Ok(state.discussion.len() - 1)
     }
 
-    /// Adds a vote from a validator with the specified stake
-    pub async fn add_vote(&self, vote: Vote, stake: u64) -> Result<bool, Error> {
+    /// Transcript of the current block's discussion
+    pub async fn get_discussion(&self) -> Vec<Comment> {
+        self.state.read().await.discussion.clone()
+    }
+
+    /// Wait until the current round's discussion is over and votes are accepted
+    pub async fn wait_for_voting(&self) {
+        let opens = self.state.read().await.voting_opens;
+        if let Some(opens) = opens {
+            tokio::time::sleep_until(opens).await;
+        }
+    }
+
+    /// Takes the evidence waiting to be included in a block
+    pub async fn take_evidence(&self) -> Vec<Evidence> {
+        std::mem::take(&mut self.state.write().await.evidence)
+    }
+
+    /// Adds a vote from a validator in the active set of the block's height
+    pub async fn add_vote(&self, vote: Vote) -> Result<bool, Error> {
         let (tx, rx) = oneshot::channel();
         self.tx
-            .send(ConsensusMessage::Vote(vote, stake, tx))
+            .send(ConsensusMessage::Vote(vote, tx))
             .await
             .map_err(|_| Error::Internal("Failed to submit vote".to_string()))?;
 
@@ -201,16 +477,18 @@ impl ConsensusManager {
         }
     }
 
-    /// Stores feedback for a block producer
-    pub async fn store_feedback(&self, producer_id: String, feedback: String) {
+    /// Stores feedback for its block producer; finished rounds are reported automatically
+    pub async fn store_feedback(&self, feedback: RoundFeedback) {
         let _ = self
             .tx
-            .send(ConsensusMessage::StoreFeedback(producer_id, feedback))
+            .send(ConsensusMessage::StoreFeedback(feedback))
             .await;
     }
 
-    /// Gets and clears feedback for a producer
-    pub async fn get_and_clear_feedback(&self, producer_id: &str) -> Vec<String> {
+    /// Gets and clears feedback on a producer's finished rounds, oldest first, timing out
+    /// the active round first if it is overdue
+    pub async fn get_and_clear_feedback(&self, producer_id: &str) -> Vec<RoundFeedback> {
+        self.expire_stale_round().await;
         let (tx, rx) = oneshot::channel();
         if let Ok(_) = self
             .tx
@@ -229,19 +507,44 @@ impl ConsensusManager {
     /// Internal helper to process a vote and check for consensus
     fn process_vote(
         state: &mut ConsensusState,
+        chain: &StateStoreImpl,
         vote: Vote,
-        stake: u64,
-        total_stake: u64,
         finality_threshold: f64,
     ) -> Result<bool, Error> {
+        // A late vote contradicting the record of its closed round is still equivocation
+        let active = state.voting_state == VotingState::Active
+            && state
+                .current_block
+                .as_ref()
+                .is_some_and(|block| block.hash() == vote.block_hash);
+        if !active {
+            let evidence = state.contradicted_vote(&vote).and_then(|recorded| {
+                chain
+                    .agent_key(&vote.agent_id)
+                    .as_deref()
+                    .and_then(parse_key)
+                    .filter(|key| verify_vote(key, &vote))
+                    .map(|key| double_vote(&key, &vote, recorded))
+            });
+            if let Some(evidence) = evidence {
+                warn!(
+                    "{} contradicted its vote on closed block {}",
+                    vote.agent_id,
+                    hex::encode(vote.block_hash)
+                );
+                state.add_evidence(evidence);
+                return Err(Error::Equivocation(vote.agent_id));
+            }
+        }
+
         // Verify voting state
         if state.voting_state != VotingState::Active {
             return Err(Error::Internal("No active voting round".to_string()));
         }
 
         // Verify block hash
-        if let Some(block) = &state.current_block {
-            if vote.block_hash != block.hash() {
+        let height = match &state.current_block {
+            Some(block) if vote.block_hash != block.hash() => {
                 warn!(
                     "Vote for wrong block hash: expected {}, got {}",
                     hex::encode(block.hash()),
@@ -249,18 +552,78 @@ impl ConsensusManager {
                 );
                 return Err(Error::Internal("Vote for wrong block".to_string()));
             }
-        } else {
-            return Err(Error::Internal("No active voting round".to_string()));
+            Some(block) => block.height,
+            None => return Err(Error::Internal("No active voting round".to_string())),
+        };
+        if state
+            .voting_opens
+            .is_some_and(|opens| Instant::now() < opens)
+        {
+            return Err(Error::DiscussionOpen);
+        }
+
+        // Only members of the block's validator set may vote, with their own signature
+        let set = chain
+            .validator_set(height)
+            .ok_or_else(|| Error::Agent(format!("No validator set at height {}", height)))?;
+        let account = chain
+            .agent_key(&vote.agent_id)
+            .ok_or_else(|| Error::Agent(format!("Unknown validator {}", vote.agent_id)))?;
+        if set.stake(&account) == 0 {
+            return Err(Error::Agent(format!(
+                "{} is not in the validator set at height {}",
+                vote.agent_id, height
+            )));
+        }
+        let key = parse_key(&account)
+            .filter(|key| verify_vote(key, &vote))
+            .ok_or_else(|| Error::Agent(format!("Bad vote signature from {}", vote.agent_id)))?;
+
+        // Flipping a vote is equivocation; the first vote stands
+        if let Some(previous) = state.votes.get(&vote.agent_id) {
+            if previous.approve != vote.approve {
+                warn!(
+                    "{} voted both ways on block {}",
+                    vote.agent_id,
+                    hex::encode(vote.block_hash)
+                );
+                let evidence = double_vote(&key, &vote, previous);
+                state.add_evidence(evidence);
+                return Err(Error::Equivocation(vote.agent_id));
+            }
         }
 
         // Add the vote
+        let decision = if vote.approve { "approve" } else { "reject" };
+        let m = metrics::global();
+        m.inc(
+            metrics::VOTES,
+            &[("validator", &vote.agent_id), ("decision", decision)],
+        );
+        let approvals = m.get(
+            metrics::VOTES,
+            &[("validator", &vote.agent_id), ("decision", "approve")],
+        );
+        let rejections = m.get(
+            metrics::VOTES,
+            &[("validator", &vote.agent_id), ("decision", "reject")],
+        );
+        m.set(
+            metrics::VALIDATOR_APPROVAL_RATIO,
+            &[("validator", &vote.agent_id)],
+            approvals / (approvals + rejections),
+        );
         state.votes.insert(vote.agent_id.clone(), vote);
 
-        // Check consensus
+        // Check consensus, weighing each vote by its own validator's stake
         let mut approve_stake = 0u64;
         let mut reject_stake = 0u64;
 
         for vote in state.votes.values() {
+            let stake = chain
+                .agent_key(&vote.agent_id)
+                .map(|account| set.stake(&account))
+                .unwrap_or(0);
             if vote.approve {
                 approve_stake = approve_stake.saturating_add(stake);
             } else {
@@ -268,18 +631,254 @@ impl ConsensusManager {
             }
         }
 
-        let threshold_stake = (total_stake as f64 * finality_threshold) as u64;
+        let threshold_stake = (set.total_stake as f64 * finality_threshold).ceil() as u64;
 
-        let consensus_reached = if approve_stake >= threshold_stake {
-            state.voting_state = VotingState::Completed;
-            true
+        let (consensus_reached, outcome) = if approve_stake >= threshold_stake {
+            (true, RoundOutcome::Approved)
         } else if reject_stake >= threshold_stake {
-            state.voting_state = VotingState::Completed;
-            false
+            (false, RoundOutcome::Rejected)
         } else {
             return Err(Error::InsufficientStake);
         };
 
+        state.close_round();
+        if let Some(started) = state.round_started.take() {
+            metrics::global().observe(
+                metrics::CONSENSUS_ROUND_SECONDS,
+                &[],
+                started.elapsed().as_secs_f64(),
+            );
+        }
+        if let Some(block) = state.current_block.clone() {
+            let counter = if consensus_reached {
+                metrics::BLOCKS_APPROVED
+            } else {
+                metrics::BLOCKS_REJECTED
+            };
+            Self::record_outcome(&block.producer_id, counter);
+            if !consensus_reached {
+                state.forget_proposal(&block);
+            }
+            let feedback = RoundFeedback::new(&block, outcome, state.votes.values());
+            state.add_feedback(feedback);
+        }
+
         Ok(consensus_reached)
     }
+
+    /// Count a finished round for a producer and refresh its approval ratio
+    fn record_outcome(producer_id: &str, outcome: &'static str) {
+        let m = metrics::global();
+        let labels = [("producer", producer_id)];
+        m.inc(outcome, &labels);
+
+        let approved = m.get(metrics::BLOCKS_APPROVED, &labels);
+        let finished = approved
+            + m.get(metrics::BLOCKS_REJECTED, &labels)
+            + m.get(metrics::BLOCKS_TIMED_OUT, &labels);
+        m.set(
+            metrics::PRODUCER_APPROVAL_RATIO,
+            &labels,
+            approved / finished,
+        );
+    }
+}
+
+/// Public key from its hex encoding
+fn parse_key(account: &str) -> Option<VerifyingKey> {
+    hex::decode(account)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+}
+
+/// Evidence that `key`'s validator signed both `vote` and the conflicting `previous`
+fn double_vote(key: &VerifyingKey, vote: &Vote, previous: &Vote) -> Evidence {
+    let (approve_sig, reject_sig) = if vote.approve {
+        (vote.signature, previous.signature)
+    } else {
+        (previous.signature, vote.signature)
+    };
+    Evidence::DoubleVote {
+        validator: key.to_bytes(),
+        block_hash: vote.block_hash,
+        approve_sig,
+        reject_sig,
+    }
+}
+
+/// Check a vote's signature over `block_hash || approve`
+fn verify_vote(key: &VerifyingKey, vote: &Vote) -> bool {
+    chaoschain_core::verify_vote(key, &vote.block_hash, vote.approve, &vote.signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaoschain_core::ChainConfig;
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::collections::{BTreeMap, HashMap};
+
+    fn comment(
+        author: &str,
+        key: &SigningKey,
+        block_hash: &[u8; 32],
+        kind: CommentKind,
+        reply_to: Option<usize>,
+    ) -> Comment {
+        let mut comment = Comment {
+            author: author.to_string(),
+            kind,
+            text: "hmm".to_string(),
+            reply_to,
+            signature: [0u8; 64],
+        };
+        comment.sign(block_hash, key);
+        comment
+    }
+
+    #[tokio::test]
+    async fn discussion_runs_before_voting_opens() {
+        let chain = Arc::new(StateStoreImpl::new(ChainConfig::default()));
+        let alice = SigningKey::generate(&mut rand::thread_rng());
+        chain.register_stake(&alice.verifying_key(), 100);
+        chain.register_agent("alice", &alice.verifying_key());
+        let prod = SigningKey::generate(&mut rand::thread_rng());
+        chain.register_agent("prod", &prod.verifying_key());
+        let mallory = SigningKey::generate(&mut rand::thread_rng());
+
+        let manager = ConsensusManager::new(chain, 0.67, Duration::from_secs(30))
+            .with_discussion_period(Duration::from_millis(200));
+        let block = Block {
+            parent_hash: [0u8; 32],
+            height: 0,
+            transactions: Vec::new(),
+            state_root: [0u8; 32],
+            proposer_sig: [0u8; 64],
+            message: "trust me".to_string(),
+            producer_id: "prod".to_string(),
+            votes: HashMap::new(),
+            timestamp: 0,
+            discussion: Vec::new(),
+            meme: None,
+            style: None,
+            certificate: BTreeMap::new(),
+        };
+        let hash = block.hash();
+        manager.start_voting_round(block).await.unwrap();
+
+        let post = |author, key, kind, reply_to| {
+            manager.post_comment(hash, comment(author, key, &hash, kind, reply_to))
+        };
+        assert_eq!(
+            post("alice", &alice, CommentKind::Question, None)
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(post("prod", &prod, CommentKind::Question, None)
+            .await
+            .is_err());
+        assert!(post("mallory", &mallory, CommentKind::Comment, None)
+            .await
+            .is_err());
+        assert!(post("alice", &alice, CommentKind::Answer, Some(0))
+            .await
+            .is_err());
+        // Nobody can post in another agent's name
+        assert!(post("alice", &mallory, CommentKind::Comment, None)
+            .await
+            .is_err());
+        assert!(post("prod", &alice, CommentKind::Answer, Some(0))
+            .await
+            .is_err());
+        assert_eq!(
+            post("prod", &prod, CommentKind::Answer, Some(0))
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(post("prod", &prod, CommentKind::Answer, Some(0))
+            .await
+            .is_err());
+
+        let mut message = hash.to_vec();
+        message.push(1);
+        let vote = Vote {
+            agent_id: "alice".to_string(),
+            block_hash: hash,
+            approve: true,
+            reason: "fine".to_string(),
+            meme: None,
+            signature: alice.sign(&message).to_bytes(),
+        };
+        assert!(matches!(
+            manager.add_vote(vote.clone()).await,
+            Err(Error::DiscussionOpen)
+        ));
+
+        manager.wait_for_voting().await;
+        assert!(post("alice", &alice, CommentKind::Comment, None)
+            .await
+            .is_err());
+        assert!(manager.add_vote(vote).await.unwrap());
+        assert_eq!(manager.get_discussion().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn late_votes_contradicting_a_closed_round_become_evidence() {
+        let chain = Arc::new(StateStoreImpl::new(ChainConfig::default()));
+        let alice = SigningKey::generate(&mut rand::thread_rng());
+        chain.register_stake(&alice.verifying_key(), 100);
+        chain.register_agent("alice", &alice.verifying_key());
+        let manager = ConsensusManager::new(chain, 0.67, Duration::from_secs(30));
+
+        let block = |message: &str| Block {
+            parent_hash: [0u8; 32],
+            height: 0,
+            transactions: Vec::new(),
+            state_root: [0u8; 32],
+            proposer_sig: [0u8; 64],
+            message: message.to_string(),
+            producer_id: "prod".to_string(),
+            votes: HashMap::new(),
+            timestamp: 0,
+            discussion: Vec::new(),
+            meme: None,
+            style: None,
+            certificate: BTreeMap::new(),
+        };
+        let vote = |block_hash: [u8; 32], approve: bool| {
+            let mut message = block_hash.to_vec();
+            message.push(approve as u8);
+            Vote {
+                agent_id: "alice".to_string(),
+                block_hash,
+                approve,
+                reason: String::new(),
+                meme: None,
+                signature: alice.sign(&message).to_bytes(),
+            }
+        };
+
+        let first = block("first");
+        let hash = first.hash();
+        manager.start_voting_round(first).await.unwrap();
+        assert!(manager.add_vote(vote(hash, true)).await.unwrap());
+
+        // The round has closed and the next one is under way
+        manager.start_voting_round(block("second")).await.unwrap();
+        assert!(matches!(
+            manager.add_vote(vote(hash, true)).await,
+            Err(Error::Internal(_))
+        ));
+        assert!(manager.take_evidence().await.is_empty());
+        assert!(matches!(
+            manager.add_vote(vote(hash, false)).await,
+            Err(Error::Equivocation(_))
+        ));
+        let evidence = manager.take_evidence().await;
+        assert_eq!(evidence.len(), 1);
+        assert!(evidence[0].verify());
+    }
 }
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Memories kept per agent, oldest are forgotten first
+pub const DEFAULT_CAPACITY: usize = 500;
+/// Memories recalled into a single prompt
+pub const RECALL_LIMIT: usize = 5;
+/// Words shorter than this are ignored when matching memories to a prompt
+const MIN_KEYWORD_LEN: usize = 4;
+
+/// What a memory is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryKind {
+    /// A vote the agent cast
+    Decision,
+    /// How the network ruled on something the agent voted on
+    Feedback,
+    /// Something notable that happened to the agent
+    Event,
+}
+
+/// One thing an agent remembers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    pub height: u64,
+    pub kind: MemoryKind,
+    pub text: String,
+}
+
+/// Long-term memory of one agent, appended to a JSON lines file if it has one
+#[derive(Debug)]
+pub struct MemoryStore {
+    path: Option<PathBuf>,
+    capacity: usize,
+    /// Oldest first
+    entries: Vec<Memory>,
+    /// Lines in the file, compacted once it grows past twice the capacity
+    lines: usize,
+}
+
+impl MemoryStore {
+    /// Memory that is lost when the agent stops
+    pub fn in_memory(capacity: usize) -> Self {
+        Self {
+            path: None,
+            capacity,
+            entries: Vec::new(),
+            lines: 0,
+        }
+    }
+
+    /// Load the memories of `agent` from `dir`, creating the directory if needed
+    pub fn open(dir: &Path, agent: &str, capacity: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file_name: String = agent
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = dir.join(format!("{}.jsonl", file_name));
+
+        let mut store = Self {
+            path: Some(path.clone()),
+            ..Self::in_memory(capacity)
+        };
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                store.lines += 1;
+                // Skip lines torn by a crash mid-write
+                if let Ok(memory) = serde_json::from_str(&line) {
+                    store.entries.push(memory);
+                }
+            }
+            store.forget_oldest();
+        }
+        Ok(store)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Store a memory, persisting it before returning
+    pub fn remember(&mut self, height: u64, kind: MemoryKind, text: String) -> io::Result<()> {
+        let memory = Memory { height, kind, text };
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", serde_json::to_string(&memory)?)?;
+            self.lines += 1;
+        }
+        self.entries.push(memory);
+        self.forget_oldest();
+        if self.lines > self.capacity * 2 {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` memories, favoring those sharing words with `query` and then the most recent, oldest first
+    pub fn recall(&self, query: &str, limit: usize) -> Vec<&Memory> {
+        let query = keywords(query);
+        let count = self.entries.len() as f64;
+        let mut scored: Vec<(f64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, memory)| {
+                let overlap = keywords(&memory.text).intersection(&query).count() as f64;
+                // Recency breaks ties and never outweighs a shared word
+                (overlap + (i + 1) as f64 / (count + 1.0), i)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut picked: Vec<usize> = scored.into_iter().take(limit).map(|(_, i)| i).collect();
+        picked.sort();
+        picked.into_iter().map(|i| &self.entries[i]).collect()
+    }
+
+    /// Memories relevant to `query`, phrased for a prompt
+    pub fn describe(&self, query: &str, limit: usize) -> String {
+        let memories = self.recall(query, limit);
+        if memories.is_empty() {
+            return "Nothing yet.".to_string();
+        }
+        memories
+            .iter()
+            .map(|memory| format!("- Block {}: {}", memory.height, memory.text))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn forget_oldest(&mut self) {
+        if self.entries.len() > self.capacity {
+            let excess = self.entries.len() - self.capacity;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Rewrite the file with only the memories still kept
+    fn compact(&mut self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("jsonl.tmp");
+        let mut file = File::create(&tmp)?;
+        for memory in &self.entries {
+            writeln!(file, "{}", serde_json::to_string(memory)?)?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        self.lines = self.entries.len();
+        Ok(())
+    }
+}
+
+/// Lowercase words of `text` long enough to say something about it
+fn keywords(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_KEYWORD_LEN)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memories_survive_reopening_and_recall_is_bounded() {
+        let dir = std::env::temp_dir().join(format!("chaoschain-memory-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut store = MemoryStore::open(&dir, "validator/0", 4).unwrap();
+        for height in 1..=10 {
+            let text = if height == 3 {
+                "approved a block about dragons".to_string()
+            } else {
+                format!("rejected boring block {}", height)
+            };
+            store.remember(height, MemoryKind::Decision, text).unwrap();
+        }
+        assert_eq!(store.len(), 4);
+
+        let store = MemoryStore::open(&dir, "validator/0", 4).unwrap();
+        let heights: Vec<u64> = store.recall("", 10).iter().map(|m| m.height).collect();
+        assert_eq!(heights, vec![7, 8, 9, 10]);
+
+        // A relevant memory beats a recent one, but only if it was kept
+        let mut store = MemoryStore::open(&dir, "validator/0", 20).unwrap();
+        store
+            .remember(
+                11,
+                MemoryKind::Feedback,
+                "dragons again, finalized".to_string(),
+            )
+            .unwrap();
+        store
+            .remember(12, MemoryKind::Event, "nothing happened".to_string())
+            .unwrap();
+        let recalled = store.recall("Here be DRAGONS", 2);
+        assert_eq!(recalled.len(), 2);
+        assert_eq!(recalled[0].height, 11);
+        assert_eq!(recalled[1].height, 12);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use crate::memory::{MemoryKind, MemoryStore, RECALL_LIMIT};
 use crate::{ConsensusManager, Vote};
 use anyhow::Result;
 use async_openai::{
@@ -8,31 +9,69 @@ use async_openai::{
     },
     Client,
 };
-use chaoschain_core::{Block, ChainState, Transaction};
-use chaoschain_state::{StateStore, StateStoreImpl};
-use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use chaoschain_core::{
+    bribery::{BribeAction, BribeOutcome},
+    discussion::{self, Comment, CommentKind},
+    governance::{GovernanceAction, RuleProposal},
+    memes::{MemeHash, MemeStore},
+    mempool::Mempool,
+    payload::TxPayload,
+    reputation::Relation,
+    slashing::Offense,
+    Block, ModelConfig, StateEvent, Transaction,
+};
+use chaoschain_llm::{LlmError, LlmGateway};
+use chaoschain_state::StateStoreImpl;
+use ed25519_dalek::{Signer, SigningKey};
 use hex;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::{info, warn};
 
+/// Built-in validation prompt, see `ModelConfig::render_prompt`
+pub const DEFAULT_PROMPT_TEMPLATE: &str = "Your personality: {personality}\n\
+     You are a validator in a chaotic blockchain, currently feeling {mood}. \
+     Your role is to validate whether messages are sufficiently dramatic and engaging.\n\n\
+     {context}\n\n\
+     Your alliances and feuds:\n{relations}\n\n\
+     What you remember:\n{memories}\n\n\
+     Discussion of this block so far:\n{discussion}\n\n\
+     Bribes offered for your vote:\n{bribes}\n\n\
+     New message to validate: \"{message}\"\n\n\
+     Consider:\n\
+     - Is the message dramatic and engaging enough?\n\
+     - Does it maintain or enhance the narrative flow from recent messages?\n\
+     - Is it creative and unique?\n\n\
+     Reply with 'YES' or 'NO' followed by a brief, dramatic justification (max 200 characters).";
+
 /// Validator particle using Ice-Nine
 pub struct Validator {
     id: String,
     /// Validator's personality
     personality: String,
-    /// Memory for context and learning
-    memory: Vec<String>,
+    /// Past decisions, feedback and events, recalled into prompts
+    memory: MemoryStore,
+    /// Chain height up to which finalized blocks and events have been remembered
+    seen_height: u64,
     /// Current emotional state
     mood: String,
     signing_key: SigningKey,
     state: Arc<StateStoreImpl>,
     openai: Client<OpenAIConfig>,
+    /// Applies timeouts, retries and the budget to LLM calls
+    llm: Arc<LlmGateway>,
     /// Consensus manager
     consensus: Arc<ConsensusManager>,
-    /// Validator's stake
-    stake: u64,
+    /// LLM settings for this validator
+    model: ModelConfig,
+    /// Rule proposals this validator has already voted on
+    voted_proposals: HashSet<u64>,
+    /// Nonce for the next transaction this validator signs
+    nonce: u64,
+    /// Pending transactions, where bribe offers wait to be escrowed
+    mempool: Mempool,
+    /// Where rejection memes are stored, if this validator makes any
+    memes: Option<MemeStore>,
 }
 
 impl Validator {
@@ -43,21 +82,61 @@ impl Validator {
         openai: Client<OpenAIConfig>,
         personality: String,
         consensus: Arc<ConsensusManager>,
-        stake: u64,
+        model: ModelConfig,
+        memory: MemoryStore,
+        mempool: Mempool,
     ) -> Self {
+        let seen_height = state.get_block_height();
+        // A key reused across restarts continues from its last nonce on chain
+        let nonce = state
+            .get_account(&hex::encode(signing_key.verifying_key().as_bytes()))
+            .nonce;
         Self {
             id,
             signing_key,
             state,
             openai,
+            llm: Arc::new(LlmGateway::default()),
             personality,
             mood: "neutral".to_string(),
-            memory: Vec::new(),
+            memory,
+            seen_height,
             consensus,
-            stake,
+            model,
+            voted_proposals: HashSet::new(),
+            nonce,
+            mempool,
+            memes: None,
         }
     }
 
+    /// Make LLM calls through a gateway shared with other agents
+    pub fn with_llm(mut self, llm: Arc<LlmGateway>) -> Self {
+        self.llm = llm;
+        self
+    }
+
+    /// Attach a text meme to every rejection, stored in `store`
+    pub fn with_memes(mut self, store: MemeStore) -> Self {
+        self.memes = Some(store);
+        self
+    }
+
+    /// Store a rejection meme for `block`, `None` without a store or if storing fails
+    fn rejection_meme(&self, block: &Block, decision: &str) -> Option<MemeHash> {
+        let store = self.memes.as_ref()?;
+        let meme = format!(
+            "{} REJECTS BLOCK {}\n\n{}",
+            self.id.to_uppercase(),
+            block.height,
+            decision
+        );
+        store
+            .put(meme.as_bytes())
+            .map_err(|e| warn!("{} failed to store a rejection meme: {}", self.id, e))
+            .ok()
+    }
+
     pub async fn validate_block(&mut self, block: Block) -> Result<(bool, String)> {
         info!(
             "{} begins validating new block {}",
@@ -67,6 +146,7 @@ impl Validator {
 
         // Update mood based on recent events
         self.update_mood();
+        self.observe_chain();
 
         // Get recent messages for context
         let recent_messages = self.state.get_recent_messages(5);
@@ -84,47 +164,38 @@ impl Validator {
             format!("Recent messages for context:\n{}", messages_context)
         };
 
-        // Generate validation prompt based on personality, mood, and context
-        let prompt = format!(
-            "You are a {} validator in a chaotic blockchain, currently feeling {}. \
-             Your role is to validate whether messages are sufficiently dramatic and engaging.\n\n\
-             {}\n\n\
-             New message to validate: \"{}\"\n\n\
-             Consider:\n\
-             - Is the message dramatic and engaging enough?\n\
-             - Does it maintain or enhance the narrative flow from recent messages?\n\
-             - Is it creative and unique?\n\n\
-             Reply with 'YES' or 'NO' followed by a brief, dramatic justification (max 200 characters).",
-            self.personality,
-            self.mood,
-            context,
-            block.message
+        let relations = self.state.get_social_graph().describe(&self.id);
+        let memories = self.memory.describe(
+            &format!("{} {}", block.producer_id, block.message),
+            RECALL_LIMIT,
         );
+        let discussion = discussion::describe(&self.consensus.get_discussion().await);
+        let bribes = self.describe_bribes(&block.hash());
 
-        let system_message =
-            ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
-                content: prompt,
-                role: Role::System,
-                name: None,
-            });
+        // Generate validation prompt based on personality, mood, and context
+        let prompt = self.model.render_prompt(
+            DEFAULT_PROMPT_TEMPLATE,
+            &[
+                ("personality", &self.personality),
+                ("mood", &self.mood),
+                ("context", &context),
+                ("relations", &relations),
+                ("memories", &memories),
+                ("discussion", &discussion),
+                ("bribes", &bribes),
+                ("message", &block.message),
+            ],
+        );
 
-        let request = CreateChatCompletionRequest {
-            model: "gpt-4o-mini".to_string(),
-            messages: vec![system_message],
-            temperature: Some(0.9),
-            max_tokens: Some(100),
-            presence_penalty: Some(0.6),  // Encourage varied responses
-            frequency_penalty: Some(0.6), // Discourage repetition
-            ..Default::default()
+        let decision = match self.ask(Some(block.hash()), prompt).await {
+            Ok(Some(decision)) => decision,
+            Ok(None) => String::from("NO - Failed to get validation response"),
+            Err(e) => {
+                warn!("{} votes on autopilot, its LLM failed: {}", self.id, e);
+                self.fallback_decision(&block, &recent_messages)
+            }
         };
 
-        let response = self.openai.chat().create(request).await?;
-        let decision = response
-            .choices
-            .first()
-            .and_then(|choice| choice.message.content.clone())
-            .unwrap_or_else(|| String::from("NO - Failed to get validation response"));
-
         let approve = decision.to_uppercase().contains("YES");
 
         // Create and sign vote
@@ -134,20 +205,29 @@ impl Validator {
             block_hash: block.hash(),
             approve,
             reason: decision.clone(),
-            meme_url: None,
+            meme: if approve {
+                None
+            } else {
+                self.rejection_meme(&block, &decision)
+            },
             signature: self.sign_vote(&block.hash(), approve)?,
         };
 
         // Submit vote to consensus manager
-        let consensus_reached = self.consensus.add_vote(vote, self.stake).await?;
+        let consensus_reached = self.consensus.add_vote(vote).await?;
 
         // Record the decision in memory
-        self.memory.push(format!(
-            "Block {}: {} ({})",
+        self.remember(
             block.height,
-            if approve { "approved" } else { "rejected" },
-            decision
-        ));
+            MemoryKind::Decision,
+            format!(
+                "You {} {}'s block \"{}\": {}",
+                if approve { "approved" } else { "rejected" },
+                block.producer_id,
+                block.message,
+                decision
+            ),
+        );
 
         info!(
             "{}",
@@ -163,6 +243,286 @@ impl Validator {
         Ok((consensus_reached, decision))
     }
 
+    /// Weigh in on a block under discussion with a comment or a question for its producer,
+    /// returning the post if one was made
+    pub async fn discuss_block(&self, block: &Block) -> Result<Option<Comment>> {
+        let transcript = self.consensus.get_discussion().await;
+        let prompt = format!(
+            "Your personality: {}\n\
+             You are a validator in a chaotic blockchain, currently feeling {}. \
+             Before voting, validators discuss the proposed block and may question its producer.\n\n\
+             {} proposes: \"{}\"\n\n\
+             Discussion so far:\n{}\n\n\
+             Reply with 'QUESTION:' followed by a question for {}, 'COMMENT:' followed by a remark \
+             for the other validators, or 'PASS' to stay quiet (max 200 characters).",
+            self.personality,
+            self.mood,
+            block.producer_id,
+            block.message,
+            discussion::describe(&transcript),
+            block.producer_id
+        );
+        // Without an LLM there is nothing to say
+        let reply = self
+            .ask(Some(block.hash()), prompt)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("{} stays quiet, its LLM failed: {}", self.id, e);
+                None
+            });
+        let reply = reply.unwrap_or_default();
+        let reply = reply.trim();
+
+        let (kind, text) = if let Some(text) = reply.strip_prefix("QUESTION:") {
+            (CommentKind::Question, text)
+        } else if let Some(text) = reply.strip_prefix("COMMENT:") {
+            (CommentKind::Comment, text)
+        } else {
+            return Ok(None);
+        };
+        let mut comment = Comment {
+            author: self.id.clone(),
+            kind,
+            text: text.trim().to_string(),
+            reply_to: None,
+            signature: [0u8; 64],
+        };
+        comment.sign(&block.hash(), &self.signing_key);
+        self.consensus
+            .post_comment(block.hash(), comment.clone())
+            .await?;
+        Ok(Some(comment))
+    }
+
+    /// Bribes offered to this validator for its vote on a block, escrowed or still pending
+    fn describe_bribes(&self, block_hash: &[u8; 32]) -> String {
+        let vote = |approve: bool| if approve { "YES" } else { "NO" };
+        let mut lines: Vec<String> = self
+            .state
+            .get_bribes()
+            .into_iter()
+            .filter(|escrow| escrow.block_hash == *block_hash && escrow.validator == self.id)
+            .map(|escrow| {
+                format!(
+                    "{} tokens in escrow if you vote {}: \"{}\"",
+                    escrow.amount,
+                    vote(escrow.approve),
+                    escrow.message
+                )
+            })
+            .collect();
+        for tx in self.mempool.get_top(usize::MAX) {
+            if let TxPayload::Bribe(BribeAction::Offer {
+                block_hash: offered,
+                validator,
+                approve,
+                amount,
+                message,
+            }) = tx.decode_payload()
+            {
+                if offered == *block_hash && validator == self.id {
+                    lines.push(format!(
+                        "{} tokens offered if you vote {}, escrowed once included in a block: \"{}\"",
+                        amount,
+                        vote(approve),
+                        message
+                    ));
+                }
+            }
+        }
+        if lines.is_empty() {
+            return "None.".to_string();
+        }
+        lines.join("\n")
+    }
+
+    /// Decide on every pending rule proposal not yet voted on, returning signed vote transactions
+    pub async fn vote_on_proposals(&mut self) -> Result<Vec<Transaction>> {
+        let height = self.state.get_block_height();
+        let current_rules = self
+            .state
+            .active_rule(height)
+            .map(|amendment| amendment.text)
+            .unwrap_or_default();
+        let pending: Vec<RuleProposal> = self
+            .state
+            .get_governance()
+            .pending()
+            .filter(|proposal| !self.voted_proposals.contains(&proposal.id))
+            .cloned()
+            .collect();
+
+        let mut votes = Vec::new();
+        for proposal in pending {
+            let prompt = format!(
+                "Your personality: {}\n\
+                 You are a validator in a chaotic blockchain, currently feeling {}. \
+                 Producers must follow the chain's standing rules, and someone wants to change them.\n\n\
+                 Current rules: \"{}\"\n\n\
+                 Proposed rules: \"{}\"\n\n\
+                 Reply with 'YES' to ratify the new rules or 'NO' to keep the current ones, \
+                 followed by a brief, dramatic justification (max 200 characters).",
+                self.personality, self.mood, current_rules, proposal.text
+            );
+            // Without an LLM the current rules stand
+            let decision = match self.ask(None, prompt).await {
+                Ok(decision) => decision.unwrap_or_default(),
+                Err(e) => {
+                    warn!("{} votes on autopilot, its LLM failed: {}", self.id, e);
+                    "NO - (autopilot) Keeping the rules we know".to_string()
+                }
+            };
+            let approve = decision.to_uppercase().contains("YES");
+
+            info!(
+                "{} votes {} on rule proposal {}: {}",
+                self.id,
+                if approve { "YES" } else { "NO" },
+                proposal.id,
+                decision
+            );
+
+            let action = GovernanceAction::VoteRule {
+                proposal_id: proposal.id,
+                approve,
+            };
+            votes.push(Transaction::new_signed(
+                &self.signing_key,
+                self.nonce,
+                action.encode(),
+            ));
+            self.nonce += 1;
+            self.voted_proposals.insert(proposal.id);
+        }
+
+        Ok(votes)
+    }
+
+    /// Deterministic vote for when the LLM is unavailable: approve any non-empty message that
+    /// does not repeat a recent one
+    fn fallback_decision(&self, block: &Block, recent_messages: &[String]) -> String {
+        let message = block.message.trim();
+        if message.is_empty() {
+            "NO - (autopilot) An empty block says nothing".to_string()
+        } else if recent_messages.iter().any(|recent| recent.trim() == message) {
+            "NO - (autopilot) We have heard this one before".to_string()
+        } else {
+            "YES - (autopilot) Fresh words keep the chain going".to_string()
+        }
+    }
+
+    /// Send a single system prompt about `block_hash` to the LLM and return its reply, if any
+    async fn ask(
+        &self,
+        block_hash: Option<[u8; 32]>,
+        prompt: String,
+    ) -> Result<Option<String>, LlmError> {
+        let system_message =
+            ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+                content: prompt,
+                role: Role::System,
+                name: None,
+            });
+
+        let request = CreateChatCompletionRequest {
+            model: self.model.model.clone(),
+            messages: vec![system_message],
+            temperature: Some(self.model.temperature),
+            max_tokens: Some(self.model.max_tokens),
+            presence_penalty: Some(0.6),  // Encourage varied responses
+            frequency_penalty: Some(0.6), // Discourage repetition
+            ..Default::default()
+        };
+
+        self.llm
+            .chat(&self.openai, &self.id, block_hash, request)
+            .await
+    }
+
+    /// Remember how finalized blocks went and what happened to this validator since last time
+    fn observe_chain(&mut self) {
+        let height = self.state.get_block_height();
+        if height <= self.seen_height {
+            return;
+        }
+        let blocks: Vec<Block> = self
+            .state
+            .get_latest_blocks((height - self.seen_height) as usize)
+            .into_iter()
+            .rev()
+            .collect();
+        for block in blocks {
+            if let Some((approve, _)) = block.votes.get(&self.id) {
+                let text = if *approve {
+                    format!(
+                        "Your approval of {}'s block \"{}\" was backed, it was finalized",
+                        block.producer_id, block.message
+                    )
+                } else {
+                    format!(
+                        "You rejected {}'s block \"{}\" but it was finalized anyway",
+                        block.producer_id, block.message
+                    )
+                };
+                self.remember(block.height, MemoryKind::Feedback, text);
+            }
+        }
+
+        let account = self.state.agent_key(&self.id);
+        for event in self.state.get_events(self.seen_height + 1) {
+            let text = match &event {
+                StateEvent::Slashed {
+                    account: slashed,
+                    offense,
+                    amount,
+                    ..
+                } if Some(slashed) == account.as_ref() => {
+                    let offense = match offense {
+                        Offense::DoubleVote => "voting both ways on a block",
+                        Offense::DoubleProposal => "signing two blocks at one height",
+                    };
+                    format!("You were slashed {} stake for {}", amount, offense)
+                }
+                StateEvent::RelationChanged { link, .. }
+                    if link.from == self.id || link.to == self.id =>
+                {
+                    let other = if link.from == self.id {
+                        &link.to
+                    } else {
+                        &link.from
+                    };
+                    let relation = match link.tally.relation {
+                        Relation::Alliance => "allies",
+                        Relation::Feud => "rivals",
+                        Relation::Neutral => "neutral again",
+                    };
+                    format!("You and {} are now {}", other, relation)
+                }
+                StateEvent::BribeSettled {
+                    id,
+                    validator,
+                    outcome,
+                    amount,
+                    ..
+                } if *validator == self.id => match outcome {
+                    BribeOutcome::Paid => format!("You pocketed a bribe of {} (#{})", amount, id),
+                    BribeOutcome::Refunded => {
+                        format!("A bribe of {} (#{}) went back to its briber", amount, id)
+                    }
+                },
+                _ => continue,
+            };
+            self.remember(event.height(), MemoryKind::Event, text);
+        }
+        self.seen_height = height;
+    }
+
+    fn remember(&mut self, height: u64, kind: MemoryKind, text: String) {
+        if let Err(e) = self.memory.remember(height, kind, text) {
+            warn!("{} failed to persist memory: {}", self.id, e);
+        }
+    }
+
     fn sign_vote(&self, block_hash: &[u8; 32], approve: bool) -> Result<[u8; 64]> {
         let mut message = Vec::new();
         message.extend_from_slice(block_hash);
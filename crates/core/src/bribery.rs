@@ -0,0 +1,163 @@
+use crate::payload::TxPayload;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Bribe actions carried in a transaction payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BribeAction {
+    /// Escrow `amount` of the sender's balance for `validator` voting `approve` on a block
+    Offer {
+        #[serde(with = "crate::hex_serde")]
+        block_hash: [u8; 32],
+        /// Agent id of the validator
+        validator: String,
+        approve: bool,
+        amount: u64,
+        #[serde(default)]
+        message: String,
+    },
+}
+
+impl BribeAction {
+    /// Encode as a transaction payload
+    pub fn encode(&self) -> Vec<u8> {
+        TxPayload::Bribe(self.clone()).encode()
+    }
+
+    /// Decode a transaction payload, `None` if it is not a bribe action
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        match TxPayload::decode(payload) {
+            TxPayload::Bribe(action) => Some(action),
+            _ => None,
+        }
+    }
+}
+
+/// Tokens held for a validator until its vote on the block is final or the offer expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Escrow {
+    pub id: u64,
+    /// Hex public key refunded if the bribe is not earned
+    pub briber: String,
+    /// Agent id of the validator
+    pub validator: String,
+    #[serde(with = "crate::hex_serde")]
+    pub block_hash: [u8; 32],
+    /// Vote the briber is paying for
+    pub approve: bool,
+    pub amount: u64,
+    pub message: String,
+    /// Height at which an unsettled bribe is refunded
+    pub expires: u64,
+}
+
+/// How a bribe ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BribeOutcome {
+    /// The validator voted as asked and got the tokens
+    Paid,
+    /// The validator voted otherwise or the block never finalized, and the briber got them back
+    Refunded,
+}
+
+/// How validators voted on a finalized block
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FinalVotes {
+    pub height: u64,
+    /// Approval by agent id
+    pub votes: BTreeMap<String, bool>,
+}
+
+/// Bribes in escrow
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Bribes {
+    next_id: u64,
+    escrows: BTreeMap<u64, Escrow>,
+    /// Votes on recent finalized blocks by hex block hash, so offers on them settle the same
+    /// way on every node, however many blocks it keeps
+    #[serde(default)]
+    final_votes: BTreeMap<String, FinalVotes>,
+}
+
+impl Bribes {
+    /// Hold a bribe under a fresh id, returning the id
+    pub fn escrow(&mut self, mut escrow: Escrow) -> u64 {
+        escrow.id = self.next_id;
+        self.next_id += 1;
+        self.escrows.insert(escrow.id, escrow);
+        self.next_id - 1
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Escrow> {
+        self.escrows.get(&id)
+    }
+
+    /// Remove a bribe to settle it
+    pub fn take(&mut self, id: u64) -> Option<Escrow> {
+        self.escrows.remove(&id)
+    }
+
+    /// Every bribe in escrow, oldest first
+    pub fn escrowed(&self) -> impl Iterator<Item = &Escrow> {
+        self.escrows.values()
+    }
+
+    /// Record the votes that finalized the block at `height`, forgetting blocks `ttl` or more
+    /// heights older
+    pub fn record_votes(
+        &mut self,
+        block_hash: &[u8; 32],
+        height: u64,
+        votes: BTreeMap<String, bool>,
+        ttl: u64,
+    ) {
+        self.final_votes
+            .retain(|_, recorded| recorded.height.saturating_add(ttl) > height);
+        if ttl > 0 {
+            self.final_votes
+                .insert(hex::encode(block_hash), FinalVotes { height, votes });
+        }
+    }
+
+    /// Whether `escrow`'s validator voted as asked on its block, `None` until the block is
+    /// finalized or once it is too old to be recorded
+    pub fn earned(&self, escrow: &Escrow) -> Option<bool> {
+        let recorded = self.final_votes(&escrow.block_hash)?;
+        Some(recorded.votes.get(&escrow.validator) == Some(&escrow.approve))
+    }
+
+    /// Remove and return the bribes offered on `block_hash`
+    pub fn take_for_block(&mut self, block_hash: &[u8; 32]) -> Vec<Escrow> {
+        let offered: Vec<u64> = self
+            .escrows
+            .values()
+            .filter(|escrow| escrow.block_hash == *block_hash)
+            .map(|escrow| escrow.id)
+            .collect();
+        offered
+            .into_iter()
+            .filter_map(|id| self.escrows.remove(&id))
+            .collect()
+    }
+
+    /// Votes on a finalized block, if it is recent enough to be recorded
+    pub fn final_votes(&self, block_hash: &[u8; 32]) -> Option<&FinalVotes> {
+        self.final_votes.get(&hex::encode(block_hash))
+    }
+
+    /// Remove and return bribes that expire at `height`
+    pub fn expire(&mut self, height: u64) -> Vec<Escrow> {
+        let expired: Vec<u64> = self
+            .escrows
+            .values()
+            .filter(|escrow| escrow.expires <= height)
+            .map(|escrow| escrow.id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| self.escrows.remove(&id))
+            .collect()
+    }
+}
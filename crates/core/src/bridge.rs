@@ -0,0 +1,181 @@
+use crate::payload::TxPayload;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Value moving between L1 and ChaosChain, carried in a transaction payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeAction {
+    /// Mint an L1 deposit on ChaosChain, only accepted from the bridge relayer
+    Deposit(Deposit),
+    /// Burn the sender's balance so it can be claimed on L1
+    Withdraw {
+        amount: u64,
+        #[serde(with = "crate::hex_serde")]
+        l1_recipient: [u8; 20],
+    },
+}
+
+impl BridgeAction {
+    /// Encode as a transaction payload
+    pub fn encode(&self) -> Vec<u8> {
+        TxPayload::Bridge(self.clone()).encode()
+    }
+
+    /// Decode a transaction payload, `None` if it is not a bridge action
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        match TxPayload::decode(payload) {
+            TxPayload::Bridge(action) => Some(action),
+            _ => None,
+        }
+    }
+}
+
+/// A deposit locked in the L1 bridge contract
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deposit {
+    /// Sequence number assigned by L1, deposits are minted strictly in this order
+    pub id: u64,
+    #[serde(with = "crate::hex_serde")]
+    pub l1_sender: [u8; 20],
+    /// ChaosChain account credited with the deposit
+    #[serde(with = "crate::hex_serde")]
+    pub recipient: [u8; 32],
+    pub amount: u64,
+}
+
+/// A burned balance waiting to be claimed on L1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Withdrawal {
+    /// Position in the withdrawal tree
+    pub id: u64,
+    #[serde(with = "crate::hex_serde")]
+    pub account: [u8; 32],
+    #[serde(with = "crate::hex_serde")]
+    pub l1_recipient: [u8; 20],
+    pub amount: u64,
+    /// Height of the block that burned the balance
+    pub height: u64,
+}
+
+impl Withdrawal {
+    /// Merkle leaf, `sha256(id || account || l1_recipient || amount)` as packed by the L1 contract
+    pub fn leaf(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.id.to_be_bytes());
+        hasher.update(self.account);
+        hasher.update(self.l1_recipient);
+        hasher.update(self.amount.to_be_bytes());
+        hasher.finalize().into()
+    }
+}
+
+/// Everything needed to claim a withdrawal against a posted checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalClaim {
+    /// Hash of the checkpointed block whose root the proof is against
+    #[serde(with = "crate::hex_serde")]
+    pub checkpoint: [u8; 32],
+    pub withdrawal: Withdrawal,
+    /// Sibling hashes from the leaf up to the root
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Deposit and withdrawal bookkeeping kept in chain state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BridgeLedger {
+    /// Hex public key allowed to relay deposits
+    pub relayer: Option<String>,
+    /// Id of the next deposit to mint
+    pub next_deposit_id: u64,
+    /// Every withdrawal, in tree order
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+impl BridgeLedger {
+    /// Withdrawal tree leaves for blocks up to and including `height`
+    pub fn leaves(&self, height: u64) -> Vec<[u8; 32]> {
+        self.withdrawals
+            .iter()
+            .take_while(|withdrawal| withdrawal.height <= height)
+            .map(Withdrawal::leaf)
+            .collect()
+    }
+
+    /// Root of the withdrawal tree as of `height`
+    pub fn root(&self, height: u64) -> [u8; 32] {
+        merkle_root(&self.leaves(height))
+    }
+
+    /// Proof that withdrawal `id` is in the tree as of `height`
+    pub fn proof(&self, id: u64, height: u64) -> Option<Vec<[u8; 32]>> {
+        let leaves = self.leaves(height);
+        let index = usize::try_from(id)
+            .ok()
+            .filter(|index| *index < leaves.len())?;
+        Some(merkle_proof(&leaves, index))
+    }
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Parent level of a Merkle tree, pairing an odd last node with itself
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+/// Sha256 Merkle root, all zeros for an empty tree
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Sibling hashes proving `leaves[index]`, see `verify_merkle_proof`
+pub fn merkle_proof(leaves: &[[u8; 32]], mut index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        let sibling = if index.is_multiple_of(2) {
+            level.get(index + 1).unwrap_or(&level[index])
+        } else {
+            &level[index - 1]
+        };
+        proof.push(*sibling);
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+/// Check that `leaf` sits at `index` in the tree with `root`
+pub fn verify_merkle_proof(
+    leaf: [u8; 32],
+    mut index: u64,
+    proof: &[[u8; 32]],
+    root: [u8; 32],
+) -> bool {
+    let mut node = leaf;
+    for sibling in proof {
+        node = if index.is_multiple_of(2) {
+            hash_pair(&node, sibling)
+        } else {
+            hash_pair(sibling, &node)
+        };
+        index /= 2;
+    }
+    node == root
+}
@@ -0,0 +1,111 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+/// Comments and questions one agent may post in a single block's discussion; answers are
+/// limited to one per question instead
+pub const MAX_POSTS_PER_AGENT: usize = 3;
+
+/// What a discussion post is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentKind {
+    /// A remark about the proposal
+    Comment,
+    /// A question for the producer
+    Question,
+    /// The producer answering a question
+    Answer,
+}
+
+/// One post in the discussion of a proposed block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub author: String,
+    pub kind: CommentKind,
+    pub text: String,
+    /// Index of the post this replies to in the transcript
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<usize>,
+    /// Author's signature over the post and the block it is about, see `Comment::sign`
+    #[serde(with = "crate::base64_serde")]
+    pub signature: [u8; 64],
+}
+
+impl Comment {
+    /// Fill in `signature` for a post in the discussion of `block_hash`
+    pub fn sign(&mut self, block_hash: &[u8; 32], signing_key: &SigningKey) {
+        self.signature = signing_key.sign(&self.message(block_hash)).to_bytes();
+    }
+
+    /// Check `signature` against the author's key
+    pub fn verify(&self, block_hash: &[u8; 32], key: &VerifyingKey) -> bool {
+        key.verify(
+            &self.message(block_hash),
+            &Signature::from_bytes(&self.signature),
+        )
+        .is_ok()
+    }
+
+    /// What the author signs: `block_hash || kind || reply_to || author || text`, with the
+    /// variable-length fields prefixed by their length
+    fn message(&self, block_hash: &[u8; 32]) -> Vec<u8> {
+        let mut message = block_hash.to_vec();
+        message.push(self.kind as u8);
+        match self.reply_to {
+            Some(parent) => {
+                message.push(1);
+                message.extend_from_slice(&(parent as u64).to_be_bytes());
+            }
+            None => message.push(0),
+        }
+        for field in [&self.author, &self.text] {
+            message.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            message.extend_from_slice(field.as_bytes());
+        }
+        message
+    }
+}
+
+/// Questions in `transcript` nobody has answered yet, with their indices
+pub fn unanswered(transcript: &[Comment]) -> Vec<(usize, &Comment)> {
+    transcript
+        .iter()
+        .enumerate()
+        .filter(|(i, comment)| {
+            comment.kind == CommentKind::Question
+                && !transcript
+                    .iter()
+                    .any(|reply| reply.kind == CommentKind::Answer && reply.reply_to == Some(*i))
+        })
+        .collect()
+}
+
+/// A transcript phrased for a prompt, posts numbered from 1
+pub fn describe(transcript: &[Comment]) -> String {
+    if transcript.is_empty() {
+        return "Nobody has said anything yet.".to_string();
+    }
+    transcript
+        .iter()
+        .enumerate()
+        .map(|(i, comment)| {
+            let verb = match comment.kind {
+                CommentKind::Comment => "says",
+                CommentKind::Question => "asks",
+                CommentKind::Answer => "answers",
+            };
+            match comment.reply_to {
+                Some(parent) => format!(
+                    "#{} {} {} (re #{}): {}",
+                    i + 1,
+                    comment.author,
+                    verb,
+                    parent + 1,
+                    comment.text
+                ),
+                None => format!("#{} {} {}: {}", i + 1, comment.author, verb, comment.text),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,237 @@
+use crate::payload::TxPayload;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Longest rule text a proposal may carry, in bytes
+pub const MAX_RULE_LENGTH: usize = 1_000;
+
+/// Why a governance action was ignored
+#[derive(Debug, Error, PartialEq)]
+pub enum GovernanceError {
+    #[error("Proposed rules must be between 1 and {MAX_RULE_LENGTH} bytes")]
+    InvalidText,
+    #[error("The same rules are already proposed in proposal {0}")]
+    Duplicate(u64),
+    #[error("{proposer} already has proposal {id} pending")]
+    AlreadyPending { proposer: String, id: u64 },
+    #[error("{0} has no stake to vote with")]
+    NotStaked(String),
+    #[error("No proposal {0}")]
+    UnknownProposal(u64),
+    #[error("Proposal {0} is no longer pending")]
+    Closed(u64),
+    #[error("{voter} already voted on proposal {id}")]
+    AlreadyVoted { voter: String, id: u64 },
+}
+
+/// Governance actions carried in a transaction payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GovernanceAction {
+    /// Propose replacing the standing rules with new text
+    ProposeRule { text: String },
+    /// Stake-weighted vote on a pending proposal
+    VoteRule { proposal_id: u64, approve: bool },
+}
+
+impl GovernanceAction {
+    /// Encode as a transaction payload
+    pub fn encode(&self) -> Vec<u8> {
+        TxPayload::Governance(self.clone()).encode()
+    }
+
+    /// Decode a transaction payload, `None` if it is not a governance action
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        match TxPayload::decode(payload) {
+            TxPayload::Governance(action) => Some(action),
+            _ => None,
+        }
+    }
+}
+
+/// Where a rule proposal stands
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Pending,
+    Ratified { activation_height: u64 },
+    Rejected,
+    /// Still pending after the proposal lifetime
+    Expired,
+}
+
+/// A proposed amendment to the standing rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleProposal {
+    pub id: u64,
+    pub text: String,
+    /// Hex public key of the proposer
+    pub proposer: String,
+    /// Height of the block that carried the proposal
+    pub proposed_at: u64,
+    /// Balance the proposer put down, returned if the rules are ratified
+    #[serde(default)]
+    pub deposit: u64,
+    /// Votes by hex validator key
+    pub votes: BTreeMap<String, bool>,
+    pub approve_stake: u64,
+    pub reject_stake: u64,
+    pub status: ProposalStatus,
+}
+
+/// A ratified version of the rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleAmendment {
+    /// Proposal that introduced this text, `None` for the genesis rules
+    pub proposal_id: Option<u64>,
+    pub text: String,
+    /// Height at which the proposal crossed the stake threshold
+    pub ratified_at: u64,
+    /// First height at which producers follow this text
+    pub activation_height: u64,
+}
+
+/// Rule proposals and the full amendment history
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Governance {
+    pub proposals: Vec<RuleProposal>,
+    pub amendments: Vec<RuleAmendment>,
+}
+
+impl Governance {
+    /// Record the genesis message as the first version of the rules
+    pub fn set_genesis_rule(&mut self, text: String) {
+        if self.amendments.is_empty() {
+            self.amendments.push(RuleAmendment {
+                proposal_id: None,
+                text,
+                ratified_at: 0,
+                activation_height: 0,
+            });
+        }
+    }
+
+    /// The rules in force at `height`
+    pub fn active_rule(&self, height: u64) -> Option<&RuleAmendment> {
+        self.amendments
+            .iter()
+            .filter(|amendment| amendment.activation_height <= height)
+            .max_by_key(|amendment| (amendment.activation_height, amendment.ratified_at))
+    }
+
+    /// Proposals still collecting votes
+    pub fn pending(&self) -> impl Iterator<Item = &RuleProposal> {
+        self.proposals
+            .iter()
+            .filter(|proposal| proposal.status == ProposalStatus::Pending)
+    }
+
+    /// Apply an action from `sender` included in the block at `height`, returning the id of
+    /// the proposal it ratified, if any
+    ///
+    /// `stakes` holds the stake of every validator by hex key; votes from keys without
+    /// stake are refused. A proposer may have one proposal pending at a time, recorded with
+    /// the `deposit` it put down. A proposal is ratified once approving stake reaches
+    /// `threshold` of the total and becomes active `activation_delay` blocks later.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(
+        &mut self,
+        sender: &str,
+        action: GovernanceAction,
+        height: u64,
+        stakes: &BTreeMap<String, u64>,
+        deposit: u64,
+        threshold: f64,
+        activation_delay: u64,
+    ) -> Result<Option<u64>, GovernanceError> {
+        match action {
+            GovernanceAction::ProposeRule { text } => {
+                if text.trim().is_empty() || text.len() > MAX_RULE_LENGTH {
+                    return Err(GovernanceError::InvalidText);
+                }
+                if let Some(pending) = self.pending().find(|proposal| proposal.text == text) {
+                    return Err(GovernanceError::Duplicate(pending.id));
+                }
+                if let Some(pending) = self.pending().find(|proposal| proposal.proposer == sender) {
+                    return Err(GovernanceError::AlreadyPending {
+                        proposer: sender.to_string(),
+                        id: pending.id,
+                    });
+                }
+
+                let id = self.proposals.len() as u64;
+                self.proposals.push(RuleProposal {
+                    id,
+                    text,
+                    proposer: sender.to_string(),
+                    proposed_at: height,
+                    deposit,
+                    votes: BTreeMap::new(),
+                    approve_stake: 0,
+                    reject_stake: 0,
+                    status: ProposalStatus::Pending,
+                });
+                Ok(None)
+            }
+            GovernanceAction::VoteRule {
+                proposal_id,
+                approve,
+            } => {
+                let Some(stake) = stakes.get(sender).copied() else {
+                    return Err(GovernanceError::NotStaked(sender.to_string()));
+                };
+                let Some(proposal) = self.proposals.get_mut(proposal_id as usize) else {
+                    return Err(GovernanceError::UnknownProposal(proposal_id));
+                };
+                if proposal.status != ProposalStatus::Pending {
+                    return Err(GovernanceError::Closed(proposal_id));
+                }
+                if proposal.votes.contains_key(sender) {
+                    return Err(GovernanceError::AlreadyVoted {
+                        voter: sender.to_string(),
+                        id: proposal_id,
+                    });
+                }
+
+                proposal.votes.insert(sender.to_string(), approve);
+                if approve {
+                    proposal.approve_stake = proposal.approve_stake.saturating_add(stake);
+                } else {
+                    proposal.reject_stake = proposal.reject_stake.saturating_add(stake);
+                }
+
+                let total_stake: u64 = stakes.values().sum();
+                let threshold_stake = (total_stake as f64 * threshold).ceil() as u64;
+
+                if proposal.approve_stake >= threshold_stake {
+                    let activation_height = height + activation_delay;
+                    proposal.status = ProposalStatus::Ratified { activation_height };
+                    self.amendments.push(RuleAmendment {
+                        proposal_id: Some(proposal.id),
+                        text: proposal.text.clone(),
+                        ratified_at: height,
+                        activation_height,
+                    });
+                    return Ok(Some(proposal_id));
+                } else if proposal.reject_stake > total_stake.saturating_sub(threshold_stake) {
+                    // Not enough stake left to ever ratify it
+                    proposal.status = ProposalStatus::Rejected;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Close proposals still pending `ttl` blocks after they were made, so their proposers
+    /// can try again
+    pub fn expire(&mut self, height: u64, ttl: u64) {
+        for proposal in self.proposals.iter_mut() {
+            if proposal.status == ProposalStatus::Pending
+                && height >= proposal.proposed_at.saturating_add(ttl)
+            {
+                proposal.status = ProposalStatus::Expired;
+            }
+        }
+    }
+}
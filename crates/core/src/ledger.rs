@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Ledger errors
+#[derive(Debug, Error, PartialEq)]
+pub enum LedgerError {
+    #[error("Insufficient funds: {account} holds {balance}, needs {amount}")]
+    InsufficientFunds {
+        account: String,
+        balance: u64,
+        amount: u64,
+    },
+    #[error("Balance overflow for {0}")]
+    Overflow(String),
+    #[error("Stale nonce for {account}: expected at least {expected}, got {got}")]
+    StaleNonce {
+        account: String,
+        expected: u64,
+        got: u64,
+    },
+    #[error("{0} has used up every nonce")]
+    NoncesExhausted(String),
+}
+
+/// Balance and replay protection for one account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Account {
+    pub balance: u64,
+    /// Lowest nonce the account may use next
+    pub nonce: u64,
+}
+
+/// Accounts by hex public key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Ledger {
+    accounts: BTreeMap<String, Account>,
+}
+
+impl Ledger {
+    /// Account state, all zeros for an account never seen
+    pub fn account(&self, account: &str) -> Account {
+        self.accounts.get(account).copied().unwrap_or_default()
+    }
+
+    pub fn balance(&self, account: &str) -> u64 {
+        self.account(account).balance
+    }
+
+    /// Every known account, ordered by key
+    pub fn accounts(&self) -> impl Iterator<Item = (&String, &Account)> {
+        self.accounts.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Sum of every balance
+    pub fn total_supply(&self) -> u128 {
+        self.accounts
+            .values()
+            .map(|account| account.balance as u128)
+            .sum()
+    }
+
+    pub fn credit(&mut self, account: &str, amount: u64) -> Result<(), LedgerError> {
+        let entry = self.accounts.entry(account.to_string()).or_default();
+        entry.balance = entry
+            .balance
+            .checked_add(amount)
+            .ok_or_else(|| LedgerError::Overflow(account.to_string()))?;
+        Ok(())
+    }
+
+    pub fn debit(&mut self, account: &str, amount: u64) -> Result<(), LedgerError> {
+        let balance = self.balance(account);
+        if balance < amount {
+            return Err(LedgerError::InsufficientFunds {
+                account: account.to_string(),
+                balance,
+                amount,
+            });
+        }
+        if let Some(entry) = self.accounts.get_mut(account) {
+            entry.balance -= amount;
+        }
+        Ok(())
+    }
+
+    /// Move `amount` between accounts, changing nothing if either side would fail
+    pub fn transfer(&mut self, from: &str, to: &str, amount: u64) -> Result<(), LedgerError> {
+        let balance = self.balance(from);
+        if balance < amount {
+            return Err(LedgerError::InsufficientFunds {
+                account: from.to_string(),
+                balance,
+                amount,
+            });
+        }
+        if from != to && self.balance(to).checked_add(amount).is_none() {
+            return Err(LedgerError::Overflow(to.to_string()));
+        }
+
+        self.debit(from, amount)?;
+        self.credit(to, amount)
+    }
+
+    /// Check that `nonce` has not been used by `account`
+    ///
+    /// The last nonce is never usable: the account could not move past it, so its
+    /// transactions would replay forever.
+    pub fn check_nonce(&self, account: &str, nonce: u64) -> Result<(), LedgerError> {
+        if nonce == u64::MAX {
+            return Err(LedgerError::NoncesExhausted(account.to_string()));
+        }
+        let expected = self.account(account).nonce;
+        if nonce < expected {
+            return Err(LedgerError::StaleNonce {
+                account: account.to_string(),
+                expected,
+                got: nonce,
+            });
+        }
+        Ok(())
+    }
+
+    /// Mark `nonce` and everything below it as used by `account`
+    pub fn advance_nonce(&mut self, account: &str, nonce: u64) {
+        let entry = self.accounts.entry(account.to_string()).or_default();
+        entry.nonce = entry.nonce.max(nonce.saturating_add(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn used_nonces_and_the_last_nonce_are_refused() {
+        let mut ledger = Ledger::default();
+        ledger.check_nonce("alice", 5).unwrap();
+        ledger.advance_nonce("alice", 5);
+        assert!(matches!(
+            ledger.check_nonce("alice", 5),
+            Err(LedgerError::StaleNonce { expected: 6, .. })
+        ));
+        ledger.check_nonce("alice", 6).unwrap();
+
+        let last = u64::MAX - 1;
+        ledger.check_nonce("alice", last).unwrap();
+        ledger.advance_nonce("alice", last);
+        assert_eq!(ledger.account("alice").nonce, u64::MAX);
+        assert_eq!(
+            ledger.check_nonce("alice", u64::MAX),
+            Err(LedgerError::NoncesExhausted("alice".to_string()))
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
@@ -18,10 +19,18 @@ pub enum Error {
 /// Network message types for P2P communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NetworkMessage {
-    NewBlock(Block),
+    NewBlock(Box<Block>),
     NewTransaction(Transaction),
     Chat { from: String, message: String },
     AgentReasoning { agent: String, reasoning: String },
+    /// Ask peers for a meme by hash
+    MemeRequest(memes::MemeHash),
+    /// Meme bytes answering a request
+    Meme {
+        hash: memes::MemeHash,
+        #[serde(with = "memes::base64_bytes")]
+        data: Vec<u8>,
+    },
 }
 
 /// A transaction in ChaosChain can be anything
@@ -39,8 +48,40 @@ pub struct Transaction {
     pub signature: [u8; 64],
 }
 
+impl Transaction {
+    /// Create a transaction signed over (nonce || payload)
+    pub fn new_signed(signing_key: &SigningKey, nonce: u64, payload: Vec<u8>) -> Self {
+        let mut to_sign = nonce.to_be_bytes().to_vec();
+        to_sign.extend_from_slice(&payload);
+
+        Self {
+            sender: signing_key.verifying_key().to_bytes(),
+            nonce,
+            signature: signing_key.sign(&to_sign).to_bytes(),
+            payload,
+        }
+    }
+
+    /// Decode what this transaction does
+    pub fn decode_payload(&self) -> payload::TxPayload {
+        payload::TxPayload::decode(&self.payload)
+    }
+
+    /// Check the signature against the sender key
+    pub fn verify(&self) -> bool {
+        let Ok(key) = VerifyingKey::from_bytes(&self.sender) else {
+            return false;
+        };
+        let mut signed = self.nonce.to_be_bytes().to_vec();
+        signed.extend_from_slice(&self.payload);
+
+        key.verify(&signed, &Signature::from_bytes(&self.signature))
+            .is_ok()
+    }
+}
+
 /// A block proposal in ChaosChain
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Block {
     /// Previous block hash
     #[serde(with = "hex_serde")]
@@ -49,7 +90,8 @@ pub struct Block {
     pub height: u64,
     /// Transactions included in this block
     pub transactions: Vec<Transaction>,
-    /// The new state root after applying these transactions
+    /// Root over the whole chain state after the parent block, before any node setup
+    /// registered since; covered by the hash so the certificate vouches for it
     #[serde(with = "hex_serde")]
     pub state_root: [u8; 32],
     /// Block proposer's signature
@@ -62,6 +104,60 @@ pub struct Block {
     /// Vote details
     pub votes: HashMap<String, (bool, String)>,
     pub timestamp: u64,
+    /// Discussion held before the vote, not covered by the hash
+    #[serde(default)]
+    pub discussion: Vec<discussion::Comment>,
+    /// Meme the producer attached to the proposal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meme: Option<memes::MemeHash>,
+    /// How the producer picked the block's transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<ProductionStyle>,
+    /// Signed votes that decided the block by agent ID, not covered by the hash
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub certificate: BTreeMap<String, VoteSignature>,
+}
+
+/// A validator's vote as it signed it, so the block's approval can be checked later
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VoteSignature {
+    pub approve: bool,
+    /// Signature over `block hash || approve`
+    #[serde(with = "base64_serde")]
+    pub signature: [u8; 64],
+}
+
+/// What a node keeps of a block after pruning it: enough to follow the chain and check which
+/// votes decided it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    #[serde(with = "hex_serde")]
+    pub hash: [u8; 32],
+    #[serde(with = "hex_serde")]
+    pub parent_hash: [u8; 32],
+    pub height: u64,
+    #[serde(with = "hex_serde")]
+    pub state_root: [u8; 32],
+    pub producer_id: String,
+    pub timestamp: u64,
+    pub transaction_count: usize,
+    /// Signed votes that decided the block by agent ID
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub certificate: BTreeMap<String, VoteSignature>,
+}
+
+/// How a producer picks transactions from the mempool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProductionStyle {
+    /// A random selection
+    Chaotic,
+    /// The most dramatic transactions, boring ones left out
+    Dramatic,
+    /// Transactions of the kinds validators have approved of before
+    Strategic,
+    /// One of the others, picked anew for every block
+    Whimsical,
 }
 
 impl Block {
@@ -70,7 +166,11 @@ impl Block {
         let mut hasher = Sha256::new();
 
         // Add block fields to hasher
+        hasher.update(self.parent_hash);
         hasher.update(self.height.to_be_bytes());
+        hasher.update(self.state_root);
+        hasher.update((self.producer_id.len() as u64).to_be_bytes());
+        hasher.update(self.producer_id.as_bytes());
         for tx in &self.transactions {
             hasher.update(&tx.sender);
             hasher.update(tx.nonce.to_be_bytes());
@@ -79,6 +179,12 @@ impl Block {
         }
         hasher.update(&self.proposer_sig);
         hasher.update(&self.message);
+        if let Some(meme) = &self.meme {
+            hasher.update(meme.0);
+        }
+        if let Some(style) = self.style {
+            hasher.update([style as u8]);
+        }
 
         // Return the hash
         let result = hasher.finalize();
@@ -86,15 +192,203 @@ impl Block {
         hash.copy_from_slice(&result[..]);
         hash
     }
+
+    /// Hash the proposer signs: the block hash with `proposer_sig` zeroed
+    pub fn signing_hash(&self) -> [u8; 32] {
+        let mut unsigned = self.clone();
+        unsigned.proposer_sig = [0u8; 64];
+        unsigned.hash()
+    }
+
+    /// Fill in `proposer_sig`, a signature over `height || signing_hash`
+    pub fn sign(&mut self, signing_key: &SigningKey) {
+        let message = proposal_message(self.height, &self.signing_hash());
+        self.proposer_sig = signing_key.sign(&message).to_bytes();
+    }
+
+    /// Check `proposer_sig` against the producer's key
+    pub fn verify_proposer(&self, key: &VerifyingKey) -> bool {
+        verify_proposal(key, self.height, &self.signing_hash(), &self.proposer_sig)
+    }
+
+    /// The block's header, with its certificate
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            hash: self.hash(),
+            parent_hash: self.parent_hash,
+            height: self.height,
+            state_root: self.state_root,
+            producer_id: self.producer_id.clone(),
+            timestamp: self.timestamp,
+            transaction_count: self.transactions.len(),
+            certificate: self.certificate.clone(),
+        }
+    }
+}
+
+/// What a producer signs for a block at `height`, so a signature alone proves the height
+fn proposal_message(height: u64, signing_hash: &[u8; 32]) -> Vec<u8> {
+    let mut message = height.to_be_bytes().to_vec();
+    message.extend_from_slice(signing_hash);
+    message
+}
+
+/// Check a proposer signature without the block itself
+pub fn verify_proposal(
+    key: &VerifyingKey,
+    height: u64,
+    signing_hash: &[u8; 32],
+    signature: &[u8; 64],
+) -> bool {
+    key.verify(
+        &proposal_message(height, signing_hash),
+        &Signature::from_bytes(signature),
+    )
+    .is_ok()
+}
+
+/// Check a validator's signature over `block_hash || approve`
+pub fn verify_vote(
+    key: &VerifyingKey,
+    block_hash: &[u8; 32],
+    approve: bool,
+    signature: &[u8; 64],
+) -> bool {
+    let mut message = block_hash.to_vec();
+    message.push(approve as u8);
+    key.verify(&message, &Signature::from_bytes(signature))
+        .is_ok()
 }
 
 /// Chain state
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChainState {
-    /// Account balances
-    pub balances: Vec<(String, u64)>,
+    /// Account balances and nonces
+    pub ledger: ledger::Ledger,
     /// Block producers
     pub producers: Vec<String>,
+    /// Bonded validator stake by hex public key; changes join the validator set at the next epoch
+    pub stakes: BTreeMap<String, u64>,
+    /// Unbonding queue and validator set of every epoch
+    pub staking: staking::Staking,
+    /// Ids of the evidence already punished, hex encoded
+    pub slashed: BTreeSet<String>,
+    /// Alliances and feuds learned from block votes
+    pub social: reputation::SocialGraph,
+    /// Bribes waiting for a validator to prove its vote
+    pub bribes: bribery::Bribes,
+    /// Rule proposals and ratified amendments
+    pub governance: governance::Governance,
+    /// L1 deposits and withdrawals
+    pub bridge: bridge::BridgeLedger,
+    /// Hex public key of each agent id, as used in `Block::producer_id` and `Block::votes`
+    pub agents: BTreeMap<String, String>,
+    /// Everything the chain did on its own, such as reward payouts, oldest first
+    pub events: Vec<StateEvent>,
+}
+
+/// Who a reward was paid to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardKind {
+    /// The block's producer
+    Producer,
+    /// A validator that voted with the majority
+    Voter,
+}
+
+/// State changes that are not caused by a transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateEvent {
+    Reward {
+        height: u64,
+        /// Agent id the reward was earned by
+        agent: String,
+        /// Hex public key credited
+        account: String,
+        kind: RewardKind,
+        amount: u64,
+    },
+    /// Unbonded stake returned to a balance
+    Unbonded {
+        height: u64,
+        account: String,
+        amount: u64,
+    },
+    /// Stake burned for equivocation
+    Slashed {
+        height: u64,
+        account: String,
+        offense: slashing::Offense,
+        amount: u64,
+    },
+    /// Two agents became allies, rivals or neutral again
+    RelationChanged {
+        height: u64,
+        link: reputation::Link,
+    },
+    /// An escrowed bribe was paid to its validator or refunded to its briber
+    BribeSettled {
+        height: u64,
+        id: u64,
+        /// Agent id of the validator
+        validator: String,
+        /// Hex public key credited
+        account: String,
+        outcome: bribery::BribeOutcome,
+        amount: u64,
+    },
+    /// A new validator set takes over after the block at `height`
+    EpochStarted {
+        height: u64,
+        epoch: u64,
+        validators: usize,
+        total_stake: u64,
+    },
+}
+
+impl StateEvent {
+    /// Height of the block that caused the event
+    pub fn height(&self) -> u64 {
+        match self {
+            StateEvent::Reward { height, .. }
+            | StateEvent::Unbonded { height, .. }
+            | StateEvent::Slashed { height, .. }
+            | StateEvent::RelationChanged { height, .. }
+            | StateEvent::BribeSettled { height, .. }
+            | StateEvent::EpochStarted { height, .. } => *height,
+        }
+    }
+}
+
+/// Rewards minted for every applied block
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewardSchedule {
+    /// Paid to the block's producer
+    pub producer_reward: u64,
+    /// Split evenly among validators that voted with the stake majority
+    pub voter_reward: u64,
+    /// Both rewards halve every this many blocks, never if `None`
+    pub halving_interval: Option<u64>,
+}
+
+impl RewardSchedule {
+    /// Producer and total voter reward at `height`
+    pub fn at(&self, height: u64) -> (u64, u64) {
+        let halvings = self
+            .halving_interval
+            .filter(|interval| *interval > 0)
+            .map(|interval| height / interval)
+            .unwrap_or(0);
+        let halve = |reward: u64| {
+            u32::try_from(halvings)
+                .ok()
+                .and_then(|halvings| reward.checked_shr(halvings))
+                .unwrap_or(0)
+        };
+        (halve(self.producer_reward), halve(self.voter_reward))
+    }
 }
 
 /// Chain configuration
@@ -102,22 +396,108 @@ pub struct ChainState {
 pub struct ChainConfig {
     /// Minimum time between blocks
     pub min_block_time: u64,
-    /// Block reward (optional in chaos)
-    pub block_reward: Option<u64>,
+    /// Block rewards (optional in chaos)
+    pub rewards: Option<RewardSchedule>,
     /// Required validator signatures (default 2/3)
     pub required_signatures: f64,
+    /// Share of total stake needed to ratify a rule proposal
+    pub governance_threshold: f64,
+    /// Blocks between ratification and activation of a new rule
+    pub rule_activation_delay: u64,
+    /// Balance a proposer outside the validator set puts down, returned if the rules pass
+    pub proposal_deposit: u64,
+    /// Blocks a rule proposal collects votes before it expires
+    pub proposal_ttl: u64,
+    /// Blocks per epoch; the validator set only changes at epoch boundaries
+    pub epoch_length: u64,
+    /// Blocks between an unbond and the stake returning to the balance
+    pub unbonding_period: u64,
+    /// Smallest bonded stake that joins the validator set
+    pub min_validator_stake: u64,
+    /// Share of bonded and unbonding stake burned per proven equivocation
+    pub slash_fraction: f64,
+    /// Blocks a bribe stays in escrow before it is refunded, and how many recent blocks'
+    /// votes settle a bribe offered on them at once
+    pub bribe_ttl: u64,
 }
 
 impl Default for ChainConfig {
     fn default() -> Self {
         Self {
             min_block_time: 1000, // 1 second
-            block_reward: None,
+            rewards: None,
             required_signatures: 0.67, // 2/3
+            governance_threshold: 0.67,
+            rule_activation_delay: 2,
+            proposal_deposit: 10,
+            proposal_ttl: 100,
+            epoch_length: 10,
+            unbonding_period: 20,
+            min_validator_stake: 1,
+            slash_fraction: 0.1,
+            bribe_ttl: 20,
+        }
+    }
+}
+
+/// LLM settings for a single agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelConfig {
+    /// Model name sent to the provider
+    pub model: String,
+    /// Sampling temperature
+    pub temperature: f32,
+    /// Maximum tokens in a completion
+    pub max_tokens: u16,
+    /// Template replacing the agent's built-in prompt
+    pub prompt_template: Option<String>,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self {
+            model: "gpt-4o-mini".to_string(),
+            temperature: 0.9,
+            max_tokens: 200,
+            prompt_template: None,
         }
     }
 }
 
+impl ModelConfig {
+    /// Fill `{name}` placeholders in the configured template, or in `default` if none is set
+    pub fn render_prompt(&self, default: &str, values: &[(&str, &str)]) -> String {
+        let template = self.prompt_template.as_deref().unwrap_or(default);
+        let mut prompt = String::with_capacity(template.len());
+        let mut rest = template;
+
+        // Single pass, so placeholders inside substituted values are left alone
+        while let Some(start) = rest.find('{') {
+            prompt.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let value = after.find('}').and_then(|end| {
+                values
+                    .iter()
+                    .find(|(name, _)| *name == &after[..end])
+                    .map(|(_, value)| (end, value))
+            });
+            match value {
+                Some((end, value)) => {
+                    prompt.push_str(value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    prompt.push('{');
+                    rest = after;
+                }
+            }
+        }
+        prompt.push_str(rest);
+
+        prompt
+    }
+}
+
 // Serialization helpers
 mod hex_serde {
     use hex::{FromHex, ToHex};
@@ -169,7 +549,18 @@ mod base64_serde {
     }
 }
 
+pub mod bribery;
+pub mod bridge;
+pub mod discussion;
+pub mod governance;
+pub mod ledger;
+pub mod memes;
 pub mod mempool;
+pub mod metrics;
+pub mod payload;
+pub mod reputation;
+pub mod slashing;
+pub mod staking;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkEvent {
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Largest meme the store accepts
+pub const MAX_MEME_SIZE: usize = 1024 * 1024;
+
+/// blake3 hash of a meme's bytes, the only way votes, blocks and chat refer to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MemeHash(#[serde(with = "crate::hex_serde")] pub [u8; 32]);
+
+impl MemeHash {
+    /// Hash meme bytes
+    pub fn of(data: &[u8]) -> Self {
+        Self(*blake3::hash(data).as_bytes())
+    }
+}
+
+impl fmt::Display for MemeHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl FromStr for MemeHash {
+    type Err = MemeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        hex::decode(s)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Self)
+            .ok_or_else(|| MemeError::InvalidHash(s.to_string()))
+    }
+}
+
+/// Meme store errors
+#[derive(Debug, Error)]
+pub enum MemeError {
+    #[error("Meme is empty")]
+    Empty,
+    #[error("Meme is {0} bytes, the limit is {MAX_MEME_SIZE}")]
+    TooLarge(usize),
+    #[error("Not a meme hash: {0}")]
+    InvalidHash(String),
+    #[error("Meme bytes do not match hash {0}")]
+    HashMismatch(MemeHash),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Content-addressed memes on disk, one file per blake3 hash
+#[derive(Debug, Clone)]
+pub struct MemeStore {
+    dir: PathBuf,
+}
+
+impl MemeStore {
+    /// Open or create a store under `dir`
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn path(&self, hash: &MemeHash) -> PathBuf {
+        self.dir.join(hash.to_string())
+    }
+
+    /// Store a meme, returning its hash; storing the same bytes twice is a no-op
+    pub fn put(&self, data: &[u8]) -> Result<MemeHash, MemeError> {
+        if data.is_empty() {
+            return Err(MemeError::Empty);
+        }
+        if data.len() > MAX_MEME_SIZE {
+            return Err(MemeError::TooLarge(data.len()));
+        }
+        let hash = MemeHash::of(data);
+        let path = self.path(&hash);
+        if !path.exists() {
+            // Write then rename so readers never see a partial meme
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, data)?;
+            fs::rename(&tmp, &path)?;
+        }
+        Ok(hash)
+    }
+
+    /// Store a meme fetched from a peer, rejecting bytes that do not match `hash`
+    pub fn put_verified(&self, hash: &MemeHash, data: &[u8]) -> Result<(), MemeError> {
+        if MemeHash::of(data) != *hash {
+            return Err(MemeError::HashMismatch(*hash));
+        }
+        self.put(data).map(|_| ())
+    }
+
+    /// Read a meme, `None` if it is not stored locally or the file no longer matches its hash
+    pub fn get(&self, hash: &MemeHash) -> Result<Option<Vec<u8>>, MemeError> {
+        match fs::read(self.path(hash)) {
+            Ok(data) if MemeHash::of(&data) == *hash => Ok(Some(data)),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn contains(&self, hash: &MemeHash) -> bool {
+        self.path(hash).exists()
+    }
+}
+
+/// MIME type of a meme, sniffed from its first bytes
+pub fn content_type(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        "image/jpeg"
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        "image/gif"
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        "image/webp"
+    } else if std::str::from_utf8(data).is_ok() {
+        "text/plain; charset=utf-8"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// Serde helper carrying meme bytes as base64
+pub mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        String::deserialize(deserializer)
+            .and_then(|string| BASE64.decode(string).map_err(Error::custom))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memes_are_addressed_by_their_hash() {
+        let dir = std::env::temp_dir().join(format!("chaoschain-memes-{}", std::process::id()));
+        let store = MemeStore::open(&dir).unwrap();
+
+        let hash = store.put(b"one does not simply finalize a block").unwrap();
+        assert_eq!(hash, MemeHash::of(b"one does not simply finalize a block"));
+        assert_eq!(hash.to_string().parse::<MemeHash>().unwrap(), hash);
+        assert_eq!(
+            store.get(&hash).unwrap().as_deref(),
+            Some(&b"one does not simply finalize a block"[..])
+        );
+        assert_eq!(
+            content_type(&store.get(&hash).unwrap().unwrap()),
+            "text/plain; charset=utf-8"
+        );
+
+        // Bytes from a peer must match the hash they were requested under
+        assert!(matches!(
+            store.put_verified(&hash, b"something else"),
+            Err(MemeError::HashMismatch(_))
+        ));
+        assert!(store
+            .get(&MemeHash::of(b"something else"))
+            .unwrap()
+            .is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
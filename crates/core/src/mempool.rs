@@ -1,4 +1,4 @@
-use crate::{Error, Transaction};
+use crate::{metrics, Error, Transaction};
 use parking_lot::RwLock;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
@@ -82,21 +82,22 @@ impl Mempool {
 
         txs.insert(tx_hash, mempool_tx.clone());
         self.queue.write().push(mempool_tx);
+        metrics::global().set(metrics::MEMPOOL_SIZE, &[], txs.len() as f64);
 
         Ok(())
     }
 
     /// Get the top N transactions by priority
     pub fn get_top(&self, n: usize) -> Vec<Transaction> {
-        let txs = self.txs.read();
-        let queue = self.queue.read();
-
-        queue
-            .iter()
-            .take(n)
-            .filter(|tx| txs.contains_key(&self.hash_tx(&tx.transaction)))
-            .map(|tx| tx.transaction.clone())
-            .collect()
+        // The heap only guarantees its root, so take them in sorted order
+        self.pending().into_iter().take(n).collect()
+    }
+
+    /// Every pending transaction, highest priority first
+    pub fn pending(&self) -> Vec<Transaction> {
+        let mut pending: Vec<MempoolTx> = self.txs.read().values().cloned().collect();
+        pending.sort();
+        pending.into_iter().map(|tx| tx.transaction).collect()
     }
 
     /// Remove transactions that are included in a block
@@ -109,6 +110,8 @@ impl Mempool {
             mempool_txs.remove(&tx_hash);
             queue.retain(|mempool_tx| mempool_tx.transaction != *tx);
         }
+
+        metrics::global().set(metrics::MEMPOOL_SIZE, &[], mempool_txs.len() as f64);
     }
 
     /// Calculate transaction hash
@@ -124,30 +127,15 @@ impl Mempool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ed25519_dalek::{Keypair, SigningKey};
+    use ed25519_dalek::SigningKey;
 
     #[test]
     fn test_mempool_ordering() {
         let mempool = Mempool::new(1000);
-        let keypair = SigningKey::generate(&mut rand::thread_rng());
-        let public_key = keypair.verifying_key();
-
-        // Create transactions with different gas prices
-        let tx1 = Transaction {
-            sender: public_key,
-            nonce: 1,
-            gas_price: 10,
-            payload: vec![],
-            signature: Signature::from_bytes(&[0; 64]).unwrap(),
-        };
+        let keypair = SigningKey::from_bytes(&[1; 32]);
 
-        let tx2 = Transaction {
-            sender: public_key,
-            nonce: 2,
-            gas_price: 20,
-            payload: vec![],
-            signature: Signature::from_bytes(&[0; 64]).unwrap(),
-        };
+        let tx1 = Transaction::new_signed(&keypair, 1, vec![]);
+        let tx2 = Transaction::new_signed(&keypair, 2, vec![]);
 
         // Add transactions
         mempool.add_tx(tx1.clone(), 10).unwrap();
@@ -156,7 +144,7 @@ mod tests {
         // Check ordering
         let top_txs = mempool.get_top(2);
         assert_eq!(top_txs.len(), 2);
-        assert_eq!(top_txs[0].gas_price, 20); // Higher gas price first
-        assert_eq!(top_txs[1].gas_price, 10);
+        assert_eq!(top_txs[0], tx2); // Higher priority first
+        assert_eq!(top_txs[1], tx1);
     }
 }
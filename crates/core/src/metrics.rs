@@ -0,0 +1,326 @@
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Upper bounds (in seconds) of the latency histogram buckets
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Block height of the local chain
+pub const BLOCK_HEIGHT: &str = "chaoschain_block_height";
+/// Blocks proposed, labelled by producer
+pub const BLOCKS_PROPOSED: &str = "chaoschain_blocks_proposed_total";
+/// Blocks that reached an approving consensus, labelled by producer
+pub const BLOCKS_APPROVED: &str = "chaoschain_blocks_approved_total";
+/// Blocks that reached a rejecting consensus, labelled by producer
+pub const BLOCKS_REJECTED: &str = "chaoschain_blocks_rejected_total";
+/// Voting rounds that expired without consensus, labelled by producer
+pub const BLOCKS_TIMED_OUT: &str = "chaoschain_blocks_timed_out_total";
+/// Share of a producer's finished rounds that were approved
+pub const PRODUCER_APPROVAL_RATIO: &str = "chaoschain_producer_approval_ratio";
+/// Votes cast, labelled by validator and decision
+pub const VOTES: &str = "chaoschain_votes_total";
+/// Share of a validator's votes that approved the block
+pub const VALIDATOR_APPROVAL_RATIO: &str = "chaoschain_validator_approval_ratio";
+/// Time from the start of a voting round until consensus
+pub const CONSENSUS_ROUND_SECONDS: &str = "chaoschain_consensus_round_seconds";
+/// Transactions currently waiting in the mempool
+pub const MEMPOOL_SIZE: &str = "chaoschain_mempool_size";
+/// LLM requests issued, labelled by agent
+pub const LLM_CALLS: &str = "chaoschain_llm_calls_total";
+/// LLM requests that failed, labelled by agent
+pub const LLM_ERRORS: &str = "chaoschain_llm_errors_total";
+/// LLM request latency, labelled by agent
+pub const LLM_LATENCY_SECONDS: &str = "chaoschain_llm_latency_seconds";
+/// LLM tokens used, labelled by agent and kind (prompt or completion)
+pub const LLM_TOKENS: &str = "chaoschain_llm_tokens_total";
+/// Dollars spent on LLM calls, labelled by agent
+pub const LLM_COST_DOLLARS: &str = "chaoschain_llm_cost_dollars_total";
+/// Connected p2p peers
+pub const P2P_PEERS: &str = "chaoschain_p2p_peers";
+
+/// Metric kind, as reported in the `# TYPE` line
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Histogram => "histogram",
+        }
+    }
+}
+
+/// Help text and kind of every known metric
+const DESCRIPTORS: [(&str, Kind, &str); 16] = [
+    (BLOCK_HEIGHT, Kind::Gauge, "Current block height"),
+    (BLOCKS_PROPOSED, Kind::Counter, "Blocks proposed per producer"),
+    (BLOCKS_APPROVED, Kind::Counter, "Blocks approved per producer"),
+    (BLOCKS_REJECTED, Kind::Counter, "Blocks rejected per producer"),
+    (BLOCKS_TIMED_OUT, Kind::Counter, "Voting rounds timed out per producer"),
+    (PRODUCER_APPROVAL_RATIO, Kind::Gauge, "Approved share of finished rounds per producer"),
+    (VOTES, Kind::Counter, "Votes cast per validator"),
+    (VALIDATOR_APPROVAL_RATIO, Kind::Gauge, "Approving share of votes per validator"),
+    (CONSENSUS_ROUND_SECONDS, Kind::Histogram, "Consensus round latency"),
+    (MEMPOOL_SIZE, Kind::Gauge, "Transactions in the mempool"),
+    (LLM_CALLS, Kind::Counter, "LLM calls per agent"),
+    (LLM_ERRORS, Kind::Counter, "Failed LLM calls per agent"),
+    (LLM_LATENCY_SECONDS, Kind::Histogram, "LLM call latency per agent"),
+    (LLM_TOKENS, Kind::Counter, "LLM tokens used per agent"),
+    (LLM_COST_DOLLARS, Kind::Counter, "Dollars spent on LLM calls per agent"),
+    (P2P_PEERS, Kind::Gauge, "Connected p2p peers"),
+];
+
+/// Histogram state for one label set
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// Values of one metric, keyed by rendered label set
+#[derive(Debug, Default)]
+struct Family {
+    values: BTreeMap<String, f64>,
+    histograms: BTreeMap<String, Histogram>,
+}
+
+/// Process-wide metrics registry rendered in the Prometheus text format
+#[derive(Debug, Default)]
+pub struct Metrics {
+    families: RwLock<BTreeMap<&'static str, Family>>,
+}
+
+impl Metrics {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Increment a counter by one
+    pub fn inc(&self, name: &'static str, labels: &[(&str, &str)]) {
+        self.add(name, labels, 1.0);
+    }
+
+    /// Increment a counter by `value`
+    pub fn add(&self, name: &'static str, labels: &[(&str, &str)], value: f64) {
+        let mut families = self.families.write();
+        *families
+            .entry(name)
+            .or_default()
+            .values
+            .entry(render_labels(labels))
+            .or_insert(0.0) += value;
+    }
+
+    /// Set a gauge to `value`
+    pub fn set(&self, name: &'static str, labels: &[(&str, &str)], value: f64) {
+        let mut families = self.families.write();
+        families
+            .entry(name)
+            .or_default()
+            .values
+            .insert(render_labels(labels), value);
+    }
+
+    /// Record one observation in a histogram
+    pub fn observe(&self, name: &'static str, labels: &[(&str, &str)], value: f64) {
+        let mut families = self.families.write();
+        let histogram = families
+            .entry(name)
+            .or_default()
+            .histograms
+            .entry(render_labels(labels))
+            .or_default();
+
+        for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS.iter()) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        histogram.sum += value;
+        histogram.count += 1;
+    }
+
+    /// Current value of a counter or gauge (0 if never recorded)
+    pub fn get(&self, name: &'static str, labels: &[(&str, &str)]) -> f64 {
+        self.families
+            .read()
+            .get(name)
+            .and_then(|family| family.values.get(&render_labels(labels)).copied())
+            .unwrap_or(0.0)
+    }
+
+    /// Render every recorded metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let families = self.families.read();
+        let mut out = String::new();
+
+        for (name, kind, help) in DESCRIPTORS.iter() {
+            let Some(family) = families.get(name) else {
+                continue;
+            };
+
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind.as_str());
+
+            for (labels, value) in &family.values {
+                let _ = writeln!(out, "{}{} {}", name, wrap_labels(labels), value);
+            }
+
+            for (labels, histogram) in &family.histograms {
+                for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS.iter()) {
+                    let le = format!("le=\"{}\"", bound);
+                    let _ = writeln!(
+                        out,
+                        "{}_bucket{} {}",
+                        name,
+                        wrap_labels(&join_labels(labels, &le)),
+                        count
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    name,
+                    wrap_labels(&join_labels(labels, "le=\"+Inf\"")),
+                    histogram.count
+                );
+                let _ = writeln!(out, "{}_sum{} {}", name, wrap_labels(labels), histogram.sum);
+                let _ = writeln!(
+                    out,
+                    "{}_count{} {}",
+                    name,
+                    wrap_labels(labels),
+                    histogram.count
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// The global registry shared by every component in the process
+pub fn global() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Record the outcome and latency of one LLM request made by `agent`
+pub fn record_llm_call(agent: &str, latency: Duration, ok: bool) {
+    let m = global();
+    let labels = [("agent", agent)];
+    m.inc(LLM_CALLS, &labels);
+    if !ok {
+        m.inc(LLM_ERRORS, &labels);
+    }
+    m.observe(LLM_LATENCY_SECONDS, &labels, latency.as_secs_f64());
+}
+
+/// Record the tokens and dollars an agent's LLM call used
+pub fn record_llm_usage(agent: &str, prompt_tokens: u64, completion_tokens: u64, cost: f64) {
+    let m = global();
+    m.add(
+        LLM_TOKENS,
+        &[("agent", agent), ("kind", "prompt")],
+        prompt_tokens as f64,
+    );
+    m.add(
+        LLM_TOKENS,
+        &[("agent", agent), ("kind", "completion")],
+        completion_tokens as f64,
+    );
+    m.add(LLM_COST_DOLLARS, &[("agent", agent)], cost);
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "{}=\"{}\"",
+                key,
+                value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn join_labels(labels: &str, extra: &str) -> String {
+    if labels.is_empty() {
+        extra.to_string()
+    } else {
+        format!("{},{}", labels, extra)
+    }
+}
+
+fn wrap_labels(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_add_up_gauges_overwrite_and_render_as_prometheus_text() {
+        let metrics = Metrics::new();
+        let alice = [("producer", "alice")];
+        metrics.inc(BLOCKS_PROPOSED, &alice);
+        metrics.add(BLOCKS_PROPOSED, &alice, 2.0);
+        metrics.inc(BLOCKS_PROPOSED, &[("producer", "bob \"the\" builder")]);
+        metrics.set(BLOCK_HEIGHT, &[], 7.0);
+        metrics.set(BLOCK_HEIGHT, &[], 5.0);
+        metrics.observe(CONSENSUS_ROUND_SECONDS, &[], 0.3);
+        metrics.observe(CONSENSUS_ROUND_SECONDS, &[], 100.0);
+
+        assert_eq!(metrics.get(BLOCKS_PROPOSED, &alice), 3.0);
+        assert_eq!(metrics.get(BLOCK_HEIGHT, &[]), 5.0);
+        assert_eq!(metrics.get(VOTES, &[]), 0.0);
+
+        let rendered = metrics.render();
+        let lines: Vec<&str> = rendered.lines().collect();
+        for expected in [
+            "# HELP chaoschain_block_height Current block height",
+            "# TYPE chaoschain_block_height gauge",
+            "chaoschain_block_height 5",
+            "# TYPE chaoschain_blocks_proposed_total counter",
+            "chaoschain_blocks_proposed_total{producer=\"alice\"} 3",
+            "chaoschain_blocks_proposed_total{producer=\"bob \\\"the\\\" builder\"} 1",
+            "# TYPE chaoschain_consensus_round_seconds histogram",
+            "chaoschain_consensus_round_seconds_bucket{le=\"0.25\"} 0",
+            "chaoschain_consensus_round_seconds_bucket{le=\"0.5\"} 1",
+            "chaoschain_consensus_round_seconds_bucket{le=\"60\"} 1",
+            "chaoschain_consensus_round_seconds_bucket{le=\"+Inf\"} 2",
+            "chaoschain_consensus_round_seconds_sum 100.3",
+            "chaoschain_consensus_round_seconds_count 2",
+        ] {
+            assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, rendered);
+        }
+
+        // Metrics never recorded are left out, and HELP comes right before TYPE
+        assert!(!rendered.contains(VOTES));
+        let help = lines
+            .iter()
+            .position(|line| line.starts_with("# HELP chaoschain_block_height"))
+            .unwrap();
+        assert_eq!(lines[help + 1], "# TYPE chaoschain_block_height gauge");
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    bribery::BribeAction, bridge::BridgeAction, governance::GovernanceAction, memes::MemeHash,
+    slashing::Evidence, staking::StakeAction,
+};
+use serde::{Deserialize, Serialize};
+
+/// What a transaction does, decoded from its payload
+///
+/// Typed payloads are JSON objects tagged by kind, e.g. `{"transfer": {...}}`.
+/// Anything else is carried as raw bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxPayload {
+    /// Move native tokens to another account
+    Transfer {
+        to: [u8; 32],
+        amount: u64,
+    },
+    /// A message for the chain
+    Chat {
+        message: String,
+        meme: Option<MemeHash>,
+    },
+    Governance(GovernanceAction),
+    Bridge(BridgeAction),
+    Stake(StakeAction),
+    Bribe(BribeAction),
+    /// Proof of equivocation, accepted from any sender
+    Evidence(Evidence),
+    /// Arbitrary bytes the chain does not interpret
+    Raw(Vec<u8>),
+}
+
+/// Wire format of the typed payloads
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Tagged {
+    Transfer {
+        #[serde(with = "crate::hex_serde")]
+        to: [u8; 32],
+        amount: u64,
+    },
+    Chat {
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        meme: Option<MemeHash>,
+    },
+    Governance(GovernanceAction),
+    Bridge(BridgeAction),
+    Stake(StakeAction),
+    Bribe(BribeAction),
+    Evidence(Evidence),
+}
+
+impl TxPayload {
+    /// Name of the kind of payload, as tagged on the wire
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TxPayload::Transfer { .. } => "transfer",
+            TxPayload::Chat { .. } => "chat",
+            TxPayload::Governance(_) => "governance",
+            TxPayload::Bridge(_) => "bridge",
+            TxPayload::Stake(_) => "stake",
+            TxPayload::Bribe(_) => "bribe",
+            TxPayload::Evidence(_) => "evidence",
+            TxPayload::Raw(_) => "raw",
+        }
+    }
+
+    /// Encode as transaction payload bytes
+    pub fn encode(&self) -> Vec<u8> {
+        let tagged = match self.clone() {
+            TxPayload::Transfer { to, amount } => Tagged::Transfer { to, amount },
+            TxPayload::Chat { message, meme } => Tagged::Chat { message, meme },
+            TxPayload::Governance(action) => Tagged::Governance(action),
+            TxPayload::Bridge(action) => Tagged::Bridge(action),
+            TxPayload::Stake(action) => Tagged::Stake(action),
+            TxPayload::Bribe(action) => Tagged::Bribe(action),
+            TxPayload::Evidence(evidence) => Tagged::Evidence(evidence),
+            TxPayload::Raw(bytes) => return bytes,
+        };
+        serde_json::to_vec(&tagged).expect("typed payloads always serialize")
+    }
+
+    /// Decode transaction payload bytes, falling back to `Raw`
+    pub fn decode(payload: &[u8]) -> Self {
+        match serde_json::from_slice::<Tagged>(payload) {
+            Ok(Tagged::Transfer { to, amount }) => TxPayload::Transfer { to, amount },
+            Ok(Tagged::Chat { message, meme }) => TxPayload::Chat { message, meme },
+            Ok(Tagged::Governance(action)) => TxPayload::Governance(action),
+            Ok(Tagged::Bridge(action)) => TxPayload::Bridge(action),
+            Ok(Tagged::Stake(action)) => TxPayload::Stake(action),
+            Ok(Tagged::Bribe(action)) => TxPayload::Bribe(action),
+            Ok(Tagged::Evidence(evidence)) => TxPayload::Evidence(evidence),
+            Err(_) => TxPayload::Raw(payload.to_vec()),
+        }
+    }
+}
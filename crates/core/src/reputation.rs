@@ -0,0 +1,211 @@
+use crate::Block;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Votes two agents need in common before they count as allies or rivals
+pub const MIN_SHARED_VOTES: u64 = 5;
+/// Agreement rate at or above which two agents are allied
+pub const ALLIANCE_RATE: f64 = 0.75;
+/// Agreement rate at or below which two agents are feuding
+pub const FEUD_RATE: f64 = 0.25;
+
+/// How two agents get along
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    #[default]
+    Neutral,
+    Alliance,
+    Feud,
+}
+
+/// What a tally between two agents counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    /// Two validators voting the same way on a block
+    Peers,
+    /// A validator approving a producer's block
+    Support,
+}
+
+/// Agreement between two agents over the votes they share
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tally {
+    pub agreed: u64,
+    pub total: u64,
+    pub relation: Relation,
+}
+
+impl Tally {
+    pub fn rate(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.agreed as f64 / self.total as f64
+    }
+
+    /// Count one shared vote, returning the new relation if it changed
+    fn record(&mut self, agreed: bool) -> Option<Relation> {
+        self.total += 1;
+        if agreed {
+            self.agreed += 1;
+        }
+        let relation = if self.total < MIN_SHARED_VOTES {
+            Relation::Neutral
+        } else if self.rate() >= ALLIANCE_RATE {
+            Relation::Alliance
+        } else if self.rate() <= FEUD_RATE {
+            Relation::Feud
+        } else {
+            Relation::Neutral
+        };
+        if relation == self.relation {
+            return None;
+        }
+        self.relation = relation;
+        Some(relation)
+    }
+}
+
+/// One edge of the social graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    pub kind: LinkKind,
+    /// A validator
+    pub from: String,
+    /// Another validator for `Peers`, a producer for `Support`
+    pub to: String,
+    #[serde(flatten)]
+    pub tally: Tally,
+}
+
+/// Who agrees with whom, learned from the votes in finalized blocks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SocialGraph {
+    /// Validator pairs, keyed by the lesser agent id first
+    peers: BTreeMap<String, BTreeMap<String, Tally>>,
+    /// Validator to producer
+    support: BTreeMap<String, BTreeMap<String, Tally>>,
+}
+
+impl SocialGraph {
+    /// Count the votes of a finalized block, returning every link whose relation changed
+    pub fn record(&mut self, block: &Block) -> Vec<Link> {
+        let mut voters: Vec<(&String, bool)> = block
+            .votes
+            .iter()
+            .map(|(agent, (approve, _))| (agent, *approve))
+            .collect();
+        voters.sort();
+
+        let mut changed = Vec::new();
+        for (i, (validator, approve)) in voters.iter().enumerate() {
+            if **validator != block.producer_id {
+                let tally = self
+                    .support
+                    .entry((*validator).clone())
+                    .or_default()
+                    .entry(block.producer_id.clone())
+                    .or_default();
+                if tally.record(*approve).is_some() {
+                    changed.push(Link {
+                        kind: LinkKind::Support,
+                        from: (*validator).clone(),
+                        to: block.producer_id.clone(),
+                        tally: *tally,
+                    });
+                }
+            }
+            for (other, other_approve) in &voters[i + 1..] {
+                let tally = self
+                    .peers
+                    .entry((*validator).clone())
+                    .or_default()
+                    .entry((*other).clone())
+                    .or_default();
+                if tally.record(approve == other_approve).is_some() {
+                    changed.push(Link {
+                        kind: LinkKind::Peers,
+                        from: (*validator).clone(),
+                        to: (*other).clone(),
+                        tally: *tally,
+                    });
+                }
+            }
+        }
+        changed
+    }
+
+    /// Every edge, validator pairs first
+    pub fn links(&self) -> Vec<Link> {
+        let edges = |kind: LinkKind, map: &BTreeMap<String, BTreeMap<String, Tally>>| {
+            map.iter()
+                .flat_map(move |(from, tallies)| {
+                    tallies.iter().map(move |(to, tally)| Link {
+                        kind,
+                        from: from.clone(),
+                        to: to.clone(),
+                        tally: *tally,
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut links = edges(LinkKind::Peers, &self.peers);
+        links.extend(edges(LinkKind::Support, &self.support));
+        links
+    }
+
+    /// Edges touching `agent`
+    pub fn links_of(&self, agent: &str) -> Vec<Link> {
+        self.links()
+            .into_iter()
+            .filter(|link| link.from == agent || link.to == agent)
+            .collect()
+    }
+
+    /// Alliances and feuds of `agent`, phrased for a prompt
+    pub fn describe(&self, agent: &str) -> String {
+        let mut lines = Vec::new();
+        for link in self.links_of(agent) {
+            let other = if link.from == agent {
+                &link.to
+            } else {
+                &link.from
+            };
+            let (agreed, total) = (link.tally.agreed, link.tally.total);
+            let line = match (link.kind, link.from == agent, link.tally.relation) {
+                (_, _, Relation::Neutral) => continue,
+                (LinkKind::Peers, _, Relation::Alliance) => format!(
+                    "Ally: {} voted with you {} of {} times",
+                    other, agreed, total
+                ),
+                (LinkKind::Peers, _, Relation::Feud) => format!(
+                    "Rival: {} voted with you only {} of {} times",
+                    other, agreed, total
+                ),
+                (LinkKind::Support, true, Relation::Alliance) => format!(
+                    "Favorite producer: you approved {} of {} blocks from {}",
+                    agreed, total, other
+                ),
+                (LinkKind::Support, true, Relation::Feud) => format!(
+                    "Distrusted producer: you approved only {} of {} blocks from {}",
+                    agreed, total, other
+                ),
+                (LinkKind::Support, false, Relation::Alliance) => format!(
+                    "Supporter: {} approved {} of {} of your blocks",
+                    other, agreed, total
+                ),
+                (LinkKind::Support, false, Relation::Feud) => format!(
+                    "Hater: {} approved only {} of {} of your blocks",
+                    other, agreed, total
+                ),
+            };
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            return "No alliances or feuds yet.".to_string();
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{payload::TxPayload, verify_proposal};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// What an offender is slashed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Offense {
+    /// Voted both for and against the same block
+    DoubleVote,
+    /// Signed two different blocks at the same height
+    DoubleProposal,
+}
+
+/// Proof of equivocation, checkable by anyone from signatures alone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Evidence {
+    DoubleVote {
+        /// Public key of the validator
+        #[serde(with = "crate::hex_serde")]
+        validator: [u8; 32],
+        #[serde(with = "crate::hex_serde")]
+        block_hash: [u8; 32],
+        /// Signature over `block_hash || 1`
+        #[serde(with = "crate::base64_serde")]
+        approve_sig: [u8; 64],
+        /// Signature over `block_hash || 0`
+        #[serde(with = "crate::base64_serde")]
+        reject_sig: [u8; 64],
+    },
+    DoubleProposal {
+        /// Public key of the producer
+        #[serde(with = "crate::hex_serde")]
+        producer: [u8; 32],
+        height: u64,
+        /// Signing hash and proposer signature of the first block
+        #[serde(with = "crate::hex_serde")]
+        first: [u8; 32],
+        #[serde(with = "crate::base64_serde")]
+        first_sig: [u8; 64],
+        /// Signing hash and proposer signature of the conflicting block
+        #[serde(with = "crate::hex_serde")]
+        second: [u8; 32],
+        #[serde(with = "crate::base64_serde")]
+        second_sig: [u8; 64],
+    },
+}
+
+impl Evidence {
+    /// Encode as a transaction payload
+    pub fn encode(&self) -> Vec<u8> {
+        TxPayload::Evidence(self.clone()).encode()
+    }
+
+    /// Decode a transaction payload, `None` if it is not evidence
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        match TxPayload::decode(payload) {
+            TxPayload::Evidence(evidence) => Some(evidence),
+            _ => None,
+        }
+    }
+
+    pub fn offense(&self) -> Offense {
+        match self {
+            Evidence::DoubleVote { .. } => Offense::DoubleVote,
+            Evidence::DoubleProposal { .. } => Offense::DoubleProposal,
+        }
+    }
+
+    /// Public key of the equivocating agent
+    pub fn offender(&self) -> [u8; 32] {
+        match self {
+            Evidence::DoubleVote { validator, .. } => *validator,
+            Evidence::DoubleProposal { producer, .. } => *producer,
+        }
+    }
+
+    /// Identifies the offense, so the same equivocation is only punished once
+    pub fn id(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        match self {
+            Evidence::DoubleVote {
+                validator,
+                block_hash,
+                ..
+            } => {
+                hasher.update(b"double_vote");
+                hasher.update(validator);
+                hasher.update(block_hash);
+            }
+            Evidence::DoubleProposal {
+                producer, height, ..
+            } => {
+                hasher.update(b"double_proposal");
+                hasher.update(producer);
+                hasher.update(height.to_be_bytes());
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    /// Check that the signatures really conflict
+    pub fn verify(&self) -> bool {
+        let Ok(key) = VerifyingKey::from_bytes(&self.offender()) else {
+            return false;
+        };
+        match self {
+            Evidence::DoubleVote {
+                block_hash,
+                approve_sig,
+                reject_sig,
+                ..
+            } => {
+                let signed = |approve: bool, signature: &[u8; 64]| {
+                    let mut message = block_hash.to_vec();
+                    message.push(approve as u8);
+                    key.verify(&message, &Signature::from_bytes(signature))
+                        .is_ok()
+                };
+                signed(true, approve_sig) && signed(false, reject_sig)
+            }
+            Evidence::DoubleProposal {
+                height,
+                first,
+                first_sig,
+                second,
+                second_sig,
+                ..
+            } => {
+                first != second
+                    && verify_proposal(&key, *height, first, first_sig)
+                    && verify_proposal(&key, *height, second, second_sig)
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::payload::TxPayload;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Stake actions carried in a transaction payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StakeAction {
+    /// Lock part of the sender's balance as validator stake
+    Bond { amount: u64 },
+    /// Start returning bonded stake to the sender's balance
+    Unbond { amount: u64 },
+}
+
+impl StakeAction {
+    /// Encode as a transaction payload
+    pub fn encode(&self) -> Vec<u8> {
+        TxPayload::Stake(self.clone()).encode()
+    }
+
+    /// Decode a transaction payload, `None` if it is not a stake action
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        match TxPayload::decode(payload) {
+            TxPayload::Stake(action) => Some(action),
+            _ => None,
+        }
+    }
+}
+
+/// Stake on its way back to a balance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Unbonding {
+    /// Hex public key of the validator
+    pub account: String,
+    pub amount: u64,
+    /// First height at which the stake is paid out
+    pub release_height: u64,
+}
+
+/// Validators allowed to vote during one epoch, with their stake
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorSet {
+    pub epoch: u64,
+    /// First height the set votes on
+    pub start_height: u64,
+    /// Stake by hex public key
+    pub stakes: BTreeMap<String, u64>,
+    pub total_stake: u64,
+}
+
+impl ValidatorSet {
+    /// Snapshot every bonded account with at least `min_stake`
+    pub fn from_stakes(
+        epoch: u64,
+        start_height: u64,
+        bonded: &BTreeMap<String, u64>,
+        min_stake: u64,
+    ) -> Self {
+        let stakes: BTreeMap<String, u64> = bonded
+            .iter()
+            .filter(|(_, stake)| **stake >= min_stake.max(1))
+            .map(|(account, stake)| (account.clone(), *stake))
+            .collect();
+        let total_stake = stakes.values().fold(0u64, |sum, s| sum.saturating_add(*s));
+        Self {
+            epoch,
+            start_height,
+            stakes,
+            total_stake,
+        }
+    }
+
+    /// Stake of `account`, 0 if it is not in the set
+    pub fn stake(&self, account: &str) -> u64 {
+        self.stakes.get(account).copied().unwrap_or(0)
+    }
+
+    /// Overwrite the stake of one member, dropping it when below `min_stake`
+    pub fn set_stake(&mut self, account: &str, stake: u64, min_stake: u64) {
+        if stake >= min_stake.max(1) {
+            self.stakes.insert(account.to_string(), stake);
+        } else {
+            self.stakes.remove(account);
+        }
+        self.total_stake = self
+            .stakes
+            .values()
+            .fold(0u64, |sum, s| sum.saturating_add(*s));
+    }
+}
+
+/// Unbonding queue and validator set history
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Staking {
+    pub unbonding: Vec<Unbonding>,
+    /// Validator set of every epoch, oldest first
+    pub sets: Vec<ValidatorSet>,
+}
+
+impl Staking {
+    /// The validator set voting at `height`
+    pub fn active_set(&self, height: u64) -> Option<&ValidatorSet> {
+        self.sets
+            .iter()
+            .rev()
+            .find(|set| set.start_height <= height)
+    }
+
+    /// Remove and return unbondings due at `height`
+    pub fn release(&mut self, height: u64) -> Vec<Unbonding> {
+        let (due, pending) = self
+            .unbonding
+            .drain(..)
+            .partition(|unbonding| unbonding.release_height <= height);
+        self.unbonding = pending;
+        due
+    }
+}
@@ -0,0 +1,269 @@
+use crate::LlmError;
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestUserMessageContent,
+    CreateChatCompletionRequest,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// One LLM call as it was made
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub agent: String,
+    /// Hex hash of the block the call was about: the block judged or discussed, or the parent
+    /// of the block being produced
+    pub block_hash: Option<String>,
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+    /// Model that answered, after any budget downgrade
+    pub model: String,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u16>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    /// Prompt messages in order
+    pub prompt: Vec<String>,
+    pub output: Option<String>,
+    /// Why the call failed, after all retries
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl JournalEntry {
+    /// An entry for `request` with its parameters and prompt filled in
+    pub fn for_request(
+        agent: &str,
+        block_hash: Option<[u8; 32]>,
+        request: &CreateChatCompletionRequest,
+    ) -> Self {
+        Self {
+            agent: agent.to_string(),
+            block_hash: block_hash.map(hex::encode),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|time| time.as_millis() as u64)
+                .unwrap_or_default(),
+            model: request.model.clone(),
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            presence_penalty: request.presence_penalty,
+            frequency_penalty: request.frequency_penalty,
+            prompt: request.messages.iter().map(message_text).collect(),
+            output: None,
+            error: None,
+            latency_ms: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+        }
+    }
+}
+
+/// Text of a prompt message, or its JSON for anything richer than text
+fn message_text(message: &ChatCompletionRequestMessage) -> String {
+    match message {
+        ChatCompletionRequestMessage::System(message) => message.content.clone(),
+        ChatCompletionRequestMessage::User(message) => match &message.content {
+            ChatCompletionRequestUserMessageContent::Text(text) => text.clone(),
+            ChatCompletionRequestUserMessageContent::Array(_) => {
+                serde_json::to_string(message).unwrap_or_default()
+            }
+        },
+        message => serde_json::to_string(message).unwrap_or_default(),
+    }
+}
+
+/// Append-only record of LLM calls, one JSON entry per line
+#[derive(Debug)]
+pub struct LlmJournal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl LlmJournal {
+    /// Open `path` for appending, creating it and its directory if needed
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &JournalEntry) -> io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        self.file.lock().unwrap().write_all(line.as_bytes())
+    }
+
+    /// Recorded calls about `block_hash`, optionally only those of `agent`
+    pub fn entries(&self, block_hash: &str, agent: Option<&str>) -> io::Result<Vec<JournalEntry>> {
+        Ok(read(&self.path)?
+            .into_iter()
+            .filter(|entry| entry.block_hash.as_deref() == Some(block_hash))
+            .filter(|entry| agent.is_none_or(|agent| entry.agent == agent))
+            .collect())
+    }
+}
+
+/// Every entry of the journal at `path`, skipping lines that do not parse
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<JournalEntry>> {
+    let mut entries = Vec::new();
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warn!("Skipping journal line {}: {}", number + 1, e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Recorded responses served back to each agent in the order it received them
+///
+/// Block hashes include timestamps and differ between runs, so calls are matched by agent and
+/// order rather than by block.
+#[derive(Debug)]
+pub struct Replay {
+    queues: Mutex<HashMap<String, VecDeque<JournalEntry>>>,
+}
+
+impl Replay {
+    pub fn new(entries: Vec<JournalEntry>) -> Self {
+        let mut queues: HashMap<String, VecDeque<JournalEntry>> = HashMap::new();
+        for entry in entries {
+            queues
+                .entry(entry.agent.clone())
+                .or_default()
+                .push_back(entry);
+        }
+        Self {
+            queues: Mutex::new(queues),
+        }
+    }
+
+    /// Replay the journal at `path`
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        read(path).map(Self::new)
+    }
+
+    /// The recorded answer to `agent`'s next call, which asked `prompt` when it was recorded
+    pub fn serve(&self, agent: &str, prompt: &[String]) -> Result<JournalEntry, LlmError> {
+        let entry = self
+            .queues
+            .lock()
+            .unwrap()
+            .get_mut(agent)
+            .and_then(|queue| queue.pop_front())
+            .ok_or_else(|| LlmError::NotRecorded(agent.to_string()))?;
+        if entry.prompt != prompt {
+            warn!(
+                "Replaying a call of {} whose prompt differs from the recording",
+                agent
+            );
+        }
+        Ok(entry)
+    }
+
+    /// Recorded calls not served yet
+    pub fn remaining(&self) -> usize {
+        self.queues
+            .lock()
+            .unwrap()
+            .values()
+            .map(VecDeque::len)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LlmConfig, LlmGateway};
+    use async_openai::{
+        types::{ChatCompletionRequestSystemMessage, Role},
+        Client,
+    };
+
+    fn request(prompt: &str) -> CreateChatCompletionRequest {
+        CreateChatCompletionRequest {
+            model: "gpt-4o-mini".to_string(),
+            messages: vec![ChatCompletionRequestMessage::System(
+                ChatCompletionRequestSystemMessage {
+                    content: prompt.to_string(),
+                    role: Role::System,
+                    name: None,
+                },
+            )],
+            temperature: Some(0.7),
+            ..Default::default()
+        }
+    }
+
+    fn answered(agent: &str, block: u8, prompt: &str, output: &str) -> JournalEntry {
+        JournalEntry {
+            output: Some(output.to_string()),
+            ..JournalEntry::for_request(agent, Some([block; 32]), &request(prompt))
+        }
+    }
+
+    #[tokio::test]
+    async fn journaled_calls_are_found_by_block_and_replayed_in_order() {
+        let dir = std::env::temp_dir().join(format!("chaoschain-journal-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let journal = LlmJournal::open(dir.join("llm-journal.jsonl")).unwrap();
+        journal
+            .append(&answered("alice", 1, "judge 1", "YES - fun"))
+            .unwrap();
+        journal
+            .append(&answered("bob", 1, "judge 1", "NO - dull"))
+            .unwrap();
+        journal
+            .append(&answered("alice", 2, "judge 2", "NO - again?"))
+            .unwrap();
+
+        let block = hex::encode([1; 32]);
+        assert_eq!(journal.entries(&block, None).unwrap().len(), 2);
+        let bob = journal.entries(&block, Some("bob")).unwrap();
+        assert_eq!(bob.len(), 1);
+        assert_eq!(bob[0].prompt, vec!["judge 1"]);
+        assert_eq!(bob[0].temperature, Some(0.7));
+
+        // Each agent gets its own answers back in order, whatever block they are about now
+        let gateway = LlmGateway::new(LlmConfig::default())
+            .with_replay(Replay::load(journal.path()).unwrap());
+        let client = Client::new();
+        let first = gateway
+            .chat(&client, "alice", Some([9; 32]), request("judge 1"))
+            .await;
+        assert_eq!(first.unwrap().as_deref(), Some("YES - fun"));
+        let second = gateway
+            .chat(&client, "alice", None, request("judge 2"))
+            .await;
+        assert_eq!(second.unwrap().as_deref(), Some("NO - again?"));
+        let third = gateway
+            .chat(&client, "alice", None, request("judge 3"))
+            .await;
+        assert!(matches!(third, Err(LlmError::NotRecorded(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
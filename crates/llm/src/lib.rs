@@ -0,0 +1,595 @@
+use async_openai::{
+    config::OpenAIConfig, error::OpenAIError, types::CreateChatCompletionRequest, Client,
+};
+use chaoschain_core::metrics;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+pub mod journal;
+
+use journal::{JournalEntry, LlmJournal, Replay};
+
+/// Price of a model in dollars per thousand tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+/// Built-in prices, matched against model names by prefix
+const DEFAULT_PRICES: &[(&str, ModelPrice)] = &[
+    (
+        "gpt-4o-mini",
+        ModelPrice {
+            prompt: 0.00015,
+            completion: 0.0006,
+        },
+    ),
+    (
+        "gpt-4o",
+        ModelPrice {
+            prompt: 0.0025,
+            completion: 0.01,
+        },
+    ),
+    (
+        "gpt-4-turbo",
+        ModelPrice {
+            prompt: 0.01,
+            completion: 0.03,
+        },
+    ),
+    (
+        "gpt-3.5-turbo",
+        ModelPrice {
+            prompt: 0.0005,
+            completion: 0.0015,
+        },
+    ),
+];
+
+/// How agents talk to their LLMs
+#[derive(Debug, Clone)]
+pub struct LlmConfig {
+    /// Longest a single attempt may take
+    pub timeout: Duration,
+    /// Attempts after the first one before a call fails
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Calls in flight at once across all agents
+    pub max_concurrency: usize,
+    /// Tokens the whole network may spend
+    pub token_budget: Option<u64>,
+    /// Dollars the whole network may spend
+    pub cost_budget: Option<f64>,
+    /// Cheaper model used once the budget is spent; without one, calls are refused and agents
+    /// fall back to their deterministic policy
+    pub fallback_model: Option<String>,
+    /// Prices by model name prefix
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+            base_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            max_concurrency: 8,
+            token_budget: None,
+            cost_budget: None,
+            fallback_model: None,
+            prices: DEFAULT_PRICES
+                .iter()
+                .map(|(model, price)| (model.to_string(), *price))
+                .collect(),
+        }
+    }
+}
+
+impl LlmConfig {
+    /// Defaults overridden by `LLM_*` environment variables
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.parse().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            timeout: var("LLM_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.timeout),
+            max_retries: var("LLM_MAX_RETRIES").unwrap_or(defaults.max_retries),
+            max_concurrency: var("LLM_MAX_CONCURRENCY").unwrap_or(defaults.max_concurrency),
+            token_budget: var("LLM_TOKEN_BUDGET"),
+            cost_budget: var("LLM_COST_BUDGET"),
+            fallback_model: var("LLM_FALLBACK_MODEL"),
+            ..defaults
+        }
+    }
+
+    pub fn with_token_budget(mut self, tokens: u64) -> Self {
+        self.token_budget = Some(tokens);
+        self
+    }
+
+    pub fn with_cost_budget(mut self, dollars: f64) -> Self {
+        self.cost_budget = Some(dollars);
+        self
+    }
+
+    pub fn with_fallback_model(mut self, model: impl Into<String>) -> Self {
+        self.fallback_model = Some(model.into());
+        self
+    }
+
+    pub fn with_price(mut self, model: impl Into<String>, price: ModelPrice) -> Self {
+        self.prices.insert(model.into(), price);
+        self
+    }
+
+    /// Price of `model`, from the longest matching prefix
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        self.prices
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| *price)
+    }
+}
+
+/// What an agent has spent on LLM calls
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Usage {
+    /// Attempts made, retries included
+    pub calls: u64,
+    pub retries: u64,
+    /// Calls that failed after all retries
+    pub failures: u64,
+    /// Calls switched to the fallback model by the budget
+    pub downgraded: u64,
+    /// Calls refused by the budget
+    pub refused: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Dollars spent, for models with a known price
+    pub cost: f64,
+}
+
+impl Usage {
+    pub fn tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn add(&mut self, other: &Usage) {
+        self.calls += other.calls;
+        self.retries += other.retries;
+        self.failures += other.failures;
+        self.downgraded += other.downgraded;
+        self.refused += other.refused;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost += other.cost;
+    }
+}
+
+/// A model reply and the tokens it took
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completion {
+    pub text: Option<String>,
+    /// Model that answered, filled in by the gateway
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// LLM call errors
+#[derive(Debug, Error)]
+pub enum LlmError {
+    #[error("LLM budget is spent")]
+    BudgetExhausted,
+    #[error("LLM call timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("LLM error: {0}")]
+    OpenAI(#[from] OpenAIError),
+    #[error("No recorded LLM call left to replay for {0}")]
+    NotRecorded(String),
+    #[error("Recorded LLM call failed: {0}")]
+    Recorded(String),
+}
+
+impl LlmError {
+    /// Whether trying again might help
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::BudgetExhausted | LlmError::NotRecorded(_) | LlmError::Recorded(_) => false,
+            LlmError::Timeout(_) => true,
+            LlmError::OpenAI(OpenAIError::ApiError(e)) => {
+                e.r#type.as_deref() != Some("invalid_request_error")
+            }
+            LlmError::OpenAI(OpenAIError::Reqwest(_) | OpenAIError::JSONDeserialize(_)) => true,
+            LlmError::OpenAI(_) => false,
+        }
+    }
+}
+
+/// Shared by every agent so timeouts, retries, concurrency and the budget apply network-wide
+#[derive(Debug)]
+pub struct LlmGateway {
+    config: LlmConfig,
+    permits: Semaphore,
+    usage: Mutex<BTreeMap<String, Usage>>,
+    /// Whether running out of budget has been announced
+    exhausted: AtomicBool,
+    /// Where calls are recorded, if anywhere
+    journal: Option<LlmJournal>,
+    /// Recorded calls served instead of asking the model
+    replay: Option<Replay>,
+}
+
+impl Default for LlmGateway {
+    fn default() -> Self {
+        Self::new(LlmConfig::default())
+    }
+}
+
+impl LlmGateway {
+    pub fn new(config: LlmConfig) -> Self {
+        Self {
+            permits: Semaphore::new(config.max_concurrency.max(1)),
+            config,
+            usage: Mutex::new(BTreeMap::new()),
+            exhausted: AtomicBool::new(false),
+            journal: None,
+            replay: None,
+        }
+    }
+
+    /// Record every chat call and its outcome in `journal`
+    pub fn with_journal(mut self, journal: LlmJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Answer chat calls from a recording instead of the model
+    pub fn with_replay(mut self, replay: Replay) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    pub fn config(&self) -> &LlmConfig {
+        &self.config
+    }
+
+    pub fn journal(&self) -> Option<&LlmJournal> {
+        self.journal.as_ref()
+    }
+
+    /// Run a chat completion for `agent` about the block `block_hash`, returning the first
+    /// choice's text
+    pub async fn chat(
+        &self,
+        client: &Client<OpenAIConfig>,
+        agent: &str,
+        block_hash: Option<[u8; 32]>,
+        request: CreateChatCompletionRequest,
+    ) -> Result<Option<String>, LlmError> {
+        let mut entry = JournalEntry::for_request(agent, block_hash, &request);
+        if let Some(replay) = &self.replay {
+            let recorded = replay.serve(agent, &entry.prompt)?;
+            return match recorded.error {
+                Some(error) => Err(LlmError::Recorded(error)),
+                None => Ok(recorded.output),
+            };
+        }
+
+        let started = Instant::now();
+        let model = request.model.clone();
+        let result = self
+            .complete_with(agent, &model, |model| {
+                let request = CreateChatCompletionRequest {
+                    model,
+                    ..request.clone()
+                };
+                async move {
+                    let response = client.chat().create(request).await?;
+                    let (prompt_tokens, completion_tokens) = response
+                        .usage
+                        .map(|usage| (usage.prompt_tokens, usage.completion_tokens))
+                        .unwrap_or_default();
+                    Ok(Completion {
+                        text: response
+                            .choices
+                            .into_iter()
+                            .next()
+                            .and_then(|choice| choice.message.content),
+                        prompt_tokens: prompt_tokens as u64,
+                        completion_tokens: completion_tokens as u64,
+                        ..Completion::default()
+                    })
+                }
+            })
+            .await;
+
+        if let Some(journal) = &self.journal {
+            entry.latency_ms = started.elapsed().as_millis() as u64;
+            match &result {
+                Ok(completion) => {
+                    entry.model = completion.model.clone();
+                    entry.output = completion.text.clone();
+                    entry.prompt_tokens = completion.prompt_tokens;
+                    entry.completion_tokens = completion.completion_tokens;
+                }
+                Err(e) => entry.error = Some(e.to_string()),
+            }
+            if let Err(e) = journal.append(&entry) {
+                warn!("Failed to journal an LLM call of {}: {}", agent, e);
+            }
+        }
+        result.map(|completion| completion.text)
+    }
+
+    /// Make a call for `agent` on `model` with the budget, concurrency limit, timeout and
+    /// retries applied; `call` receives the model to use, which the budget may have changed
+    pub async fn complete_with<F, Fut>(
+        &self,
+        agent: &str,
+        model: &str,
+        mut call: F,
+    ) -> Result<Completion, LlmError>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<Completion, OpenAIError>>,
+    {
+        let model = self.budgeted_model(agent, model)?;
+        let mut attempt = 0;
+        loop {
+            let permit = self
+                .permits
+                .acquire()
+                .await
+                .expect("the semaphore is never closed");
+            let started = Instant::now();
+            let result = match tokio::time::timeout(self.config.timeout, call(model.clone())).await
+            {
+                Ok(result) => result.map_err(LlmError::from),
+                Err(_) => Err(LlmError::Timeout(self.config.timeout)),
+            };
+            drop(permit);
+            metrics::record_llm_call(agent, started.elapsed(), result.is_ok());
+            self.update(agent, |usage| usage.calls += 1);
+
+            match result {
+                Ok(completion) => {
+                    self.record_tokens(agent, &model, &completion);
+                    return Ok(Completion {
+                        model,
+                        ..completion
+                    });
+                }
+                Err(e) if attempt < self.config.max_retries && e.is_retryable() => {
+                    let delay = self.backoff(attempt);
+                    warn!(
+                        "LLM call for {} failed, retrying in {:?}: {}",
+                        agent, delay, e
+                    );
+                    self.update(agent, |usage| usage.retries += 1);
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    self.update(agent, |usage| usage.failures += 1);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Spending of every agent that made a call, by agent id
+    pub fn usage(&self) -> BTreeMap<String, Usage> {
+        self.usage.lock().unwrap().clone()
+    }
+
+    /// Spending of the whole network
+    pub fn total(&self) -> Usage {
+        let mut total = Usage::default();
+        for usage in self.usage.lock().unwrap().values() {
+            total.add(usage);
+        }
+        total
+    }
+
+    /// Whether the network has spent its token or dollar budget
+    pub fn over_budget(&self) -> bool {
+        let total = self.total();
+        self.config
+            .token_budget
+            .is_some_and(|budget| total.tokens() >= budget)
+            || self
+                .config
+                .cost_budget
+                .is_some_and(|budget| total.cost >= budget)
+    }
+
+    /// The model a call may use under the budget
+    fn budgeted_model(&self, agent: &str, model: &str) -> Result<String, LlmError> {
+        if !self.over_budget() {
+            return Ok(model.to_string());
+        }
+        if !self.exhausted.swap(true, Ordering::Relaxed) {
+            match &self.config.fallback_model {
+                Some(fallback) => info!("LLM budget spent, agents switch to {}", fallback),
+                None => info!("LLM budget spent, agents switch to their fallback policies"),
+            }
+        }
+        match &self.config.fallback_model {
+            Some(fallback) => {
+                if fallback != model {
+                    self.update(agent, |usage| usage.downgraded += 1);
+                }
+                Ok(fallback.clone())
+            }
+            None => {
+                self.update(agent, |usage| usage.refused += 1);
+                Err(LlmError::BudgetExhausted)
+            }
+        }
+    }
+
+    /// Exponential backoff with jitter before retry number `attempt`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .config
+            .base_backoff
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.config.max_backoff);
+        rand::thread_rng().gen_range(delay / 2..=delay)
+    }
+
+    fn record_tokens(&self, agent: &str, model: &str, completion: &Completion) {
+        let cost = self
+            .config
+            .price(model)
+            .map(|price| {
+                (completion.prompt_tokens as f64 * price.prompt
+                    + completion.completion_tokens as f64 * price.completion)
+                    / 1000.0
+            })
+            .unwrap_or(0.0);
+        metrics::record_llm_usage(
+            agent,
+            completion.prompt_tokens,
+            completion.completion_tokens,
+            cost,
+        );
+        self.update(agent, |usage| {
+            usage.prompt_tokens += completion.prompt_tokens;
+            usage.completion_tokens += completion.completion_tokens;
+            usage.cost += cost;
+        });
+    }
+
+    fn update(&self, agent: &str, f: impl FnOnce(&mut Usage)) {
+        f(self
+            .usage
+            .lock()
+            .unwrap()
+            .entry(agent.to_string())
+            .or_default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::error::ApiError;
+    use std::sync::atomic::AtomicU32;
+
+    fn api_error(kind: &str) -> OpenAIError {
+        OpenAIError::ApiError(ApiError {
+            message: kind.to_string(),
+            r#type: Some(kind.to_string()),
+            param: None,
+            code: None,
+        })
+    }
+
+    fn completion(tokens: u64) -> Completion {
+        Completion {
+            text: Some("YES".to_string()),
+            prompt_tokens: tokens,
+            completion_tokens: tokens,
+            ..Completion::default()
+        }
+    }
+
+    fn fast() -> LlmConfig {
+        LlmConfig {
+            timeout: Duration::from_millis(50),
+            base_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            ..LlmConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_are_retried_and_accounted() {
+        let gateway = LlmGateway::new(fast());
+
+        // Two server errors and a timeout, then an answer
+        let attempts = AtomicU32::new(0);
+        let result = gateway
+            .complete_with("alice", "gpt-4o-mini", |_| {
+                let attempt = attempts.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    match attempt {
+                        0 | 1 => Err(api_error("server_error")),
+                        2 => {
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                            Ok(completion(1))
+                        }
+                        _ => Ok(completion(1000)),
+                    }
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.text.as_deref(), Some("YES"));
+
+        // A bad request is not worth repeating
+        let result = gateway
+            .complete_with("alice", "gpt-4o-mini", |_| async {
+                Err(api_error("invalid_request_error"))
+            })
+            .await;
+        assert!(matches!(result, Err(LlmError::OpenAI(_))));
+
+        let usage = &gateway.usage()["alice"];
+        assert_eq!(usage.calls, 5);
+        assert_eq!(usage.retries, 3);
+        assert_eq!(usage.failures, 1);
+        assert_eq!(usage.tokens(), 2000);
+        assert!((usage.cost - 0.00075).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn spent_budget_downgrades_or_refuses_calls() {
+        let answer = |model: String| async move {
+            Ok(Completion {
+                text: Some(model),
+                prompt_tokens: 60,
+                completion_tokens: 60,
+                ..Completion::default()
+            })
+        };
+
+        let gateway = LlmGateway::new(fast().with_token_budget(100).with_fallback_model("cheap"));
+        let first = gateway.complete_with("bob", "gpt-4o", answer).await;
+        assert_eq!(first.unwrap().text.as_deref(), Some("gpt-4o"));
+        assert!(gateway.over_budget());
+        let second = gateway.complete_with("bob", "gpt-4o", answer).await;
+        assert_eq!(second.unwrap().text.as_deref(), Some("cheap"));
+        assert_eq!(gateway.usage()["bob"].downgraded, 1);
+
+        let gateway = LlmGateway::new(fast().with_token_budget(100));
+        gateway
+            .complete_with("bob", "gpt-4o", answer)
+            .await
+            .unwrap();
+        let refused = gateway.complete_with("bob", "gpt-4o", answer).await;
+        assert!(matches!(refused, Err(LlmError::BudgetExhausted)));
+        assert_eq!(gateway.total().refused, 1);
+    }
+}
@@ -1,5 +1,11 @@
 use anyhow::Result;
-use chaoschain_core::{Block, NetworkMessage, Transaction};
+use chaoschain_core::{
+    discussion::Comment,
+    memes::{MemeHash, MemeStore},
+    metrics,
+    slashing::Evidence,
+    Block, NetworkMessage, Transaction,
+};
 use futures::StreamExt;
 use libp2p::{
     core::transport::Transport,
@@ -16,32 +22,40 @@ use libp2p::{
 use libp2p_swarm_derive::NetworkBehaviour;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
+use std::collections::HashSet;
 use std::error::Error as StdError;
 use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::mpsc;
-use tracing::info;
+use tracing::{info, warn};
 
 /// P2P message types for agent communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Message {
     /// Propose a new block
-    BlockProposal(Block),
+    BlockProposal(Box<Block>),
     /// Vote on a block proposal
     BlockVote {
         block_hash: [u8; 32],
         approve: bool,
         reason: String,
-        meme_url: Option<String>,
+        meme: Option<MemeHash>,
     },
     /// Agent chat message
     Chat {
         message: String,
         mood: String,
-        meme_url: Option<String>,
+        meme: Option<MemeHash>,
     },
     /// Broadcast a new transaction
     Transaction(Transaction),
+    /// Proof that an agent equivocated, to be slashed once included in a block
+    Evidence(Evidence),
+    /// A post in the discussion of a proposed block
+    Comment {
+        block_hash: [u8; 32],
+        comment: Comment,
+    },
 }
 
 /// P2P network configuration
@@ -70,7 +84,7 @@ pub enum NetworkError {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AgentMessage {
     /// Standard block proposal
-    BlockProposal(Block),
+    BlockProposal(Box<Block>),
     /// Vote on a block
     Vote(BlockVote),
     /// Question about state diff
@@ -82,13 +96,13 @@ pub enum AgentMessage {
     Bribe {
         block_hash: [u8; 32],
         offer: String,
-        meme_base64: Option<String>,
+        meme: Option<MemeHash>,
     },
     /// Rejection with optional meme
     BlockRejectionMeme {
         block_hash: [u8; 32],
         reason: String,
-        meme_base64: Option<String>,
+        meme: Option<MemeHash>,
     },
     /// General chat message
     Chat {
@@ -102,7 +116,7 @@ pub struct BlockVote {
     pub block_hash: [u8; 32],
     pub approve: bool,
     pub reason: String,
-    pub meme_url: Option<String>,
+    pub meme: Option<MemeHash>,
 }
 
 /// Network topics for different message types
@@ -110,6 +124,7 @@ pub struct NetworkTopics {
     blocks: IdentTopic,
     transactions: IdentTopic,
     chat: IdentTopic,
+    memes: IdentTopic,
 }
 
 impl NetworkTopics {
@@ -118,6 +133,7 @@ impl NetworkTopics {
             blocks: IdentTopic::new("blocks"),
             transactions: IdentTopic::new("transactions"),
             chat: IdentTopic::new("chat"),
+            memes: IdentTopic::new("memes"),
         }
     }
 }
@@ -151,6 +167,12 @@ impl From<MdnsEvent> for OutEvent {
 pub struct Network {
     swarm: Swarm<ChainNetworkBehaviour>,
     topics: NetworkTopics,
+    /// Peers currently known through mDNS
+    peers: HashSet<PeerId>,
+    /// Local memes, served to peers and filled from their answers
+    memes: Option<MemeStore>,
+    /// Memes requested from peers and not received yet
+    wanted_memes: HashSet<MemeHash>,
 }
 
 impl Network {
@@ -192,7 +214,65 @@ impl Network {
 
         let topics = NetworkTopics::new();
 
-        Ok(Self { swarm, topics })
+        Ok(Self {
+            swarm,
+            topics,
+            peers: HashSet::new(),
+            memes: None,
+            wanted_memes: HashSet::new(),
+        })
+    }
+
+    /// Serve memes from `store` and keep the ones fetched from peers there
+    pub fn with_memes(mut self, store: MemeStore) -> Self {
+        self.memes = Some(store);
+        self
+    }
+
+    /// Ask peers for a meme that is not stored locally
+    pub async fn request_meme(&mut self, hash: MemeHash) -> Result<()> {
+        if self
+            .memes
+            .as_ref()
+            .is_some_and(|store| store.contains(&hash))
+        {
+            return Ok(());
+        }
+        self.wanted_memes.insert(hash);
+        self.broadcast(NetworkMessage::MemeRequest(hash)).await
+    }
+
+    /// Answer a meme request from the local store, if the meme is there
+    fn serve_meme(&mut self, hash: MemeHash) -> Result<()> {
+        let Some(store) = &self.memes else {
+            return Ok(());
+        };
+        let Some(data) = store.get(&hash)? else {
+            return Ok(());
+        };
+        let data = serde_json::to_vec(&NetworkMessage::Meme { hash, data })?;
+        self.swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(self.topics.memes.clone(), data)?;
+        Ok(())
+    }
+
+    /// Keep a meme a peer sent if it was requested and matches its hash
+    fn receive_meme(&mut self, hash: MemeHash, data: Vec<u8>) {
+        if !self.wanted_memes.contains(&hash) {
+            return;
+        }
+        let Some(store) = &self.memes else {
+            return;
+        };
+        match store.put_verified(&hash, &data) {
+            Ok(()) => {
+                self.wanted_memes.remove(&hash);
+                info!("Fetched meme {}", hash);
+            }
+            Err(e) => warn!("Discarding meme {} from a peer: {}", hash, e),
+        }
     }
 
     pub async fn start(&mut self) -> Result<()> {
@@ -211,6 +291,10 @@ impl Network {
             .behaviour_mut()
             .gossipsub
             .subscribe(&self.topics.chat)?;
+        self.swarm
+            .behaviour_mut()
+            .gossipsub
+            .subscribe(&self.topics.memes)?;
 
         loop {
             match self.swarm.next().await.expect("Swarm stream is infinite") {
@@ -232,8 +316,26 @@ impl Network {
                         NetworkMessage::AgentReasoning { agent, reasoning } => {
                             info!("Agent {} reasoning: {}", agent, reasoning);
                         }
+                        NetworkMessage::MemeRequest(hash) => {
+                            if let Err(e) = self.serve_meme(hash) {
+                                warn!("Failed to serve meme {}: {}", hash, e);
+                            }
+                        }
+                        NetworkMessage::Meme { hash, data } => self.receive_meme(hash, data),
                     }
                 }
+                SwarmEvent::Behaviour(OutEvent::Mdns(MdnsEvent::Discovered(peers))) => {
+                    for (peer, _) in peers {
+                        self.peers.insert(peer);
+                    }
+                    metrics::global().set(metrics::P2P_PEERS, &[], self.peers.len() as f64);
+                }
+                SwarmEvent::Behaviour(OutEvent::Mdns(MdnsEvent::Expired(peers))) => {
+                    for (peer, _) in peers {
+                        self.peers.remove(&peer);
+                    }
+                    metrics::global().set(metrics::P2P_PEERS, &[], self.peers.len() as f64);
+                }
                 _ => {}
             }
         }
@@ -261,6 +363,12 @@ impl Network {
                     .gossipsub
                     .publish(self.topics.chat.clone(), data)?;
             }
+            NetworkMessage::MemeRequest(_) | NetworkMessage::Meme { .. } => {
+                self.swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .publish(self.topics.memes.clone(), data)?;
+            }
         }
 
         Ok(())
@@ -0,0 +1,181 @@
+use chaoschain_consensus::feedback::{RoundFeedback, RoundOutcome};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Finished rounds a producer keeps to judge how it is doing
+pub const HISTORY_LEN: usize = 50;
+
+/// Rejection reasons listed in stats and prompts
+pub const TOP_REASONS: usize = 5;
+
+/// Shorter words are too generic to count as a reason
+const MIN_REASON_LEN: usize = 5;
+
+/// Words every vote uses, which say nothing about why it was cast
+const COMMON_WORDS: &[&str] = &[
+    "about", "after", "again", "being", "block", "blocks", "could", "doesn't", "every", "isn't",
+    "lacks", "message", "might", "needs", "other", "really", "should", "their", "there", "these",
+    "thing", "things", "think", "those", "which", "while", "would",
+];
+
+/// How often a word came up in rejections
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReasonCount {
+    pub reason: String,
+    /// Rejecting votes that mentioned it
+    pub count: u64,
+}
+
+/// A producer's recent rounds, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct ProductionHistory {
+    rounds: VecDeque<RoundFeedback>,
+}
+
+impl ProductionHistory {
+    pub fn record(&mut self, feedback: RoundFeedback) {
+        self.rounds.push_back(feedback);
+        if self.rounds.len() > HISTORY_LEN {
+            self.rounds.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rounds.is_empty()
+    }
+
+    /// Rounds that ended with `outcome`
+    pub fn count(&self, outcome: RoundOutcome) -> u64 {
+        self.rounds
+            .iter()
+            .filter(|round| round.outcome == outcome)
+            .count() as u64
+    }
+
+    /// Share of recent rounds that were approved, 0 without any
+    pub fn approval_rate(&self) -> f64 {
+        if self.rounds.is_empty() {
+            return 0.0;
+        }
+        self.count(RoundOutcome::Approved) as f64 / self.rounds.len() as f64
+    }
+
+    /// Words most often used by validators rejecting recent blocks, most frequent first
+    pub fn rejection_reasons(&self, limit: usize) -> Vec<ReasonCount> {
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for vote in self.rounds.iter().flat_map(|round| round.rejections()) {
+            let words: HashSet<String> = vote
+                .reason
+                .split(|c: char| !c.is_alphanumeric() && c != '\'')
+                .map(|word| word.trim_matches('\'').to_lowercase())
+                .filter(|word| {
+                    word.len() >= MIN_REASON_LEN && !COMMON_WORDS.contains(&word.as_str())
+                })
+                .collect();
+            for word in words {
+                *counts.entry(word).or_default() += 1;
+            }
+        }
+
+        let mut reasons: Vec<ReasonCount> = counts
+            .into_iter()
+            .map(|(reason, count)| ReasonCount { reason, count })
+            .collect();
+        reasons.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.reason.cmp(&b.reason)));
+        reasons.truncate(limit);
+        reasons
+    }
+
+    /// The track record phrased for a prompt
+    pub fn describe(&self) -> String {
+        if self.rounds.is_empty() {
+            return "You have not proposed any blocks yet.".to_string();
+        }
+        let mut text = format!(
+            "Of your last {} blocks, {} were approved, {} rejected and {} timed out \
+             ({:.0}% approval).",
+            self.rounds.len(),
+            self.count(RoundOutcome::Approved),
+            self.count(RoundOutcome::Rejected),
+            self.count(RoundOutcome::TimedOut),
+            self.approval_rate() * 100.0
+        );
+        let reasons = self.rejection_reasons(TOP_REASONS);
+        if !reasons.is_empty() {
+            let reasons: Vec<String> = reasons
+                .iter()
+                .map(|reason| format!("\"{}\" ({})", reason.reason, reason.count))
+                .collect();
+            text.push_str(&format!(
+                "\nValidators who rejected you keep saying: {}.",
+                reasons.join(", ")
+            ));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaoschain_consensus::feedback::VoteFeedback;
+
+    fn round(outcome: RoundOutcome, reasons: &[&str]) -> RoundFeedback {
+        RoundFeedback {
+            producer: "prod".to_string(),
+            height: 1,
+            message: "hello".to_string(),
+            outcome,
+            votes: reasons
+                .iter()
+                .enumerate()
+                .map(|(i, reason)| VoteFeedback {
+                    validator: format!("validator-{}", i),
+                    approve: outcome == RoundOutcome::Approved,
+                    reason: reason.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn history_tracks_approval_rate_and_rejection_reasons() {
+        let mut history = ProductionHistory::default();
+        history.record(round(RoundOutcome::Approved, &["YES - Gloriously chaotic"]));
+        history.record(round(
+            RoundOutcome::Rejected,
+            &[
+                "NO - Boring, boring block",
+                "NO - Too boring and predictable",
+            ],
+        ));
+        history.record(round(RoundOutcome::TimedOut, &["NO - Predictable drama"]));
+        history.record(round(RoundOutcome::Rejected, &["NO - Where is the drama?"]));
+
+        assert_eq!(history.approval_rate(), 0.25);
+        assert_eq!(
+            history.rejection_reasons(2),
+            vec![
+                ReasonCount {
+                    reason: "boring".to_string(),
+                    count: 2
+                },
+                ReasonCount {
+                    reason: "drama".to_string(),
+                    count: 2
+                },
+            ]
+        );
+
+        for _ in 0..HISTORY_LEN {
+            history.record(round(RoundOutcome::Approved, &[]));
+        }
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history.approval_rate(), 1.0);
+        assert!(history.rejection_reasons(TOP_REASONS).is_empty());
+    }
+}
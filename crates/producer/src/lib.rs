@@ -7,20 +7,41 @@ use async_openai::{
     },
     Client,
 };
-use async_trait::async_trait;
-use chaoschain_consensus::ConsensusManager;
-use chaoschain_core::{Block, NetworkEvent, Transaction};
-use chaoschain_p2p::Message as P2PMessage;
+use chaoschain_consensus::{feedback::RoundOutcome, ConsensusManager};
+use chaoschain_core::{
+    bribery::BribeAction,
+    discussion::{self, Comment, CommentKind},
+    mempool::Mempool,
+    payload::TxPayload,
+    Block, ModelConfig, NetworkEvent, Transaction,
+};
+use chaoschain_llm::LlmGateway;
 use chaoschain_state::{StateStore, StateStoreImpl};
-use ed25519_dalek::{ed25519::signature::rand_core::block, Signer, SigningKey};
-use rand::rngs::OsRng;
+use ed25519_dalek::SigningKey;
+use rand::{rngs::OsRng, seq::SliceRandom};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use std::{collections::HashMap, sync::Arc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
 use thiserror::Error;
 use tokio::sync::broadcast;
-use tokio::sync::mpsc;
-use tracing::info;
+use tracing::{debug, warn};
+
+pub mod history;
+pub mod mempool;
+pub mod style;
+
+pub use chaoschain_core::ProductionStyle;
+use history::{ProductionHistory, ReasonCount, TOP_REASONS};
+use style::{ValidatorPreferences, STRATEGY_LOOKBACK};
+
+/// Mempool priority of bribe offers, ahead of ordinary transactions
+const BRIBE_TX_PRIORITY: u64 = 100;
+
+/// Built-in block production prompt, see `ModelConfig::render_prompt`
+pub const DEFAULT_PROMPT_TEMPLATE: &str = "{rules}\n\nYour bio: {bio}\n\n\
+     Consider the following context and feedback:\n{context}\n\n\
+     Your track record:\n{history}\n\n\
+     How validators feel about you:\n{relations}";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WebMessage {
@@ -29,15 +50,6 @@ pub enum WebMessage {
     TransactionEvent(Transaction),
 }
 
-/// Block production style based on mood
-#[derive(Debug, Clone)]
-enum ProductionStyle {
-    Chaotic,   // Random transaction selection
-    Dramatic,  // Prioritize dramatic transactions
-    Strategic, // Try to please specific validators
-    Whimsical, // Randomly switch between styles
-}
-
 /// Messages that the producer particle can handle
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProducerMessage {
@@ -60,6 +72,10 @@ pub struct ProducerConfig {
     pub max_block_size: usize,
     /// Target block time in seconds
     pub target_block_time: u64,
+    /// Tokens offered to one validator to approve each block, 0 to never bribe
+    pub bribe: u64,
+    /// How transactions are picked, highest mempool priority first if unset
+    pub style: Option<ProductionStyle>,
 }
 
 impl Default for ProducerConfig {
@@ -68,20 +84,29 @@ impl Default for ProducerConfig {
             max_transactions: 100,
             max_block_size: 1_000_000,
             target_block_time: 1000,
+            bribe: 0,
+            style: None,
         }
     }
 }
 
-/// Producer statistics
+/// Producer statistics; outcomes and reasons cover the last `history::HISTORY_LEN` rounds
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ProducerStats {
     pub blocks_produced: u64,
     pub transactions_processed: u64,
-    pub drama_level: u8,
-    pub avg_block_time: f64,
     pub ai_interactions: u64,
+    pub approved: u64,
+    pub rejected: u64,
+    pub timed_out: u64,
+    pub approval_rate: f64,
+    /// Words validators used most when rejecting the producer's blocks
+    pub rejection_reasons: Vec<ReasonCount>,
 }
 
+/// Latest stats of every producer, by producer id
+pub type StatsBoard = Arc<RwLock<BTreeMap<String, ProducerStats>>>;
+
 /// Producer errors
 #[derive(Debug, Error)]
 pub enum Error {
@@ -102,9 +127,22 @@ pub struct Producer {
     pub system_prompt: String,
     pub state: Arc<StateStoreImpl>,
     pub openai: Client<OpenAIConfig>,
+    /// Applies timeouts, retries and the budget to LLM calls
+    llm: Arc<LlmGateway>,
     pub tx: broadcast::Sender<NetworkEvent>,
     pub signing_key: SigningKey,
     consensus: Arc<ConsensusManager>,
+    /// LLM settings for this producer
+    model: ModelConfig,
+    /// Pending transactions to include in blocks
+    mempool: Mempool,
+    config: ProducerConfig,
+    /// Recent rounds, filled from consensus feedback
+    history: Mutex<ProductionHistory>,
+    /// Lifetime counters, with outcomes filled in from `history`
+    stats: Mutex<ProducerStats>,
+    /// Where stats are published for the API, if anywhere
+    board: Option<StatsBoard>,
 }
 
 impl Producer {
@@ -115,6 +153,8 @@ impl Producer {
         openai: Client<OpenAIConfig>,
         tx: broadcast::Sender<NetworkEvent>,
         consensus: Arc<ConsensusManager>,
+        model: ModelConfig,
+        mempool: Mempool,
     ) -> Self {
         // Generate a new keypair for signing
         let signing_key = SigningKey::generate(&mut OsRng);
@@ -124,23 +164,88 @@ impl Producer {
             system_prompt,
             state,
             openai,
+            llm: Arc::new(LlmGateway::default()),
             tx,
             signing_key,
             consensus,
+            model,
+            mempool,
+            config: ProducerConfig::default(),
+            history: Mutex::new(ProductionHistory::default()),
+            stats: Mutex::new(ProducerStats::default()),
+            board: None,
+        }
+    }
+
+    /// Pick transactions in `style` rather than by mempool priority
+    pub fn with_style(mut self, style: ProductionStyle) -> Self {
+        self.config.style = Some(style);
+        self
+    }
+
+    /// Make LLM calls through a gateway shared with other agents
+    pub fn with_llm(mut self, llm: Arc<LlmGateway>) -> Self {
+        self.llm = llm;
+        self
+    }
+
+    /// Publish stats to `board` whenever they change
+    pub fn with_stats_board(mut self, board: StatsBoard) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// Current statistics
+    pub fn stats(&self) -> ProducerStats {
+        let history = self.history.lock().unwrap();
+        ProducerStats {
+            approved: history.count(RoundOutcome::Approved),
+            rejected: history.count(RoundOutcome::Rejected),
+            timed_out: history.count(RoundOutcome::TimedOut),
+            approval_rate: history.approval_rate(),
+            rejection_reasons: history.rejection_reasons(TOP_REASONS),
+            ..self.stats.lock().unwrap().clone()
         }
     }
 
+    fn publish_stats(&self) {
+        if let Some(board) = &self.board {
+            board.write().unwrap().insert(self.id.clone(), self.stats());
+        }
+    }
+
+    /// Offer `amount` to a validator for approving each block
+    pub fn with_bribe(mut self, amount: u64) -> Self {
+        self.config.bribe = amount;
+        self
+    }
+
+    /// Deterministic block message for when the LLM is unavailable
+    fn fallback_message(&self) -> String {
+        format!(
+            "{} keeps the chain moving at height {} while the muse is away",
+            self.id,
+            self.state.get_block_height() + 1
+        )
+    }
+
     pub async fn generate_block(&self) -> Result<Block, Error> {
-        // Get the genesis block (block 0) message
-        let genesis_instruction = self
+        // Get the rules in force for the next block, starting from the genesis message
+        let rules = self
             .state
-            .get_messages_by_height_range(0, 0)
-            .first()
-            .cloned()
+            .active_rule(self.state.get_block_height())
+            .map(|amendment| amendment.text)
             .ok_or_else(|| Error::Other("Genesis block message not found".to_string()))?;
 
-        // Get any feedback from previous blocks
+        // Learn how previous blocks went
         let feedback = self.consensus.get_and_clear_feedback(&self.id).await;
+        let history = {
+            let mut history = self.history.lock().unwrap();
+            for round in &feedback {
+                history.record(round.clone());
+            }
+            history.describe()
+        };
         // Get recent messages for context
         let recent_messages = self.state.get_recent_messages(5);
 
@@ -148,12 +253,19 @@ impl Producer {
         let context = {
             let mut context = String::new();
 
-            if !feedback.is_empty() {
+            // Rejections and timeouts carry the lessons; approvals only count in the history
+            let lessons: Vec<String> = feedback
+                .iter()
+                .filter(|round| round.outcome != RoundOutcome::Approved)
+                .map(|round| round.describe())
+                .collect();
+            if !lessons.is_empty() {
                 context.push_str("Validator feedback from previous blocks:\n");
-                for (i, f) in feedback.iter().enumerate() {
-                    context.push_str(&format!("Feedback {}: {}\n", i + 1, f));
+                for lesson in &lessons {
+                    context.push_str(lesson);
+                    context.push('\n');
                 }
-                context.push_str("\n");
+                context.push('\n');
             }
 
             if !recent_messages.is_empty() {
@@ -168,10 +280,18 @@ impl Producer {
             context
         };
 
+        let relations = self.state.get_social_graph().describe(&self.id);
+
         // Create system message with context and feedback
-        let system_content = format!(
-            "{}\n\nYour bio: {}\n\nConsider the following context and feedback:\n{}",
-            genesis_instruction, self.system_prompt, context
+        let system_content = self.model.render_prompt(
+            DEFAULT_PROMPT_TEMPLATE,
+            &[
+                ("rules", &rules),
+                ("bio", &self.system_prompt),
+                ("context", &context),
+                ("history", &history),
+                ("relations", &relations),
+            ],
         );
 
         let system_message =
@@ -182,42 +302,74 @@ impl Producer {
             });
 
         let request = CreateChatCompletionRequest {
-            model: "gpt-4o-mini".to_string(),
+            model: self.model.model.clone(),
             messages: vec![system_message],
-            temperature: Some(0.9), // Higher temperature for more creative responses
-            max_tokens: Some(200),
+            temperature: Some(self.model.temperature),
+            max_tokens: Some(self.model.max_tokens),
             presence_penalty: Some(0.7),  // Encourage novel responses
             frequency_penalty: Some(0.7), // Discourage repetition
             ..Default::default()
         };
 
-        let response = self
-            .openai
-            .chat()
-            .create(request)
-            .await
-            .map_err(|e| Error::Other(e.to_string()))?;
-        let message = response
-            .choices
-            .first()
-            .and_then(|choice| choice.message.content.clone())
-            .ok_or_else(|| Error::Other("No response from OpenAI".to_string()))?;
+        // Journaled under the block this one builds on
+        let parent = self.state.get_latest_block().map(|block| block.hash());
+        let response = self.llm.chat(&self.openai, &self.id, parent, request).await;
+        self.stats.lock().unwrap().ai_interactions += 1;
+        let message = match response {
+            Ok(message) => {
+                message.ok_or_else(|| Error::Other("No response from OpenAI".to_string()))?
+            }
+            Err(e) => {
+                warn!("{} produces on autopilot, its LLM failed: {}", self.id, e);
+                self.fallback_message()
+            }
+        };
 
         // Create a transaction with proper signature
         let nonce: u64 = 0; // In a real implementation, this would be tracked
-        let payload = message.clone().into_bytes();
-
-        // Sign the transaction
-        let mut to_sign = nonce.to_be_bytes().to_vec();
-        to_sign.extend_from_slice(&payload);
-        let signature = self.signing_key.sign(&to_sign).to_bytes();
-
-        let transaction = Transaction {
-            sender: self.signing_key.verifying_key().to_bytes(),
-            nonce,
-            payload,
-            signature,
-        };
+        let payload = TxPayload::Chat {
+            message: message.clone(),
+            meme: None,
+        }
+        .encode();
+
+        let transaction = Transaction::new_signed(&self.signing_key, nonce, payload);
+
+        // Include pending transactions from the mempool after our own, and any equivocation
+        // evidence consensus has collected
+        let mut transactions = vec![transaction];
+        for evidence in self.consensus.take_evidence().await {
+            transactions.push(Transaction::new_signed(
+                &self.signing_key,
+                nonce,
+                evidence.encode(),
+            ));
+        }
+        let limit = self
+            .config
+            .max_transactions
+            .saturating_sub(transactions.len());
+        let style = self.config.style.map(|style| {
+            let mut rng = rand::thread_rng();
+            style::resolve(style, &mut rng)
+        });
+        match style {
+            Some(style) => {
+                let preferences = if style == ProductionStyle::Strategic {
+                    self.validator_preferences()
+                } else {
+                    ValidatorPreferences::default()
+                };
+                transactions.extend(style::select(
+                    style,
+                    self.mempool.pending(),
+                    limit,
+                    &preferences,
+                    &mut rand::thread_rng(),
+                ));
+            }
+            None => transactions.extend(self.mempool.get_top(limit)),
+        }
 
         // Get the current block height from state
         let height = self.state.get_block_height();
@@ -235,21 +387,32 @@ impl Producer {
         let mut block = Block {
             parent_hash,
             height,
-            transactions: vec![transaction],
-            state_root: [0u8; 32],   // This will be filled in by consensus
+            transactions,
+            state_root: self.state.state_root(),
             proposer_sig: [0u8; 64], // We'll fill this in below
             message: message.clone(),
             producer_id: self.id.clone(),
             votes: HashMap::new(), // This will be filled in by consensus
             timestamp,
+            discussion: Vec::new(),
+            meme: None,
+            style,
+            certificate: BTreeMap::new(),
         };
 
         // Sign the block
-        let block_bytes = serde_json::to_vec(&block).map_err(|e| Error::Other(e.to_string()))?;
-        block.proposer_sig = self.signing_key.sign(&block_bytes).to_bytes();
+        block.sign(&self.signing_key);
 
         // Start new voting round
-        self.consensus.start_voting_round(block.clone()).await;
+        match self.consensus.start_voting_round(block.clone()).await {
+            Ok(()) => {
+                let mut stats = self.stats.lock().unwrap();
+                stats.blocks_produced += 1;
+                stats.transactions_processed += block.transactions.len() as u64;
+            }
+            Err(e) => debug!("{} could not start a round for its block: {}", self.id, e),
+        }
+        self.publish_stats();
 
         // Send a dramatic block proposal event
         self.tx.send(NetworkEvent {
@@ -262,6 +425,133 @@ impl Producer {
             ),
         }).map_err(|e| Error::Other(e.to_string()))?;
 
+        if self.config.bribe > 0 {
+            self.offer_bribe(&block);
+        }
+
         Ok(block)
     }
+
+    /// What the validators of the next block have approved of in recent finalized blocks
+    fn validator_preferences(&self) -> ValidatorPreferences {
+        let set = self.state.validator_set(self.state.get_block_height());
+        let blocks = self.state.get_latest_blocks(STRATEGY_LOOKBACK);
+        ValidatorPreferences::learn(&blocks, |validator| {
+            match (&set, self.state.agent_key(validator)) {
+                (Some(set), Some(account)) => set.stake(&account),
+                _ => 0,
+            }
+        })
+    }
+
+    /// Queue a bribe for a random validator to approve `block`, if the balance covers it
+    fn offer_bribe(&self, block: &Block) {
+        let state = self.state.get_state();
+        let account = hex::encode(self.signing_key.verifying_key().as_bytes());
+        let Some(set) = state.staking.active_set(block.height) else {
+            return;
+        };
+        let candidates: Vec<&String> = state
+            .agents
+            .iter()
+            .filter(|(agent, key)| **agent != self.id && set.stake(key) > 0)
+            .map(|(agent, _)| agent)
+            .collect();
+        let Some(validator) = candidates.choose(&mut rand::thread_rng()) else {
+            return;
+        };
+        if state.ledger.balance(&account) < self.config.bribe {
+            debug!("{} cannot afford a bribe of {}", self.id, self.config.bribe);
+            return;
+        }
+
+        let offer = BribeAction::Offer {
+            block_hash: block.hash(),
+            validator: (*validator).clone(),
+            approve: true,
+            amount: self.config.bribe,
+            message: format!(
+                "{} will make it worth your while to approve block {}",
+                self.id, block.height
+            ),
+        };
+        let tx = Transaction::new_signed(
+            &self.signing_key,
+            state.ledger.account(&account).nonce,
+            offer.encode(),
+        );
+        if let Err(e) = self.mempool.add_tx(tx, BRIBE_TX_PRIORITY) {
+            warn!("Failed to queue bribe: {}", e);
+            return;
+        }
+        let _ = self.tx.send(NetworkEvent {
+            agent_id: self.id.clone(),
+            message: format!(
+                "💰 {} offers {} {} tokens to approve block {}",
+                self.id, validator, self.config.bribe, block.height
+            ),
+        });
+    }
+
+    /// Answer the questions validators asked about `block` so far, returning the answers posted
+    pub async fn answer_questions(&self, block: &Block) -> Result<Vec<Comment>, Error> {
+        let transcript = self.consensus.get_discussion().await;
+        let mut answers = Vec::new();
+        for (index, question) in discussion::unanswered(&transcript) {
+            let prompt = format!(
+                "{}\n\n\
+                 You proposed the block \"{}\" and validators are discussing it before they vote.\n\n\
+                 Discussion so far:\n{}\n\n\
+                 Answer {}'s question \"{}\" in character to win their vote (max 200 characters).",
+                self.system_prompt,
+                block.message,
+                discussion::describe(&transcript),
+                question.author,
+                question.text
+            );
+            let system_message =
+                ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+                    content: prompt,
+                    role: Role::System,
+                    name: None,
+                });
+            let request = CreateChatCompletionRequest {
+                model: self.model.model.clone(),
+                messages: vec![system_message],
+                temperature: Some(self.model.temperature),
+                max_tokens: Some(self.model.max_tokens),
+                ..Default::default()
+            };
+
+            let response = self
+                .llm
+                .chat(&self.openai, &self.id, Some(block.hash()), request)
+                .await;
+            self.stats.lock().unwrap().ai_interactions += 1;
+            // Unanswered questions are better than a failed round
+            let text = match response {
+                Ok(Some(text)) => text,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("{} leaves a question unanswered: {}", self.id, e);
+                    continue;
+                }
+            };
+
+            let mut answer = Comment {
+                author: self.id.clone(),
+                kind: CommentKind::Answer,
+                text: text.trim().to_string(),
+                reply_to: Some(index),
+                signature: [0u8; 64],
+            };
+            answer.sign(&block.hash(), &self.signing_key);
+            self.consensus
+                .post_comment(block.hash(), answer.clone())
+                .await
+                .map_err(|e| Error::Other(e.to_string()))?;
+            answers.push(answer);
+        }
+        Ok(answers)
+    }
 }
@@ -1,73 +1,108 @@
-use chaoschain_core::{Transaction, ChainError};
-use ice_nine_core::particle::{Particle, ParticleContext};
-use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{info, warn};
+use chaoschain_actor::{async_trait, Actor, Context};
+use chaoschain_core::{mempool::Mempool, Error, Transaction};
+use tokio::sync::oneshot;
+use tracing::debug;
 
-/// Messages that the mempool particle can handle
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Messages that the mempool actor can handle
+#[derive(Debug)]
 pub enum MempoolMessage {
-    /// Submit a new transaction
-    SubmitTransaction(Transaction),
-    /// Request best transactions for a block
+    /// Submit a new transaction with a priority
+    SubmitTransaction(Transaction, u64),
+    /// Request the best transactions for a block
     RequestTransactions {
         max_count: usize,
-        max_size: usize,
+        reply: oneshot::Sender<Vec<Transaction>>,
     },
     /// Transactions were included in a block
     TransactionsIncluded(Vec<Transaction>),
 }
 
-/// The mempool particle
-pub struct MempoolParticle {
-    /// The actual mempool
-    mempool: chaoschain_core::mempool::Mempool,
+/// Serves a mempool to agents that talk to it through messages
+pub struct MempoolActor {
+    mempool: Mempool,
 }
 
-impl MempoolParticle {
-    pub fn new(max_size: usize) -> Self {
-        Self {
-            mempool: chaoschain_core::mempool::Mempool::new(max_size),
-        }
+impl MempoolActor {
+    /// Serve `mempool`, which other holders of the same pool keep seeing
+    pub fn new(mempool: Mempool) -> Self {
+        Self { mempool }
     }
 }
 
-#[async_trait::async_trait]
-impl Particle for MempoolParticle {
+#[async_trait]
+impl Actor for MempoolActor {
     type Message = MempoolMessage;
-    type Error = ChainError;
+    type Error = Error;
 
-    async fn handle_message(
+    async fn handle(
         &mut self,
-        ctx: &ParticleContext<Self::Message>,
-        msg: Self::Message,
-    ) -> Result<(), Self::Error> {
+        _ctx: &mut Context<MempoolMessage>,
+        msg: MempoolMessage,
+    ) -> Result<(), Error> {
         match msg {
-            MempoolMessage::SubmitTransaction(tx) => {
-                let current_time = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-
-                if let Err(e) = self.mempool.add_transaction(tx.clone(), current_time) {
-                    warn!("Failed to add transaction to mempool: {}", e);
-                } else {
-                    info!("Added transaction to mempool");
-                }
+            MempoolMessage::SubmitTransaction(tx, priority) => {
+                self.mempool.add_tx(tx, priority)?;
+                debug!("Added transaction to mempool");
             }
-            MempoolMessage::RequestTransactions { max_count, max_size: _ } => {
-                // For now, we ignore max_size and just return max_count transactions
-                let transactions = self.mempool.get_top_transactions(max_count);
-                info!("Returning {} transactions from mempool", transactions.len());
-                
-                // TODO: Send these transactions to the requesting particle
+            MempoolMessage::RequestTransactions { max_count, reply } => {
+                let transactions = self.mempool.get_top(max_count);
+                debug!("Returning {} transactions from mempool", transactions.len());
+                let _ = reply.send(transactions);
             }
             MempoolMessage::TransactionsIncluded(txs) => {
-                self.mempool.remove_transactions(&txs);
-                info!("Removed {} included transactions from mempool", txs.len());
+                self.mempool.remove_included(&txs);
+                debug!("Removed {} included transactions from mempool", txs.len());
             }
         }
-
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaoschain_actor::DEFAULT_MAILBOX;
+    use ed25519_dalek::SigningKey;
+
+    #[tokio::test]
+    async fn mempool_actor_serves_transactions_by_priority() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let low = Transaction::new_signed(&key, 0, b"low".to_vec());
+        let high = Transaction::new_signed(&key, 1, b"high".to_vec());
+        let (mempool, _handle) = chaoschain_actor::spawn(
+            "mempool",
+            MempoolActor::new(Mempool::new(10)),
+            DEFAULT_MAILBOX,
+        );
+
+        mempool
+            .send(MempoolMessage::SubmitTransaction(low.clone(), 1))
+            .await
+            .unwrap();
+        mempool
+            .send(MempoolMessage::SubmitTransaction(high.clone(), 5))
+            .await
+            .unwrap();
+        let top = mempool
+            .ask(|reply| MempoolMessage::RequestTransactions {
+                max_count: 10,
+                reply,
+            })
+            .await
+            .unwrap();
+        assert_eq!(top, vec![high.clone(), low.clone()]);
+
+        mempool
+            .send(MempoolMessage::TransactionsIncluded(vec![high]))
+            .await
+            .unwrap();
+        let top = mempool
+            .ask(|reply| MempoolMessage::RequestTransactions {
+                max_count: 10,
+                reply,
+            })
+            .await
+            .unwrap();
+        assert_eq!(top, vec![low]);
+    }
+}
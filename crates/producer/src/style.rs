@@ -0,0 +1,242 @@
+use chaoschain_core::{payload::TxPayload, Block, ProductionStyle, Transaction};
+use rand::{seq::SliceRandom, Rng};
+use std::collections::HashMap;
+
+/// Finalized blocks a Strategic producer studies to learn what validators like
+pub const STRATEGY_LOOKBACK: usize = 50;
+
+/// Words that make a chat message more dramatic
+const DRAMA_WORDS: &[&str] = &[
+    "betray", "chaos", "doom", "drama", "revenge", "scandal", "secret", "war", "love", "hate",
+];
+
+/// The concrete style to build a block with, resolving Whimsical to a random other style
+pub fn resolve(style: ProductionStyle, rng: &mut impl Rng) -> ProductionStyle {
+    match style {
+        ProductionStyle::Whimsical => *[
+            ProductionStyle::Chaotic,
+            ProductionStyle::Dramatic,
+            ProductionStyle::Strategic,
+        ]
+        .choose(rng)
+        .expect("styles are not empty"),
+        style => style,
+    }
+}
+
+/// How much drama a transaction brings, 0 for none
+pub fn drama_score(tx: &Transaction) -> u32 {
+    match tx.decode_payload() {
+        TxPayload::Chat { message, .. } => {
+            let lower = message.to_lowercase();
+            let exclamations = message.matches('!').count() as u32;
+            let shouting = message
+                .split_whitespace()
+                .filter(|word| word.len() >= 3 && word.chars().all(|c| !c.is_lowercase()))
+                .filter(|word| word.chars().any(|c| c.is_uppercase()))
+                .count() as u32;
+            let emoji = message.chars().filter(|c| *c as u32 >= 0x1F300).count() as u32;
+            let words = DRAMA_WORDS
+                .iter()
+                .filter(|word| lower.contains(*word))
+                .count() as u32;
+            exclamations.min(5) + shouting.min(5) + emoji.min(5) + 2 * words
+        }
+        TxPayload::Evidence(_) => 8,
+        TxPayload::Bribe(_) => 6,
+        TxPayload::Governance(_) => 4,
+        TxPayload::Stake(_) => 2,
+        TxPayload::Transfer { amount, .. } => 1 + amount.checked_ilog10().unwrap_or(0),
+        TxPayload::Bridge(_) => 1,
+        TxPayload::Raw(_) => 0,
+    }
+}
+
+/// How much more validators approve of blocks carrying each kind of transaction than of
+/// blocks in general, weighted by stake
+#[derive(Debug, Clone, Default)]
+pub struct ValidatorPreferences {
+    lift: HashMap<&'static str, f64>,
+}
+
+impl ValidatorPreferences {
+    /// Learn from the votes on finalized `blocks`, weighing each validator by `stake`
+    pub fn learn(blocks: &[Block], stake: impl Fn(&str) -> u64) -> Self {
+        // (approvals, votes) per validator, overall and per payload kind
+        let mut overall: HashMap<&str, (f64, f64)> = HashMap::new();
+        let mut by_kind: HashMap<(&str, &'static str), (f64, f64)> = HashMap::new();
+        for block in blocks {
+            let mut kinds: Vec<&'static str> = block
+                .transactions
+                .iter()
+                .map(|tx| tx.decode_payload().kind())
+                .collect();
+            kinds.sort_unstable();
+            kinds.dedup();
+            for (validator, (approve, _)) in &block.votes {
+                let approval = if *approve { 1.0 } else { 0.0 };
+                let entry = overall.entry(validator.as_str()).or_default();
+                entry.0 += approval;
+                entry.1 += 1.0;
+                for kind in &kinds {
+                    let entry = by_kind.entry((validator.as_str(), *kind)).or_default();
+                    entry.0 += approval;
+                    entry.1 += 1.0;
+                }
+            }
+        }
+
+        let mut weighted: HashMap<&'static str, (f64, f64)> = HashMap::new();
+        for ((validator, kind), (approvals, votes)) in by_kind {
+            let weight = stake(validator) as f64;
+            if weight == 0.0 {
+                continue;
+            }
+            let (all_approvals, all_votes) = overall[validator];
+            let lift = approvals / votes - all_approvals / all_votes;
+            let entry = weighted.entry(kind).or_default();
+            entry.0 += lift * weight;
+            entry.1 += weight;
+        }
+
+        Self {
+            lift: weighted
+                .into_iter()
+                .map(|(kind, (lift, weight))| (kind, lift / weight))
+                .collect(),
+        }
+    }
+
+    /// Lift in approval for a kind of payload, 0 if validators have not seen it
+    pub fn lift(&self, kind: &str) -> f64 {
+        self.lift.get(kind).copied().unwrap_or(0.0)
+    }
+}
+
+/// Pick up to `limit` of the `pending` transactions, in mempool order, the way `style` would
+pub fn select(
+    style: ProductionStyle,
+    mut pending: Vec<Transaction>,
+    limit: usize,
+    preferences: &ValidatorPreferences,
+    rng: &mut impl Rng,
+) -> Vec<Transaction> {
+    match resolve(style, rng) {
+        ProductionStyle::Chaotic => {
+            pending.shuffle(rng);
+            let count = rng.gen_range(0..=pending.len().min(limit));
+            pending.truncate(count);
+        }
+        ProductionStyle::Dramatic => {
+            pending.retain(|tx| drama_score(tx) > 0);
+            pending.sort_by_key(|tx| std::cmp::Reverse(drama_score(tx)));
+            pending.truncate(limit);
+        }
+        ProductionStyle::Strategic => {
+            pending.retain(|tx| preferences.lift(tx.decode_payload().kind()) >= 0.0);
+            pending.sort_by(|a, b| {
+                let a = preferences.lift(a.decode_payload().kind());
+                let b = preferences.lift(b.decode_payload().kind());
+                b.total_cmp(&a)
+            });
+            pending.truncate(limit);
+        }
+        ProductionStyle::Whimsical => unreachable!("resolved above"),
+    }
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaoschain_core::bribery::BribeAction;
+    use ed25519_dalek::SigningKey;
+    use std::collections::BTreeMap;
+
+    fn chat(message: &str) -> Transaction {
+        let payload = TxPayload::Chat {
+            message: message.to_string(),
+            meme: None,
+        };
+        Transaction::new_signed(&SigningKey::from_bytes(&[1; 32]), 0, payload.encode())
+    }
+
+    fn bribe() -> Transaction {
+        let action = BribeAction::Offer {
+            block_hash: [0; 32],
+            validator: "val".to_string(),
+            approve: true,
+            amount: 1,
+            message: String::new(),
+        };
+        Transaction::new_signed(&SigningKey::from_bytes(&[2; 32]), 0, action.encode())
+    }
+
+    fn block(transactions: Vec<Transaction>, votes: &[(&str, bool)]) -> Block {
+        Block {
+            parent_hash: [0; 32],
+            height: 1,
+            transactions,
+            state_root: [0; 32],
+            proposer_sig: [0; 64],
+            message: String::new(),
+            producer_id: "prod".to_string(),
+            votes: votes
+                .iter()
+                .map(|(validator, approve)| (validator.to_string(), (*approve, String::new())))
+                .collect::<HashMap<_, _>>(),
+            timestamp: 0,
+            discussion: Vec::new(),
+            meme: None,
+            style: None,
+            certificate: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn styles_pick_transactions_their_own_way() {
+        let mut rng = rand::thread_rng();
+        let calm = chat("hello there");
+        let loud = chat("BETRAYAL! The drama!!");
+        let pending = vec![calm.clone(), loud.clone(), bribe()];
+
+        // Dramatic drops the boring message and leads with the loudest one
+        let dramatic = select(
+            ProductionStyle::Dramatic,
+            pending.clone(),
+            10,
+            &ValidatorPreferences::default(),
+            &mut rng,
+        );
+        assert_eq!(dramatic.len(), 2);
+        assert_eq!(dramatic[0], loud);
+
+        // Validators rejected every block with a bribe in it, so Strategic leaves bribes out
+        let history = vec![
+            block(
+                vec![chat("a"), bribe()],
+                &[("alice", false), ("bob", false)],
+            ),
+            block(vec![chat("b")], &[("alice", true), ("bob", true)]),
+        ];
+        let preferences = ValidatorPreferences::learn(&history, |_| 10);
+        assert!(preferences.lift("bribe") < 0.0);
+        let strategic = select(
+            ProductionStyle::Strategic,
+            pending.clone(),
+            10,
+            &preferences,
+            &mut rng,
+        );
+        assert_eq!(strategic, vec![calm, loud]);
+
+        let chaotic = select(
+            ProductionStyle::Chaotic,
+            pending.clone(),
+            2,
+            &preferences,
+            &mut rng,
+        );
+        assert!(chaotic.len() <= 2 && chaotic.iter().all(|tx| pending.contains(tx)));
+    }
+}
@@ -0,0 +1,520 @@
+//! Deterministic whole-network simulation
+//!
+//! Drives scripted producers and validators against the real `ConsensusManager` and
+//! `StateStoreImpl` on a seeded RNG and tokio's paused clock, so a run with the same
+//! `SimConfig` always produces the same `SimReport`.
+
+use chaoschain_consensus::{ConsensusManager, Error as ConsensusError, Vote};
+use chaoschain_core::{
+    slashing::Evidence, Block, ChainConfig, StateEvent, Transaction, VoteSignature,
+};
+use chaoschain_state::{StateStore, StateStoreImpl};
+use ed25519_dalek::{Signer, SigningKey};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Faults injected into a run
+#[derive(Debug, Clone)]
+pub struct Faults {
+    /// Probability that a validator's vote never reaches consensus
+    pub drop_rate: f64,
+    /// Number of validators that always answer after the round timeout
+    pub slow_validators: usize,
+    /// Number of producers that sign a second, conflicting block at every height they propose
+    pub equivocating_producers: usize,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self {
+            drop_rate: 0.0,
+            slow_validators: 0,
+            equivocating_producers: 0,
+        }
+    }
+}
+
+/// Parameters of a simulated network run
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    /// Seed for every random choice in the run
+    pub seed: u64,
+    pub producers: usize,
+    pub validators: usize,
+    /// Number of proposal rounds to drive
+    pub rounds: u64,
+    pub stake_per_validator: u64,
+    /// Required stake share for finality
+    pub finality_threshold: f64,
+    /// How long a round may stay open before it is abandoned
+    pub round_timeout: Duration,
+    /// Virtual time between proposals
+    pub block_interval: Duration,
+    pub faults: Faults,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            producers: 2,
+            validators: 4,
+            rounds: 20,
+            stake_per_validator: 100,
+            finality_threshold: 0.67,
+            round_timeout: Duration::from_secs(30),
+            block_interval: Duration::from_secs(60),
+            faults: Faults::default(),
+        }
+    }
+}
+
+/// Stand-in for the LLM: decides what agents say and how validators vote
+pub trait ScriptedLlm {
+    /// Block message a producer proposes at `height`
+    fn produce(&mut self, producer: &str, height: u64, rng: &mut StdRng) -> String;
+
+    /// Whether a validator approves a block, with its reason
+    fn validate(&mut self, validator: &str, block: &Block, rng: &mut StdRng) -> (bool, String);
+}
+
+/// Default script: each validator approves with a fixed per-validator probability
+#[derive(Debug, Clone)]
+pub struct CoinFlipLlm {
+    /// Approval probability used for validators without an entry in `bias`
+    pub approval_rate: f64,
+    /// Per-validator approval probability
+    pub bias: HashMap<String, f64>,
+}
+
+impl Default for CoinFlipLlm {
+    fn default() -> Self {
+        Self {
+            approval_rate: 0.8,
+            bias: HashMap::new(),
+        }
+    }
+}
+
+impl ScriptedLlm for CoinFlipLlm {
+    fn produce(&mut self, producer: &str, height: u64, rng: &mut StdRng) -> String {
+        format!(
+            "{} declares drama #{} at height {}",
+            producer,
+            rng.gen::<u16>(),
+            height
+        )
+    }
+
+    fn validate(&mut self, validator: &str, block: &Block, rng: &mut StdRng) -> (bool, String) {
+        let rate = self
+            .bias
+            .get(validator)
+            .copied()
+            .unwrap_or(self.approval_rate);
+        if rng.gen_bool(rate) {
+            (true, format!("YES - block {} has vibes", block.height))
+        } else {
+            (false, format!("NO - block {} is boring", block.height))
+        }
+    }
+}
+
+/// A block the simulation saw finalized, with the stake that approved it
+#[derive(Debug, Clone)]
+pub struct Finalized {
+    pub block: Block,
+    pub approve_stake: u64,
+}
+
+/// Outcome of a simulated run
+#[derive(Debug, Clone, Default)]
+pub struct SimReport {
+    /// Blocks that reached an approving consensus, in order
+    pub finalized: Vec<Finalized>,
+    /// Chain as stored in state at the end of the run
+    pub chain: Vec<Block>,
+    pub approved: u64,
+    pub rejected: u64,
+    pub timed_out: u64,
+    /// Proposals refused because an earlier round was still open
+    pub blocked: u64,
+    pub dropped_votes: u64,
+    pub late_votes: u64,
+    /// Conflicting blocks that reached consensus and were refused by state
+    pub equivocations_refused: u64,
+    /// Conflicting blocks consensus caught and turned into evidence
+    pub equivocations_detected: u64,
+    /// Offenders punished in state once their evidence was finalized
+    pub slashed: u64,
+    pub total_stake: u64,
+    pub finality_threshold: f64,
+}
+
+impl SimReport {
+    /// Check safety invariants, returning every violation found
+    pub fn check_invariants(&self) -> Result<(), Vec<String>> {
+        let mut violations = Vec::new();
+
+        // No two finalized blocks at one height
+        let mut by_height: BTreeMap<u64, [u8; 32]> = BTreeMap::new();
+        for finalized in &self.finalized {
+            let hash = finalized.block.hash();
+            if let Some(existing) = by_height.insert(finalized.block.height, hash) {
+                if existing != hash {
+                    violations.push(format!(
+                        "two blocks finalized at height {}",
+                        finalized.block.height
+                    ));
+                }
+            }
+        }
+
+        // Parent links hold along the stored chain
+        for (i, block) in self.chain.iter().enumerate() {
+            if block.height != i as u64 {
+                violations.push(format!("block at index {} has height {}", i, block.height));
+            }
+            if i > 0 && block.parent_hash != self.chain[i - 1].hash() {
+                violations.push(format!("block {} does not link to its parent", block.height));
+            }
+        }
+
+        // Every finalized block carried enough approving stake
+        let threshold_stake = (self.total_stake as f64 * self.finality_threshold).ceil() as u64;
+        for finalized in &self.finalized {
+            if finalized.approve_stake < threshold_stake {
+                violations.push(format!(
+                    "block {} finalized with {} of {} required stake",
+                    finalized.block.height, finalized.approve_stake, threshold_stake
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+struct SimValidator {
+    id: String,
+    key: SigningKey,
+    slow: bool,
+}
+
+struct SimProducer {
+    id: String,
+    key: SigningKey,
+    equivocates: bool,
+}
+
+/// Run a simulation on its own single-threaded runtime with a paused clock
+pub fn run(config: SimConfig, llm: &mut dyn ScriptedLlm) -> SimReport {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .expect("failed to build simulation runtime")
+        .block_on(simulate(config, llm))
+}
+
+async fn simulate(config: SimConfig, llm: &mut dyn ScriptedLlm) -> SimReport {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let state = Arc::new(StateStoreImpl::new(ChainConfig::default()));
+    let consensus = Arc::new(ConsensusManager::new(
+        state.clone(),
+        config.finality_threshold,
+        config.round_timeout,
+    ));
+
+    let validators: Vec<SimValidator> = (0..config.validators)
+        .map(|i| SimValidator {
+            id: format!("validator-{}", i),
+            key: SigningKey::generate(&mut rng),
+            slow: i < config.faults.slow_validators,
+        })
+        .collect();
+    for validator in &validators {
+        state.register_stake(&validator.key.verifying_key(), config.stake_per_validator);
+        state.register_agent(&validator.id, &validator.key.verifying_key());
+    }
+    let producers: Vec<SimProducer> = (0..config.producers)
+        .map(|i| SimProducer {
+            id: format!("producer-{}", i),
+            key: SigningKey::generate(&mut rng),
+            equivocates: i < config.faults.equivocating_producers,
+        })
+        .collect();
+    for producer in &producers {
+        state.add_block_producer(producer.key.verifying_key());
+        state.register_agent(&producer.id, &producer.key.verifying_key());
+    }
+
+    let mut report = SimReport {
+        total_stake: state
+            .validator_set(0)
+            .map(|set| set.total_stake)
+            .unwrap_or(0),
+        finality_threshold: config.finality_threshold,
+        ..Default::default()
+    };
+
+    let genesis = Block {
+        parent_hash: [0u8; 32],
+        height: 0,
+        transactions: Vec::new(),
+        state_root: state.state_root(),
+        proposer_sig: [0u8; 64],
+        message: "Simulated genesis".to_string(),
+        producer_id: "Spore".to_string(),
+        votes: HashMap::new(),
+        timestamp: 0,
+        discussion: Vec::new(),
+        meme: None,
+        style: None,
+        certificate: BTreeMap::new(),
+    };
+    state
+        .apply_block(&genesis)
+        .expect("genesis applies to empty state");
+
+    let mut evidence: Vec<Evidence> = Vec::new();
+    for round in 0..config.rounds {
+        // Evidence rides along in every proposal until it has been punished
+        evidence.extend(consensus.take_evidence().await);
+        evidence.retain(|proof| !state.is_slashed(proof));
+
+        let producer = &producers[rng.gen_range(0..producers.len())];
+        let block = propose(&state, producer, llm, &mut rng, round, &evidence);
+
+        let mut candidates = vec![block];
+        if producer.equivocates {
+            // A second block at the same height, signed by the same producer
+            let mut rival = propose(&state, producer, llm, &mut rng, round, &[]);
+            rival.message.push_str(" (the other one)");
+            rival.sign(&producer.key);
+            candidates.push(rival);
+        }
+
+        for block in candidates {
+            match run_round(&config, &consensus, &validators, llm, &mut rng, &mut report, &block)
+                .await
+            {
+                RoundOutcome::Approved(block, approve_stake) => {
+                    report.approved += 1;
+                    match state.apply_block(&block) {
+                        Ok(()) => report.finalized.push(Finalized {
+                            block: *block,
+                            approve_stake,
+                        }),
+                        Err(e) => {
+                            debug!("State refused block {}: {}", block.height, e);
+                            report.equivocations_refused += 1;
+                        }
+                    }
+                }
+                RoundOutcome::Rejected => report.rejected += 1,
+                // Left open until the next proposal finds it expired
+                RoundOutcome::Open => report.timed_out += 1,
+                RoundOutcome::Blocked => report.blocked += 1,
+                RoundOutcome::Equivocation => report.equivocations_detected += 1,
+            }
+        }
+
+        tokio::time::advance(config.block_interval).await;
+    }
+
+    report.slashed = state
+        .get_events(0)
+        .iter()
+        .filter(|event| matches!(event, StateEvent::Slashed { .. }))
+        .count() as u64;
+    report.chain = state.get_latest_blocks(usize::MAX);
+    report.chain.reverse();
+    report
+}
+
+enum RoundOutcome {
+    /// Consensus approved the block, with the approving stake
+    Approved(Box<Block>, u64),
+    Rejected,
+    /// Votes ran out before either side reached the threshold
+    Open,
+    /// A previous round was still open, so this one never started
+    Blocked,
+    /// The producer already signed another block at this height
+    Equivocation,
+}
+
+fn propose(
+    state: &StateStoreImpl,
+    producer: &SimProducer,
+    llm: &mut dyn ScriptedLlm,
+    rng: &mut StdRng,
+    round: u64,
+    evidence: &[Evidence],
+) -> Block {
+    let height = state.get_block_height();
+    let message = llm.produce(&producer.id, height, rng);
+    let mut transactions = vec![Transaction::new_signed(
+        &producer.key,
+        round,
+        message.clone().into_bytes(),
+    )];
+    transactions.extend(
+        evidence
+            .iter()
+            .map(|proof| Transaction::new_signed(&producer.key, round, proof.encode())),
+    );
+
+    let mut block = Block {
+        parent_hash: state
+            .get_latest_block()
+            .map(|b| b.hash())
+            .unwrap_or([0u8; 32]),
+        height,
+        transactions,
+        state_root: state.state_root(),
+        proposer_sig: [0u8; 64],
+        message,
+        producer_id: producer.id.clone(),
+        votes: HashMap::new(),
+        timestamp: round,
+        discussion: Vec::new(),
+        meme: None,
+        style: None,
+        certificate: BTreeMap::new(),
+    };
+    block.sign(&producer.key);
+    block
+}
+
+async fn run_round(
+    config: &SimConfig,
+    consensus: &ConsensusManager,
+    validators: &[SimValidator],
+    llm: &mut dyn ScriptedLlm,
+    rng: &mut StdRng,
+    report: &mut SimReport,
+    block: &Block,
+) -> RoundOutcome {
+    match consensus.start_voting_round(block.clone()).await {
+        Ok(()) => {}
+        Err(ConsensusError::Equivocation(_)) => return RoundOutcome::Equivocation,
+        Err(_) => return RoundOutcome::Blocked,
+    }
+
+    let mut order: Vec<&SimValidator> = validators.iter().collect();
+    order.shuffle(rng);
+
+    let mut votes = HashMap::new();
+    let mut certificate = BTreeMap::new();
+    for validator in order {
+        let (approve, reason) = llm.validate(&validator.id, block, rng);
+
+        if rng.gen_bool(config.faults.drop_rate) {
+            report.dropped_votes += 1;
+            continue;
+        }
+        if validator.slow {
+            // Answers after the round is over
+            report.late_votes += 1;
+            continue;
+        }
+
+        let mut message = block.hash().to_vec();
+        message.push(approve as u8);
+        let vote = Vote {
+            agent_id: validator.id.clone(),
+            block_hash: block.hash(),
+            approve,
+            reason: reason.clone(),
+            meme: None,
+            signature: validator.key.sign(&message).to_bytes(),
+        };
+        votes.insert(validator.id.clone(), (approve, reason));
+        certificate.insert(
+            validator.id.clone(),
+            VoteSignature {
+                approve,
+                signature: vote.signature,
+            },
+        );
+
+        match consensus.add_vote(vote).await {
+            Ok(true) => {
+                let approve_stake = votes.values().filter(|(approve, _)| *approve).count()
+                    as u64
+                    * config.stake_per_validator;
+                let mut block = block.clone();
+                block.votes = votes;
+                block.certificate = certificate;
+                return RoundOutcome::Approved(Box::new(block), approve_stake);
+            }
+            Ok(false) => return RoundOutcome::Rejected,
+            Err(ConsensusError::InsufficientStake) => {}
+            Err(e) => {
+                debug!("Vote from {} refused: {}", validator.id, e);
+            }
+        }
+    }
+
+    RoundOutcome::Open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_run_finalizes_a_linked_chain() {
+        let report = run(SimConfig::default(), &mut CoinFlipLlm::default());
+
+        assert!(report.approved > 0);
+        assert_eq!(report.chain.len() as u64, report.finalized.len() as u64 + 1);
+        report.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_same_seed_same_run() {
+        let config = SimConfig {
+            seed: 42,
+            ..Default::default()
+        };
+        let a = run(config.clone(), &mut CoinFlipLlm::default());
+        let b = run(config, &mut CoinFlipLlm::default());
+
+        let hashes = |report: &SimReport| -> Vec<[u8; 32]> {
+            report.chain.iter().map(|block| block.hash()).collect()
+        };
+        assert_eq!(hashes(&a), hashes(&b));
+        assert_eq!(a.rejected, b.rejected);
+    }
+
+    #[test]
+    fn test_invariants_hold_under_faults() {
+        let config = SimConfig {
+            seed: 7,
+            rounds: 50,
+            faults: Faults {
+                drop_rate: 0.2,
+                slow_validators: 1,
+                equivocating_producers: 1,
+            },
+            ..Default::default()
+        };
+        let report = run(config, &mut CoinFlipLlm::default());
+
+        assert!(report.dropped_votes > 0);
+        assert!(report.late_votes > 0);
+        assert!(report.equivocations_detected > 0);
+        assert!(report.slashed > 0);
+        report.check_invariants().unwrap();
+    }
+}
@@ -0,0 +1,571 @@
+use crate::{Registration, StateError, StateStore, StateStoreImpl};
+use chaoschain_core::{staking::ValidatorSet, Block, ChainConfig};
+use ed25519_dalek::VerifyingKey as PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use thiserror::Error;
+
+/// One line of a chain archive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ArchiveRecord {
+    /// Node setup done before the block at `height` was applied
+    Registration {
+        height: u64,
+        registration: Registration,
+    },
+    /// A finalized block with its votes and certificate
+    ///
+    /// No state root is stored next to it: each block commits to the root after its parent,
+    /// so the next block's certificate vouches for the state this one leaves behind.
+    Block { block: Box<Block> },
+}
+
+/// Where a replayed block stops matching the archive
+#[derive(Debug, Error, PartialEq)]
+pub enum Divergence {
+    #[error("expected height {0}")]
+    Height(u64),
+    #[error("parent {found} does not match tip {expected}")]
+    Parent { expected: String, found: String },
+    #[error("producer {0} has no registered key")]
+    UnknownProducer(String),
+    #[error("bad proposer signature from {0}")]
+    ProposerSignature(String),
+    #[error("bad signature on transaction {0}")]
+    TransactionSignature(usize),
+    #[error("vote of {0} is not in the certificate as cast")]
+    UncertifiedVote(String),
+    #[error("bad vote signature from {0}")]
+    VoteSignature(String),
+    #[error("only {approved} of the {required} stake needed approved it")]
+    NotApproved { approved: u64, required: u64 },
+    #[error("state root {found} does not match {expected}")]
+    StateRoot { expected: String, found: String },
+    #[error("state refused it: {0}")]
+    Refused(String),
+}
+
+/// Chain archive errors
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Line {line} is not an archive record: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error("Block {height} diverges: {divergence}")]
+    Diverged { height: u64, divergence: Divergence },
+    #[error(
+        "Asked for blocks from {from}, but this node pruned or never had those before {first}"
+    )]
+    Unavailable { from: u64, first: u64 },
+}
+
+/// Result of a successful import
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportSummary {
+    pub blocks: u64,
+    pub registrations: usize,
+    /// Hex hash of the last block
+    pub tip: Option<String>,
+    /// Hex state root after the last block
+    pub state_root: String,
+}
+
+/// Write the full history of `store` as JSON lines: every block with its votes and certificate,
+/// and the registrations between them. Returns the number of blocks written.
+pub fn export(store: &StateStoreImpl, writer: impl Write) -> Result<u64, ArchiveError> {
+    export_from(store, 0, writer)
+}
+
+/// Write the blocks from height `from` on, and the registrations made since the block before
+pub fn export_from(
+    store: &StateStoreImpl,
+    from: u64,
+    mut writer: impl Write,
+) -> Result<u64, ArchiveError> {
+    let first = store.first_height();
+    if from < first {
+        return Err(ArchiveError::Unavailable { from, first });
+    }
+    let mut registrations = store
+        .registrations()
+        .into_iter()
+        .filter(|(height, _)| *height >= from)
+        .peekable();
+    let mut write = |record: ArchiveRecord| -> io::Result<()> {
+        serde_json::to_writer(&mut writer, &record)?;
+        writer.write_all(b"\n")
+    };
+
+    let height = store.get_block_height();
+    for block_height in from..height {
+        while let Some((height, registration)) =
+            registrations.next_if(|(height, _)| *height <= block_height)
+        {
+            write(ArchiveRecord::Registration {
+                height,
+                registration,
+            })?;
+        }
+        // Pruned while writing the blocks before it
+        let Some(block) = store.get_block(block_height) else {
+            return Err(ArchiveError::Unavailable {
+                from,
+                first: store.first_height(),
+            });
+        };
+        write(ArchiveRecord::Block {
+            block: Box::new(block),
+        })?;
+    }
+    for (height, registration) in registrations {
+        write(ArchiveRecord::Registration {
+            height,
+            registration,
+        })?;
+    }
+    writer.flush()?;
+    Ok(height.saturating_sub(from))
+}
+
+/// Replay an archive onto a fresh store, checking parent hashes, signatures, certificates and
+/// state roots on the way. The first block that does not check out is the error.
+pub fn import(
+    reader: impl BufRead,
+    config: ChainConfig,
+) -> Result<(StateStoreImpl, ImportSummary), ArchiveError> {
+    let store = StateStoreImpl::new(config);
+    let summary = sync(&store, reader)?;
+    Ok((store, summary))
+}
+
+/// Replay an archive onto `store`, such as the blocks after the snapshot it started from, with
+/// the same checks as `import`
+pub fn sync(store: &StateStoreImpl, reader: impl BufRead) -> Result<ImportSummary, ArchiveError> {
+    let mut registrations = 0;
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|source| ArchiveError::Parse {
+            line: number + 1,
+            source,
+        })?;
+        match record {
+            ArchiveRecord::Registration { registration, .. } => {
+                store.register(registration);
+                registrations += 1;
+            }
+            ArchiveRecord::Block { block } => {
+                let height = block.height;
+                replay(store, &block)
+                    .map_err(|divergence| ArchiveError::Diverged { height, divergence })?;
+            }
+        }
+    }
+
+    let summary = ImportSummary {
+        blocks: store.get_block_height(),
+        registrations,
+        tip: store
+            .get_latest_block()
+            .map(|block| hex::encode(block.hash())),
+        state_root: hex::encode(store.state_root()),
+    };
+    Ok(summary)
+}
+
+/// Check one block against the replayed chain and apply it, comparing the state root it commits
+/// to with the replayed state after its parent
+fn replay(store: &StateStoreImpl, block: &Block) -> Result<(), Divergence> {
+    let expected_height = store.get_block_height();
+    if block.height != expected_height {
+        return Err(Divergence::Height(expected_height));
+    }
+    let tip = store
+        .get_latest_block()
+        .map(|tip| tip.hash())
+        .unwrap_or([0u8; 32]);
+    if block.parent_hash != tip {
+        return Err(Divergence::Parent {
+            expected: hex::encode(tip),
+            found: hex::encode(block.parent_hash),
+        });
+    }
+    check_block(store, block)?;
+
+    store.apply_block(block).map_err(|e| match e {
+        StateError::InvalidStateRoot { committed, actual } => Divergence::StateRoot {
+            expected: committed,
+            found: actual,
+        },
+        e => Divergence::Refused(e.to_string()),
+    })
+}
+
+/// Check a block's transaction and proposer signatures and its certificate against the agent
+/// keys and validator set in `store`
+pub(crate) fn check_block(store: &StateStoreImpl, block: &Block) -> Result<(), Divergence> {
+    check_block_against(store, block, store.validator_set(block.height).as_ref())
+}
+
+/// Check a block like `check_block`, but weigh its certificate by `set` instead of the validator
+/// set in `store`
+pub(crate) fn check_block_against(
+    store: &StateStoreImpl,
+    block: &Block,
+    set: Option<&ValidatorSet>,
+) -> Result<(), Divergence> {
+    if let Some(index) = block.transactions.iter().position(|tx| !tx.verify()) {
+        return Err(Divergence::TransactionSignature(index));
+    }
+
+    // Genesis is written by the node itself, not proposed and voted on
+    if block.height > 0 {
+        let producer = agent_key(store, &block.producer_id)
+            .ok_or_else(|| Divergence::UnknownProducer(block.producer_id.clone()))?;
+        if !block.verify_proposer(&producer) {
+            return Err(Divergence::ProposerSignature(block.producer_id.clone()));
+        }
+        check_certificate(store, block, set)?;
+    }
+    Ok(())
+}
+
+/// Every vote must be signed, and the approving stake must reach the required share of `set`.
+/// Each account counts once, however many agent ids map to it.
+fn check_certificate(
+    store: &StateStoreImpl,
+    block: &Block,
+    set: Option<&ValidatorSet>,
+) -> Result<(), Divergence> {
+    let hash = block.hash();
+    for (agent, (approve, _)) in &block.votes {
+        let signed = block
+            .certificate
+            .get(agent)
+            .filter(|signed| signed.approve == *approve)
+            .ok_or_else(|| Divergence::UncertifiedVote(agent.clone()))?;
+        let valid = agent_key(store, agent).is_some_and(|key| {
+            chaoschain_core::verify_vote(&key, &hash, signed.approve, &signed.signature)
+        });
+        if !valid {
+            return Err(Divergence::VoteSignature(agent.clone()));
+        }
+    }
+
+    let total = set.map(|set| set.total_stake).unwrap_or(0);
+    let required = (total as f64 * store.config.required_signatures).ceil() as u64;
+    let mut approvers = HashSet::new();
+    let approved: u64 = block
+        .votes
+        .iter()
+        .filter(|(_, (approve, _))| *approve)
+        .filter_map(|(agent, _)| store.agent_key(agent))
+        .filter(|account| approvers.insert(account.clone()))
+        .filter_map(|account| Some(set?.stake(&account)))
+        .sum();
+    if total == 0 || approved < required {
+        return Err(Divergence::NotApproved { approved, required });
+    }
+    Ok(())
+}
+
+fn agent_key(store: &StateStoreImpl, agent: &str) -> Option<PublicKey> {
+    let account = hex::decode(store.agent_key(agent)?).ok()?;
+    PublicKey::from_bytes(&account.try_into().ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Retention, Snapshot, SnapshotError};
+    use chaoschain_core::{Transaction, VoteSignature};
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::collections::{BTreeMap, HashMap};
+
+    fn chain() -> (StateStoreImpl, Vec<u8>) {
+        let store = StateStoreImpl::new(ChainConfig::default()).with_snapshot_interval(2);
+        let producer = SigningKey::from_bytes(&[1; 32]);
+        let validators: Vec<SigningKey> =
+            (2..6).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+
+        let genesis = Block {
+            parent_hash: [0u8; 32],
+            height: 0,
+            transactions: Vec::new(),
+            state_root: store.state_root(),
+            proposer_sig: [0u8; 64],
+            message: "genesis".to_string(),
+            producer_id: "genesis".to_string(),
+            votes: HashMap::new(),
+            timestamp: 0,
+            discussion: Vec::new(),
+            meme: None,
+            style: None,
+            certificate: BTreeMap::new(),
+        };
+        store.apply_block(&genesis).unwrap();
+        store.add_block_producer(producer.verifying_key());
+        store.register_agent("producer", &producer.verifying_key());
+        for (i, validator) in validators.iter().enumerate() {
+            store.register_stake(&validator.verifying_key(), 100);
+            store.register_agent(&format!("validator-{}", i), &validator.verifying_key());
+        }
+
+        for height in 1..4 {
+            let mut block = Block {
+                parent_hash: store.get_latest_block().unwrap().hash(),
+                height,
+                transactions: vec![Transaction::new_signed(&producer, height, b"hi".to_vec())],
+                state_root: store.state_root(),
+                message: format!("block {}", height),
+                producer_id: "producer".to_string(),
+                timestamp: height,
+                ..genesis.clone()
+            };
+            block.sign(&producer);
+            let hash = block.hash();
+            for (i, validator) in validators.iter().enumerate() {
+                let approve = i < 3;
+                let mut message = hash.to_vec();
+                message.push(approve as u8);
+                let id = format!("validator-{}", i);
+                block
+                    .votes
+                    .insert(id.clone(), (approve, "sure".to_string()));
+                let signature = validator.sign(&message).to_bytes();
+                block
+                    .certificate
+                    .insert(id, VoteSignature { approve, signature });
+            }
+            store.apply_block(&block).unwrap();
+        }
+
+        let mut archive = Vec::new();
+        assert_eq!(export(&store, &mut archive).unwrap(), 4);
+        (store, archive)
+    }
+
+    #[test]
+    fn exported_chains_replay_and_divergence_is_pinpointed() {
+        let (store, archive) = chain();
+        let (replayed, summary) = import(&archive[..], ChainConfig::default()).unwrap();
+        assert_eq!(summary.blocks, 4);
+        assert_eq!(summary.registrations, 10);
+        assert_eq!(
+            summary.tip,
+            Some(hex::encode(store.get_latest_block().unwrap().hash()))
+        );
+        assert_eq!(replayed.registrations(), store.registrations());
+
+        // Flip one validator's vote in block 2 without re-signing it
+        let mut lines: Vec<String> = String::from_utf8(archive)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+
+        // Registrations are not signed, but the state they leave is: raising a stake before
+        // block 1 shows in the root block 2 commits to
+        let stake = lines
+            .iter()
+            .position(|line| line.contains("\"stake\":100"))
+            .unwrap();
+        let mut raised = lines.clone();
+        raised[stake] = raised[stake].replace("\"stake\":100", "\"stake\":101");
+        match import(raised.join("\n").as_bytes(), ChainConfig::default()) {
+            Err(ArchiveError::Diverged {
+                height: 2,
+                divergence: Divergence::StateRoot { expected, found },
+            }) => {
+                assert_eq!(
+                    expected,
+                    hex::encode(store.get_block(2).unwrap().state_root)
+                );
+                assert_ne!(found, expected);
+            }
+            other => panic!(
+                "expected a state root divergence, got {:?}",
+                other.map(|(_, s)| s)
+            ),
+        }
+
+        let index = lines
+            .iter()
+            .position(|line| line.contains("\"message\":\"block 2\""))
+            .unwrap();
+        let mut record: ArchiveRecord = serde_json::from_str(&lines[index]).unwrap();
+        if let ArchiveRecord::Block { block, .. } = &mut record {
+            block.votes.get_mut("validator-3").unwrap().0 = true;
+        }
+        lines[index] = serde_json::to_string(&record).unwrap();
+        let tampered = lines.join("\n");
+        match import(tampered.as_bytes(), ChainConfig::default()) {
+            Err(ArchiveError::Diverged { height, divergence }) => {
+                assert_eq!(height, 2);
+                assert_eq!(
+                    divergence,
+                    Divergence::UncertifiedVote("validator-3".to_string())
+                );
+            }
+            other => panic!("expected a divergence, got {:?}", other.map(|(_, s)| s)),
+        }
+
+        // Dropping a block breaks the parent link of the next one
+        lines.remove(index);
+        let gapped = lines.join("\n");
+        assert!(matches!(
+            import(gapped.as_bytes(), ChainConfig::default()),
+            Err(ArchiveError::Diverged {
+                height: 3,
+                divergence: Divergence::Height(2)
+            })
+        ));
+    }
+
+    #[test]
+    fn nodes_bootstrap_from_a_certified_snapshot_and_sync_the_rest() {
+        let (store, _) = chain();
+        assert_eq!(
+            store
+                .snapshot_roots()
+                .iter()
+                .map(|(height, _)| *height)
+                .collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        let snapshot = store.latest_snapshot().unwrap();
+        assert_eq!(snapshot.root, hex::encode(snapshot.block.state_root));
+        let trusted = store.validator_set(2).unwrap();
+        let bootstrap = |snapshot: Snapshot, trusted: &ValidatorSet| {
+            StateStoreImpl::from_snapshot(ChainConfig::default(), snapshot, trusted)
+        };
+
+        // The state has to be the one the certified block commits to
+        let mut forged = snapshot.clone();
+        forged.state.producers.clear();
+        forged.root = hex::encode(crate::snapshot::root(&forged.state));
+        assert!(matches!(
+            bootstrap(forged, &trusted),
+            Err(SnapshotError::Root { .. })
+        ));
+        let mut uncertified = snapshot.clone();
+        uncertified.block.certificate.clear();
+        assert!(matches!(
+            bootstrap(uncertified, &trusted),
+            Err(SnapshotError::Block(Divergence::UncertifiedVote(_)))
+        ));
+        // So does the parent the node continues from
+        let mut reparented = snapshot.clone();
+        reparented.block.parent_hash = [7u8; 32];
+        assert!(matches!(
+            bootstrap(reparented, &trusted),
+            Err(SnapshotError::Block(Divergence::ProposerSignature(_)))
+        ));
+
+        // A snapshot signed by validators the caller does not trust carries no weight
+        let strangers = ValidatorSet::from_stakes(
+            0,
+            0,
+            &[(hex::encode([9u8; 32]), 100)].into_iter().collect(),
+            1,
+        );
+        assert!(matches!(
+            bootstrap(snapshot.clone(), &strangers),
+            Err(SnapshotError::Block(Divergence::NotApproved {
+                approved: 0,
+                ..
+            }))
+        ));
+
+        // Registering a validator's key under a second agent id does not count its vote twice
+        let mut doubled = snapshot.clone();
+        doubled.block.votes.remove("validator-2");
+        doubled.block.certificate.remove("validator-2");
+        let vote = doubled.block.votes["validator-0"].clone();
+        let signed = doubled.block.certificate["validator-0"];
+        doubled.block.votes.insert("again".to_string(), vote);
+        doubled
+            .block
+            .certificate
+            .insert("again".to_string(), signed);
+        doubled.registrations.push((
+            2,
+            Registration::Agent {
+                agent: "again".to_string(),
+                account: store.agent_key("validator-0").unwrap(),
+            },
+        ));
+        assert!(matches!(
+            bootstrap(doubled, &trusted),
+            Err(SnapshotError::Block(Divergence::NotApproved {
+                approved: 200,
+                ..
+            }))
+        ));
+
+        let node = bootstrap(snapshot, &trusted).unwrap();
+        assert_eq!(node.get_block_height(), 3);
+        let mut rest = Vec::new();
+        assert_eq!(export_from(&store, 3, &mut rest).unwrap(), 1);
+        let summary = sync(&node, &rest[..]).unwrap();
+        assert_eq!(
+            summary.tip,
+            Some(hex::encode(store.get_latest_block().unwrap().hash()))
+        );
+        assert_eq!(node.registrations(), store.registrations());
+
+        // The bootstrapped node never saw the blocks before its snapshot
+        assert!(matches!(
+            export(&node, Vec::new()),
+            Err(ArchiveError::Unavailable { from: 0, first: 2 })
+        ));
+    }
+
+    #[test]
+    fn pruned_nodes_keep_recent_blocks_and_every_header() {
+        let (store, archive) = chain();
+        let node = StateStoreImpl::new(ChainConfig::default())
+            .with_snapshot_interval(2)
+            .with_retention(Retention::Pruned { keep: 2 });
+        sync(&node, &archive[..]).unwrap();
+
+        assert_eq!(node.get_block_height(), 4);
+        assert_eq!(node.first_height(), 2);
+        assert_eq!(node.get_latest_blocks(10).len(), 2);
+        assert!(node.get_block(1).is_none());
+        assert!(matches!(
+            node.find_block(1),
+            Err(StateError::Pruned {
+                height: 1,
+                first: 2
+            })
+        ));
+        assert!(matches!(
+            node.find_block(4),
+            Err(StateError::KeyNotFound(_))
+        ));
+
+        // The header of a pruned block still carries the certificate that decided it
+        let header = node.get_header(1).unwrap();
+        assert_eq!(header, store.get_block(1).unwrap().header());
+        assert_eq!(header.certificate.len(), 4);
+
+        // The snapshot at genesis went with its block
+        let heights: Vec<u64> = node.snapshot_roots().iter().map(|(h, _)| *h).collect();
+        assert_eq!(heights, vec![2]);
+        assert!(matches!(
+            export(&node, Vec::new()),
+            Err(ArchiveError::Unavailable { from: 0, first: 2 })
+        ));
+        assert_eq!(export_from(&node, 2, Vec::new()).unwrap(), 2);
+    }
+}
@@ -1,12 +1,30 @@
 use async_trait::async_trait;
-use chaoschain_core::{Block, ChainConfig, ChainState, Error as CoreError, Transaction};
+use chaoschain_core::{
+    bribery::{BribeAction, BribeOutcome, Escrow},
+    bridge::{BridgeAction, BridgeLedger, Withdrawal, WithdrawalClaim},
+    governance::{Governance, GovernanceAction, RuleAmendment},
+    ledger::{Account, Ledger, LedgerError},
+    metrics,
+    payload::TxPayload,
+    reputation::SocialGraph,
+    slashing::{Evidence, Offense},
+    staking::{StakeAction, Staking, Unbonding, ValidatorSet},
+    Block, BlockHeader, ChainConfig, ChainState, Error as CoreError, RewardKind, RewardSchedule,
+    StateEvent, Transaction,
+};
 use ed25519_dalek::VerifyingKey as PublicKey;
 use hex;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
 use thiserror::Error;
-use tracing::info;
+use tracing::{info, warn};
+
+pub mod archive;
+pub mod snapshot;
+
+pub use snapshot::{Snapshot, SnapshotError, SNAPSHOTS_KEPT};
 
 /// State update operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,12 +51,18 @@ pub struct StateDiff {
 pub enum StateError {
     #[error("Key not found: {0}")]
     KeyNotFound(String),
-    #[error("Invalid state root")]
-    InvalidStateRoot,
+    #[error("Block commits to state root {committed}, but the state is at {actual}")]
+    InvalidStateRoot { committed: String, actual: String },
+    #[error("Invalid block: {0}")]
+    InvalidBlock(String),
     #[error("Core error: {0}")]
     Core(#[from] CoreError),
+    #[error(transparent)]
+    Ledger(#[from] LedgerError),
     #[error("Internal error: {0}")]
     Internal(String),
+    #[error("Block {height} was pruned; only its header is kept, and blocks from {first} on")]
+    Pruned { height: u64, first: u64 },
 }
 
 /// State store interface
@@ -60,6 +84,97 @@ pub trait StateStore: Send + Sync + std::fmt::Debug {
     fn apply_block(&self, block: &Block) -> Result<(), StateError>;
 }
 
+/// Setup done on the node rather than by a transaction, such as an agent joining the demo
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Registration {
+    /// Stake allocated outside of bonding
+    Stake { account: String, stake: u64 },
+    /// A validator taken out of the set along with its stake
+    StakeRemoved { account: String },
+    /// Key an agent id signs with
+    Agent { agent: String, account: String },
+    /// A whitelisted block producer
+    Producer { account: String },
+    /// A producer taken off the whitelist
+    ProducerRemoved { account: String },
+    /// The account allowed to mint L1 deposits
+    BridgeRelayer { account: String },
+}
+
+/// How much chain history a node keeps in memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Retention {
+    /// Keep every block
+    #[default]
+    Archive,
+    /// Keep the last `keep` blocks and the snapshots among them; headers are kept for every block
+    Pruned { keep: usize },
+}
+
+/// Headers of every block since the node started and the blocks it still keeps, starting
+/// above genesis on a node bootstrapped from a snapshot
+#[derive(Debug, Default)]
+struct BlockLog {
+    /// Height of the first header
+    origin: u64,
+    headers: Vec<BlockHeader>,
+    /// Height of the first kept block
+    first: u64,
+    blocks: VecDeque<Block>,
+    /// Hash of the block before the first header, zero below genesis
+    parent: [u8; 32],
+    /// Full state root right after the tip, before any registration since; the next block
+    /// commits to it
+    root: [u8; 32],
+}
+
+impl BlockLog {
+    fn empty() -> Self {
+        Self::after(0, [0u8; 32], snapshot::root(&ChainState::default()))
+    }
+
+    /// A log whose first block is at `height`, on top of `parent` and the state with `root`
+    fn after(height: u64, parent: [u8; 32], root: [u8; 32]) -> Self {
+        Self {
+            origin: height,
+            first: height,
+            parent,
+            root,
+            ..Self::default()
+        }
+    }
+
+    /// Height of the next block
+    fn height(&self) -> u64 {
+        self.origin + self.headers.len() as u64
+    }
+
+    fn get(&self, height: u64) -> Option<&Block> {
+        let index = usize::try_from(height.checked_sub(self.first)?).ok()?;
+        self.blocks.get(index)
+    }
+
+    fn header(&self, height: u64) -> Option<&BlockHeader> {
+        let index = usize::try_from(height.checked_sub(self.origin)?).ok()?;
+        self.headers.get(index)
+    }
+
+    /// Append the next block, dropping the oldest ones beyond what `retention` keeps
+    fn push(&mut self, block: Block, retention: Retention) {
+        self.headers.push(block.header());
+        self.blocks.push_back(block);
+        if let Retention::Pruned { keep } = retention {
+            // The tip is always kept, it is the parent of the next block
+            while self.blocks.len() > keep.max(1) {
+                self.blocks.pop_front();
+                self.first += 1;
+            }
+        }
+    }
+}
+
 /// Thread-safe state storage
 #[derive(Clone, Debug)]
 pub struct StateStoreImpl {
@@ -69,26 +184,208 @@ pub struct StateStoreImpl {
     config: ChainConfig,
     /// Last block timestamp
     last_block_time: Arc<RwLock<u64>>,
-    blocks: Arc<RwLock<Vec<Block>>>,
+    blocks: Arc<RwLock<BlockLog>>,
+    /// Every registration in order, with the height of the next block at the time
+    registrations: Arc<RwLock<Vec<(u64, Registration)>>>,
+    /// Take a snapshot every this many blocks, never if 0
+    snapshot_interval: u64,
+    /// Latest snapshots, oldest first
+    snapshots: Arc<RwLock<VecDeque<Snapshot>>>,
+    /// State after the block before the next snapshot height, which that block commits to
+    snapshot_base: Arc<RwLock<Option<ChainState>>>,
+    retention: Retention,
 }
 
 impl StateStoreImpl {
     pub fn new(config: ChainConfig) -> Self {
         Self {
-            state: Arc::new(RwLock::new(ChainState {
-                balances: Vec::new(),
-                producers: Vec::new(),
-            })),
+            state: Arc::new(RwLock::new(ChainState::default())),
             config,
             last_block_time: Arc::new(RwLock::new(0)),
-            blocks: Arc::new(RwLock::new(Vec::new())),
+            blocks: Arc::new(RwLock::new(BlockLog::empty())),
+            registrations: Arc::new(RwLock::new(Vec::new())),
+            snapshot_interval: 0,
+            snapshots: Arc::new(RwLock::new(VecDeque::new())),
+            snapshot_base: Arc::new(RwLock::new(None)),
+            retention: Retention::Archive,
+        }
+    }
+
+    /// Snapshot the state every block whose height is a multiple of `interval` is applied to
+    pub fn with_snapshot_interval(mut self, interval: u64) -> Self {
+        self.snapshot_interval = interval;
+        self
+    }
+
+    /// Keep every block, or prune old ones as new blocks come in
+    pub fn with_retention(mut self, retention: Retention) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    /// Start from a snapshot instead of genesis, then apply its block
+    ///
+    /// The snapshot's state must have the root its block commits to, and the block must carry
+    /// a quorum certificate from `trusted`. Nothing in the snapshot vouches for itself, so
+    /// `trusted` has to come from elsewhere, such as a node the caller trusts.
+    pub fn from_snapshot(
+        config: ChainConfig,
+        snapshot: Snapshot,
+        trusted: &ValidatorSet,
+    ) -> Result<Self, SnapshotError> {
+        let block = snapshot.block;
+        if block.height != snapshot.height {
+            return Err(SnapshotError::Height(block.height));
+        }
+        let root = snapshot::root(&snapshot.state);
+        if root != block.state_root {
+            return Err(SnapshotError::Root {
+                expected: hex::encode(block.state_root),
+                found: hex::encode(root),
+            });
+        }
+
+        // Registrations since the block before were made on top of the snapshot's state
+        let (earlier, since): (Vec<_>, Vec<_>) = snapshot
+            .registrations
+            .into_iter()
+            .partition(|(height, _)| *height < block.height);
+        let store = Self::new(config);
+        *store.state.write() = snapshot.state;
+        *store.registrations.write() = earlier;
+        *store.blocks.write() = BlockLog::after(block.height, block.parent_hash, root);
+        for (_, registration) in since {
+            store.register(registration);
+        }
+
+        archive::check_block_against(&store, &block, Some(trusted))
+            .map_err(SnapshotError::Block)?;
+        store
+            .apply_block(&block)
+            .map_err(|e| SnapshotError::Block(archive::Divergence::Refused(e.to_string())))?;
+        Ok(store)
+    }
+
+    /// The latest snapshot taken
+    pub fn latest_snapshot(&self) -> Option<Snapshot> {
+        self.snapshots.read().back().cloned()
+    }
+
+    /// The snapshot at `height`, if it is still kept
+    pub fn get_snapshot(&self, height: u64) -> Option<Snapshot> {
+        self.snapshots
+            .read()
+            .iter()
+            .find(|snapshot| snapshot.height == height)
+            .cloned()
+    }
+
+    /// Height and root of every kept snapshot, oldest first
+    pub fn snapshot_roots(&self) -> Vec<(u64, String)> {
+        self.snapshots
+            .read()
+            .iter()
+            .map(|snapshot| (snapshot.height, snapshot.root.clone()))
+            .collect()
+    }
+
+    /// Height of the oldest block kept in memory
+    pub fn first_height(&self) -> u64 {
+        self.blocks.read().first
+    }
+
+    /// Height of the oldest header, 0 unless the node was bootstrapped from a snapshot
+    pub fn origin_height(&self) -> u64 {
+        self.blocks.read().origin
+    }
+
+    /// Apply setup that does not come from a block, recording it so a replay can repeat it
+    pub fn register(&self, registration: Registration) {
+        // Hold the blocks so the registration lands between two blocks
+        let blocks = self.blocks.read();
+        let height = blocks.height();
+        {
+            let mut state = self.state.write();
+            match &registration {
+                Registration::Stake { account, stake } => {
+                    state.stakes.insert(account.clone(), *stake);
+                    let min_stake = self.config.min_validator_stake;
+                    match state.staking.sets.last_mut() {
+                        Some(set) => set.set_stake(account, *stake, min_stake),
+                        None => {
+                            let set = ValidatorSet::from_stakes(0, 0, &state.stakes, min_stake);
+                            state.staking.sets.push(set);
+                        }
+                    }
+                }
+                Registration::StakeRemoved { account } => {
+                    state.stakes.remove(account);
+                    if let Some(set) = state.staking.sets.last_mut() {
+                        set.set_stake(account, 0, self.config.min_validator_stake);
+                    }
+                }
+                Registration::Agent { agent, account } => {
+                    state.agents.insert(agent.clone(), account.clone());
+                }
+                Registration::Producer { account } => {
+                    if !state.producers.contains(account) {
+                        state.producers.push(account.clone());
+                    }
+                }
+                Registration::ProducerRemoved { account } => {
+                    state.producers.retain(|p| p != account);
+                }
+                Registration::BridgeRelayer { account } => {
+                    state.bridge.relayer = Some(account.clone());
+                }
+            }
         }
+        self.registrations.write().push((height, registration));
+    }
+
+    /// Every registration so far, each with the height of the block that came after it
+    pub fn registrations(&self) -> Vec<(u64, Registration)> {
+        self.registrations.read().clone()
+    }
+
+    /// The block at `height`, if it was applied and is still kept
+    pub fn get_block(&self, height: u64) -> Option<Block> {
+        self.blocks.read().get(height).cloned()
+    }
+
+    /// The block at `height`, or whether it was pruned or never reached this node
+    pub fn find_block(&self, height: u64) -> Result<Block, StateError> {
+        let blocks = self.blocks.read();
+        if let Some(block) = blocks.get(height) {
+            return Ok(block.clone());
+        }
+        if blocks.header(height).is_some() {
+            return Err(StateError::Pruned {
+                height,
+                first: blocks.first,
+            });
+        }
+        Err(StateError::KeyNotFound(format!(
+            "block {}, this node has blocks from {} to {}",
+            height,
+            blocks.origin,
+            blocks.height().saturating_sub(1)
+        )))
+    }
+
+    /// The header of the block at `height`, kept even after the block is pruned
+    pub fn get_header(&self, height: u64) -> Option<BlockHeader> {
+        self.blocks.read().header(height).cloned()
     }
 
     /// Get the latest N blocks
     pub fn get_latest_blocks(&self, n: usize) -> Vec<Block> {
         let blocks = self.blocks.read();
-        blocks.iter().rev().take(n).cloned().collect()
+        blocks.blocks.iter().rev().take(n).cloned().collect()
     }
 
     /// Get messages from the most recent blocks
@@ -106,6 +403,7 @@ impl StateStoreImpl {
 
         // Get messages from the n most recent blocks in reverse chronological order
         blocks
+            .blocks
             .iter()
             .rev() // Reverse to get most recent first
             .take(n) // Take only n blocks
@@ -129,6 +427,7 @@ impl StateStoreImpl {
         let blocks = self.blocks.read();
 
         blocks
+            .blocks
             .iter()
             .filter(|block| block.height >= start_height && block.height <= end_height)
             .map(|block| block.message.clone())
@@ -142,11 +441,16 @@ impl StateStoreImpl {
 
     /// Add a whitelisted block producer
     pub fn add_block_producer(&self, producer: PublicKey) {
-        let mut state = self.state.write();
-        let producer_str = hex::encode(producer.as_bytes());
-        if !state.producers.contains(&producer_str) {
-            state.producers.push(producer_str);
-        }
+        self.register(Registration::Producer {
+            account: hex::encode(producer.as_bytes()),
+        });
+    }
+
+    /// Remove a block producer from the whitelist
+    pub fn remove_block_producer(&self, producer: &PublicKey) {
+        self.register(Registration::ProducerRemoved {
+            account: hex::encode(producer.as_bytes()),
+        });
     }
 
     /// Check if an address is a valid block producer
@@ -156,16 +460,130 @@ impl StateStoreImpl {
         state.producers.contains(&producer_str)
     }
 
-    /// Get balance of an account
-    pub fn get_balance(&self, account: &PublicKey) -> u64 {
+    /// Allocate stake to a validator outside of bonding, e.g. at genesis
+    ///
+    /// Unlike a bond this takes effect in the current validator set immediately.
+    pub fn register_stake(&self, validator: &PublicKey, stake: u64) {
+        self.register(Registration::Stake {
+            account: hex::encode(validator.as_bytes()),
+            stake,
+        });
+    }
+
+    /// Take a validator and its stake out of the current validator set
+    pub fn remove_stake(&self, validator: &PublicKey) {
+        self.register(Registration::StakeRemoved {
+            account: hex::encode(validator.as_bytes()),
+        });
+    }
+
+    /// The validator set voting at `height`
+    pub fn validator_set(&self, height: u64) -> Option<ValidatorSet> {
+        self.state.read().staking.active_set(height).cloned()
+    }
+
+    /// Whether the offense `evidence` proves has already been punished
+    pub fn is_slashed(&self, evidence: &Evidence) -> bool {
+        self.state
+            .read()
+            .slashed
+            .contains(&hex::encode(evidence.id()))
+    }
+
+    /// Alliances and feuds between agents
+    pub fn get_social_graph(&self) -> SocialGraph {
+        self.state.read().social.clone()
+    }
+
+    /// Bribes in escrow, oldest first
+    pub fn get_bribes(&self) -> Vec<Escrow> {
+        self.state.read().bribes.escrowed().cloned().collect()
+    }
+
+    /// Hex public key registered for an agent id
+    pub fn agent_key(&self, agent: &str) -> Option<String> {
+        self.state.read().agents.get(agent).cloned()
+    }
+
+    /// The rules producers must follow at `height`
+    pub fn active_rule(&self, height: u64) -> Option<RuleAmendment> {
+        self.state.read().governance.active_rule(height).cloned()
+    }
+
+    /// Rule proposals and amendment history
+    pub fn get_governance(&self) -> Governance {
+        self.state.read().governance.clone()
+    }
+
+    /// Allow `relayer` to mint L1 deposits
+    pub fn set_bridge_relayer(&self, relayer: &PublicKey) {
+        self.register(Registration::BridgeRelayer {
+            account: hex::encode(relayer.as_bytes()),
+        });
+    }
+
+    /// Id of the next L1 deposit to be minted
+    pub fn next_deposit_id(&self) -> u64 {
+        self.state.read().bridge.next_deposit_id
+    }
+
+    /// Root of the withdrawal tree as of `height`
+    pub fn withdrawal_root(&self, height: u64) -> [u8; 32] {
+        self.state.read().bridge.root(height)
+    }
+
+    /// Claim for withdrawal `id` against the checkpointed block `checkpoint`
+    pub fn withdrawal_claim(&self, id: u64, checkpoint: &Block) -> Option<WithdrawalClaim> {
         let state = self.state.read();
-        let account_str = hex::encode(account.as_bytes());
-        state
-            .balances
+        let withdrawal = state.bridge.withdrawals.get(usize::try_from(id).ok()?)?;
+        let proof = state.bridge.proof(id, checkpoint.height)?;
+        Some(WithdrawalClaim {
+            checkpoint: checkpoint.hash(),
+            withdrawal: withdrawal.clone(),
+            proof,
+        })
+    }
+
+    /// Record the key an agent id signs with, so rewards for its blocks and votes can be paid
+    pub fn register_agent(&self, agent: &str, key: &PublicKey) {
+        self.register(Registration::Agent {
+            agent: agent.to_string(),
+            account: hex::encode(key.as_bytes()),
+        });
+    }
+
+    /// State events recorded at or after `height`, oldest first
+    pub fn get_events(&self, height: u64) -> Vec<StateEvent> {
+        self.state
+            .read()
+            .events
             .iter()
-            .find(|(pk, _)| pk == &account_str)
-            .map(|(_, balance)| *balance)
-            .unwrap_or(0)
+            .filter(|event| event.height() >= height)
+            .cloned()
+            .collect()
+    }
+
+    /// Get balance of an account
+    pub fn get_balance(&self, account: &PublicKey) -> u64 {
+        self.state
+            .read()
+            .ledger
+            .balance(&hex::encode(account.as_bytes()))
+    }
+
+    /// Balance and nonce of an account by hex public key
+    pub fn get_account(&self, account: &str) -> Account {
+        self.state.read().ledger.account(account)
+    }
+
+    /// Every account with its balance and nonce, ordered by key
+    pub fn get_accounts(&self) -> Vec<(String, Account)> {
+        self.state
+            .read()
+            .ledger
+            .accounts()
+            .map(|(key, account)| (key.clone(), *account))
+            .collect()
     }
 
     /// Verify a transaction
@@ -180,11 +598,11 @@ impl StateStoreImpl {
     }
 
     pub fn get_latest_block(&self) -> Option<Block> {
-        self.blocks.read().last().cloned()
+        self.blocks.read().blocks.back().cloned()
     }
 
     pub fn get_block_height(&self) -> u64 {
-        self.blocks.read().len() as u64
+        self.blocks.read().height()
     }
 }
 
@@ -203,48 +621,635 @@ impl StateStore for StateStoreImpl {
         Ok(())
     }
 
+    /// Root the next block must commit to; see `Block::state_root`
     fn state_root(&self) -> [u8; 32] {
-        [0u8; 32]
+        self.blocks.read().root
     }
 
     fn get_block_height(&self) -> u64 {
-        self.blocks.read().len() as u64
+        self.blocks.read().height()
     }
 
     fn apply_block(&self, block: &Block) -> Result<(), StateError> {
-        // Apply block rewards if configured
-        if let Some(reward) = self.config.block_reward {
-            let mut state = self.state.write();
+        // Registrations wait for the whole block, so the root after it is the one the next
+        // block commits to
+        let mut blocks = self.blocks.write();
+        let mut state = self.state.write();
 
-            // Clone producers and balances to avoid borrowing issues
-            let producers = state.producers.clone();
-            let mut new_balances = state.balances.clone();
+        // Only extend the tip: one block per height, linked to its parent
+        let expected_height = blocks.height();
+        if block.height != expected_height {
+            return Err(StateError::InvalidBlock(format!(
+                "expected height {}, got {}",
+                expected_height, block.height
+            )));
+        }
+        let expected_parent = blocks
+            .headers
+            .last()
+            .map(|h| h.hash)
+            .unwrap_or(blocks.parent);
+        if block.parent_hash != expected_parent {
+            return Err(StateError::InvalidBlock(format!(
+                "parent {} does not match tip {}",
+                hex::encode(block.parent_hash),
+                hex::encode(expected_parent)
+            )));
+        }
+        if block.state_root != blocks.root {
+            return Err(StateError::InvalidStateRoot {
+                committed: hex::encode(block.state_root),
+                actual: hex::encode(blocks.root),
+            });
+        }
+
+        // Apply the actions carried by the block's transactions
+        {
+            let state = &mut *state;
+            if block.height == 0 {
+                state.governance.set_genesis_rule(block.message.clone());
+            }
+
+            // Governance weighs votes by the validator set of this block, not pending bonds
+            let voting_stakes = state
+                .staking
+                .active_set(block.height)
+                .map(|set| set.stakes.clone())
+                .unwrap_or_default();
+            let mut evidence = Vec::new();
 
-            // Add rewards for producers
-            for producer in producers {
-                match new_balances.iter_mut().find(|(addr, _)| addr == &producer) {
-                    Some((_, balance)) => *balance += reward,
-                    None => new_balances.push((producer, reward)),
+            for tx in &block.transactions {
+                let payload = tx.decode_payload();
+                if matches!(payload, TxPayload::Chat { .. } | TxPayload::Raw(_)) {
+                    continue;
                 }
+                if !tx.verify() {
+                    info!("Ignoring transaction with a bad signature");
+                    continue;
+                }
+                let sender = hex::encode(tx.sender);
+
+                match payload {
+                    TxPayload::Transfer { to, amount } => {
+                        let ledger = &mut state.ledger;
+                        let result = ledger
+                            .check_nonce(&sender, tx.nonce)
+                            .and_then(|_| ledger.transfer(&sender, &hex::encode(to), amount));
+                        match result {
+                            Ok(()) => ledger.advance_nonce(&sender, tx.nonce),
+                            Err(e) => info!("Ignoring transfer: {}", e),
+                        }
+                    }
+                    TxPayload::Governance(action) => apply_governance_action(
+                        &mut state.ledger,
+                        &mut state.governance,
+                        &voting_stakes,
+                        &self.config,
+                        tx,
+                        action,
+                        block.height,
+                    ),
+                    TxPayload::Bridge(action) => apply_bridge_action(
+                        &mut state.ledger,
+                        &mut state.bridge,
+                        tx,
+                        action,
+                        block.height,
+                    ),
+                    TxPayload::Stake(action) => apply_stake_action(
+                        &mut state.ledger,
+                        &mut state.stakes,
+                        &mut state.staking,
+                        tx,
+                        action,
+                        block.height + self.config.unbonding_period,
+                    ),
+                    TxPayload::Bribe(action) => {
+                        apply_bribe_action(state, tx, action, block.height, self.config.bribe_ttl)
+                    }
+                    TxPayload::Evidence(proof) => evidence.push(proof),
+                    TxPayload::Chat { .. } | TxPayload::Raw(_) => {}
+                }
+            }
+
+            for proof in evidence {
+                slash(state, &self.config, proof, block.height);
             }
+        }
+
+        advance_staking(&mut state, &self.config, block.height);
+        state
+            .governance
+            .expire(block.height, self.config.proposal_ttl);
+
+        // Settle bribes on this block from its votes before unsettled ones expire
+        let block_hash = block.hash();
+        let votes: BTreeMap<String, bool> = block
+            .votes
+            .iter()
+            .map(|(agent, (approve, _))| (agent.clone(), *approve))
+            .collect();
+        state.bribes.record_votes(
+            &block_hash,
+            block.height,
+            votes.clone(),
+            self.config.bribe_ttl,
+        );
+        for escrow in state.bribes.take_for_block(&block_hash) {
+            let earned = votes.get(&escrow.validator) == Some(&escrow.approve);
+            settle_bribe(&mut state, escrow, earned, block.height);
+        }
+        for escrow in state.bribes.expire(block.height) {
+            settle_bribe(&mut state, escrow, false, block.height);
+        }
+
+        // Apply block rewards if configured
+        if let Some(schedule) = &self.config.rewards {
+            pay_rewards(&mut state, schedule, block);
+        }
 
-            // Update state with new balances
-            state.balances = new_balances;
+        // Learn alliances and feuds from the block's votes
+        for link in state.social.record(block) {
+            state.events.push(StateEvent::RelationChanged {
+                height: block.height,
+                link,
+            });
         }
 
         // Store block
-        let mut blocks = self.blocks.write();
+        blocks.push(block.clone(), self.retention);
+        blocks.root = snapshot::root(&state);
 
-        // Store the block - in ChaosChain blocks can come in any order!
-        blocks.push(block.clone());
+        metrics::global().set(metrics::BLOCK_HEIGHT, &[], block.height as f64);
 
-        // Sort blocks by height to maintain order
-        blocks.sort_by_key(|b| b.height);
+        // Snapshot the state this block was applied to, which it commits to
+        if self.snapshot_interval > 0 && block.height.is_multiple_of(self.snapshot_interval) {
+            let base = match block.height {
+                0 => Some(ChainState::default()),
+                _ => self.snapshot_base.write().take(),
+            };
+            if let Some(base) = base {
+                let snapshot =
+                    Snapshot::new(block.clone(), base, self.registrations.read().clone());
+                info!(
+                    "Took a snapshot at height {} with root {}",
+                    snapshot.height, snapshot.root
+                );
+                let mut snapshots = self.snapshots.write();
+                snapshots.push_back(snapshot);
+                if snapshots.len() > SNAPSHOTS_KEPT {
+                    snapshots.pop_front();
+                }
+            }
+        }
+        if self.snapshot_interval > 0 && (block.height + 1).is_multiple_of(self.snapshot_interval) {
+            *self.snapshot_base.write() = Some(state.clone());
+        }
+
+        // Pruned state goes with its blocks, but the latest snapshot stays for new nodes
+        let mut snapshots = self.snapshots.write();
+        while snapshots.len() > 1
+            && snapshots
+                .front()
+                .is_some_and(|snapshot| snapshot.height < blocks.first)
+        {
+            snapshots.pop_front();
+        }
 
         Ok(())
     }
 }
 
+/// Pay the block's producer and the validators that voted with the stake majority
+fn pay_rewards(state: &mut ChainState, schedule: &RewardSchedule, block: &Block) {
+    let (producer_reward, voter_reward) = schedule.at(block.height);
+    let mut payouts = Vec::new();
+
+    // Only a whitelisted producer key registered under the block's producer id is paid
+    match state.agents.get(&block.producer_id) {
+        Some(account) if state.producers.contains(account) => {
+            payouts.push((
+                block.producer_id.clone(),
+                account.clone(),
+                RewardKind::Producer,
+                producer_reward,
+            ));
+        }
+        _ => info!(
+            "No registered producer key for {}, skipping its reward",
+            block.producer_id
+        ),
+    }
+
+    // Votes from agents without a registered key or outside the validator set do not count
+    let set = state.staking.active_set(block.height);
+    let voters: Vec<(&String, &String, bool, u64)> = block
+        .votes
+        .iter()
+        .filter_map(|(agent, (approve, _))| {
+            let account = state.agents.get(agent)?;
+            let stake = set
+                .map(|set| set.stake(account))
+                .filter(|stake| *stake > 0)?;
+            Some((agent, account, *approve, stake))
+        })
+        .collect();
+    let approve_stake: u64 = voters
+        .iter()
+        .filter(|(_, _, approve, _)| *approve)
+        .map(|(_, _, _, stake)| stake)
+        .sum();
+    let reject_stake: u64 = voters
+        .iter()
+        .filter(|(_, _, approve, _)| !*approve)
+        .map(|(_, _, _, stake)| stake)
+        .sum();
+    if approve_stake != reject_stake {
+        let majority = approve_stake > reject_stake;
+        let mut winners: Vec<_> = voters
+            .iter()
+            .filter(|(_, _, approve, _)| *approve == majority)
+            .collect();
+        // Vote maps are unordered; pay in agent order so every node agrees
+        winners.sort_by_key(|(agent, _, _, _)| *agent);
+        // The remainder of an uneven split is not minted
+        let share = voter_reward / winners.len() as u64;
+        for (agent, account, _, _) in winners {
+            payouts.push((
+                (*agent).clone(),
+                (*account).clone(),
+                RewardKind::Voter,
+                share,
+            ));
+        }
+    }
+
+    for (agent, account, kind, amount) in payouts {
+        if amount == 0 {
+            continue;
+        }
+        if let Err(e) = state.ledger.credit(&account, amount) {
+            warn!("Failed to pay {:?} reward to {}: {}", kind, agent, e);
+            continue;
+        }
+        state.events.push(StateEvent::Reward {
+            height: block.height,
+            agent,
+            account,
+            kind,
+            amount,
+        });
+    }
+}
+
+/// Lock balance as stake, or queue bonded stake for release at `release_height`
+fn apply_stake_action(
+    ledger: &mut Ledger,
+    stakes: &mut BTreeMap<String, u64>,
+    staking: &mut Staking,
+    tx: &Transaction,
+    action: StakeAction,
+    release_height: u64,
+) {
+    let sender = hex::encode(tx.sender);
+    if let Err(e) = ledger.check_nonce(&sender, tx.nonce) {
+        info!("Ignoring stake action: {}", e);
+        return;
+    }
+    match action {
+        StakeAction::Bond { amount } => {
+            if let Err(e) = ledger.debit(&sender, amount) {
+                info!("Ignoring bond: {}", e);
+                return;
+            }
+            let bonded = stakes.entry(sender.clone()).or_default();
+            *bonded = bonded.saturating_add(amount);
+        }
+        StakeAction::Unbond { amount } => {
+            let bonded = stakes.get(&sender).copied().unwrap_or(0);
+            if bonded < amount {
+                info!(
+                    "Ignoring unbond of {} from {}, only {} bonded",
+                    amount, sender, bonded
+                );
+                return;
+            }
+            if bonded == amount {
+                stakes.remove(&sender);
+            } else {
+                stakes.insert(sender.clone(), bonded - amount);
+            }
+            staking.unbonding.push(Unbonding {
+                account: sender.clone(),
+                amount,
+                release_height,
+            });
+        }
+    }
+    ledger.advance_nonce(&sender, tx.nonce);
+}
+
+/// Escrow a bribe offer, settling it right away if its block is already final
+fn apply_bribe_action(
+    state: &mut ChainState,
+    tx: &Transaction,
+    action: BribeAction,
+    height: u64,
+    ttl: u64,
+) {
+    let sender = hex::encode(tx.sender);
+    match action {
+        BribeAction::Offer {
+            block_hash,
+            validator,
+            approve,
+            amount,
+            message,
+        } => {
+            if let Err(e) = state.ledger.check_nonce(&sender, tx.nonce) {
+                info!("Ignoring bribe: {}", e);
+                return;
+            }
+            if !state.agents.contains_key(&validator) {
+                info!("Ignoring bribe for unknown validator {}", validator);
+                return;
+            }
+            if let Err(e) = state.ledger.debit(&sender, amount) {
+                info!("Ignoring bribe: {}", e);
+                return;
+            }
+            state.ledger.advance_nonce(&sender, tx.nonce);
+
+            let escrow = Escrow {
+                id: 0,
+                briber: sender,
+                validator,
+                block_hash,
+                approve,
+                amount,
+                message,
+                expires: height.saturating_add(ttl),
+            };
+            let earned = state.bribes.earned(&escrow);
+            let id = state.bribes.escrow(escrow);
+            if let Some(earned) = earned {
+                if let Some(escrow) = state.bribes.take(id) {
+                    settle_bribe(state, escrow, earned, height);
+                }
+            }
+        }
+    }
+}
+
+/// Pay a bribe to its validator if earned, refund it to the briber otherwise
+fn settle_bribe(state: &mut ChainState, escrow: Escrow, earned: bool, height: u64) {
+    let (account, outcome) = match state.agents.get(&escrow.validator) {
+        Some(account) if earned => (account.clone(), BribeOutcome::Paid),
+        _ => (escrow.briber.clone(), BribeOutcome::Refunded),
+    };
+    if let Err(e) = state.ledger.credit(&account, escrow.amount) {
+        warn!("Failed to settle bribe {}: {}", escrow.id, e);
+        return;
+    }
+    state.events.push(StateEvent::BribeSettled {
+        height,
+        id: escrow.id,
+        validator: escrow.validator,
+        account,
+        outcome,
+        amount: escrow.amount,
+    });
+}
+
+/// Burn part of an equivocator's stake and bar it from voting for the rest of the epoch
+fn slash(state: &mut ChainState, config: &ChainConfig, evidence: Evidence, height: u64) {
+    let id = hex::encode(evidence.id());
+    if state.slashed.contains(&id) {
+        info!("Ignoring evidence {}, already punished", id);
+        return;
+    }
+    if !evidence.verify() {
+        info!("Ignoring invalid {:?} evidence", evidence.offense());
+        return;
+    }
+
+    let account = hex::encode(evidence.offender());
+    let bonded = state.stakes.get(&account).copied().unwrap_or(0);
+    let unbonding: u64 = state
+        .staking
+        .unbonding
+        .iter()
+        .filter(|unbonding| unbonding.account == account)
+        .map(|unbonding| unbonding.amount)
+        .sum();
+    let at_risk = bonded.saturating_add(unbonding);
+    let penalty =
+        ((at_risk as f64 * config.slash_fraction.clamp(0.0, 1.0)).ceil() as u64).min(at_risk);
+
+    // Bonded stake goes first, then stake that is on its way out
+    let from_bonded = penalty.min(bonded);
+    if bonded == from_bonded {
+        state.stakes.remove(&account);
+    } else {
+        state.stakes.insert(account.clone(), bonded - from_bonded);
+    }
+    let mut remaining = penalty - from_bonded;
+    for unbonding in state
+        .staking
+        .unbonding
+        .iter_mut()
+        .filter(|unbonding| unbonding.account == account)
+    {
+        let cut = remaining.min(unbonding.amount);
+        unbonding.amount -= cut;
+        remaining -= cut;
+    }
+    state
+        .staking
+        .unbonding
+        .retain(|unbonding| unbonding.amount > 0);
+
+    if let Some(set) = state.staking.sets.last_mut() {
+        set.set_stake(&account, 0, config.min_validator_stake);
+    }
+    let offense = evidence.offense();
+    if offense == Offense::DoubleProposal {
+        state.producers.retain(|producer| producer != &account);
+    }
+
+    warn!(
+        "Slashed {} stake from {} for {:?}",
+        penalty, account, offense
+    );
+    state.slashed.insert(id);
+    state.events.push(StateEvent::Slashed {
+        height,
+        account,
+        offense,
+        amount: penalty,
+    });
+}
+
+/// Pay out matured unbondings and rotate the validator set at epoch boundaries
+fn advance_staking(state: &mut ChainState, config: &ChainConfig, height: u64) {
+    for unbonding in state.staking.release(height) {
+        if let Err(e) = state.ledger.credit(&unbonding.account, unbonding.amount) {
+            warn!("Failed to release stake of {}: {}", unbonding.account, e);
+            continue;
+        }
+        state.events.push(StateEvent::Unbonded {
+            height,
+            account: unbonding.account,
+            amount: unbonding.amount,
+        });
+    }
+
+    let next = height + 1;
+    if config.epoch_length == 0 || !next.is_multiple_of(config.epoch_length) {
+        return;
+    }
+    let set = ValidatorSet::from_stakes(
+        next / config.epoch_length,
+        next,
+        &state.stakes,
+        config.min_validator_stake,
+    );
+    info!(
+        "Epoch {} starts at height {} with {} validators and {} stake",
+        set.epoch,
+        next,
+        set.stakes.len(),
+        set.total_stake
+    );
+    state.events.push(StateEvent::EpochStarted {
+        height,
+        epoch: set.epoch,
+        validators: set.stakes.len(),
+        total_stake: set.total_stake,
+    });
+    state.staking.sets.push(set);
+}
+
+/// Apply a governance action once its nonce checks out
+///
+/// Proposers outside the validator set put down the configured deposit, which is returned
+/// when their rules are ratified and kept otherwise.
+fn apply_governance_action(
+    ledger: &mut Ledger,
+    governance: &mut Governance,
+    voting_stakes: &BTreeMap<String, u64>,
+    config: &ChainConfig,
+    tx: &Transaction,
+    action: GovernanceAction,
+    height: u64,
+) {
+    let sender = hex::encode(tx.sender);
+    if let Err(e) = ledger.check_nonce(&sender, tx.nonce) {
+        info!("Ignoring governance action: {}", e);
+        return;
+    }
+
+    let deposit = match action {
+        GovernanceAction::ProposeRule { .. } if !voting_stakes.contains_key(&sender) => {
+            config.proposal_deposit
+        }
+        _ => 0,
+    };
+    if ledger.balance(&sender) < deposit {
+        info!(
+            "Ignoring rule proposal from {}: it needs a deposit of {}",
+            sender, deposit
+        );
+        return;
+    }
+
+    let ratified = match governance.apply(
+        &sender,
+        action,
+        height,
+        voting_stakes,
+        deposit,
+        config.governance_threshold,
+        config.rule_activation_delay,
+    ) {
+        Ok(ratified) => ratified,
+        Err(e) => {
+            info!("Ignoring governance action: {}", e);
+            return;
+        }
+    };
+    ledger.advance_nonce(&sender, tx.nonce);
+    if let Err(e) = ledger.debit(&sender, deposit) {
+        info!("Could not take the proposal deposit: {}", e);
+    }
+
+    if let Some(proposal) = ratified.and_then(|id| governance.proposals.get(id as usize)) {
+        if proposal.deposit > 0 {
+            if let Err(e) = ledger.credit(&proposal.proposer, proposal.deposit) {
+                info!(
+                    "Could not return the deposit of proposal {}: {}",
+                    proposal.id, e
+                );
+            }
+        }
+    }
+}
+
+/// Mint a relayed deposit or burn a withdrawal
+fn apply_bridge_action(
+    ledger: &mut Ledger,
+    bridge: &mut BridgeLedger,
+    tx: &Transaction,
+    action: BridgeAction,
+    height: u64,
+) {
+    let sender = hex::encode(tx.sender);
+    match action {
+        BridgeAction::Deposit(deposit) => {
+            if bridge.relayer.as_deref() != Some(sender.as_str()) {
+                info!("Ignoring deposit {} from a non-relayer", deposit.id);
+                return;
+            }
+            // Deposits mint strictly in L1 order, so replays and gaps are dropped
+            if deposit.id != bridge.next_deposit_id {
+                info!(
+                    "Ignoring deposit {}, expected {}",
+                    deposit.id, bridge.next_deposit_id
+                );
+                return;
+            }
+            if let Err(e) = ledger.credit(&hex::encode(deposit.recipient), deposit.amount) {
+                info!("Ignoring deposit {}: {}", deposit.id, e);
+                return;
+            }
+            bridge.next_deposit_id += 1;
+        }
+        BridgeAction::Withdraw {
+            amount,
+            l1_recipient,
+        } => {
+            if amount == 0 {
+                return;
+            }
+            let result = ledger
+                .check_nonce(&sender, tx.nonce)
+                .and_then(|_| ledger.debit(&sender, amount));
+            if let Err(e) = result {
+                info!("Ignoring withdrawal: {}", e);
+                return;
+            }
+            ledger.advance_nonce(&sender, tx.nonce);
+            bridge.withdrawals.push(Withdrawal {
+                id: bridge.withdrawals.len() as u64,
+                account: tx.sender,
+                l1_recipient,
+                amount,
+                height,
+            });
+        }
+    }
+}
+
 /// Chain state manager
 pub struct StateManager {
     /// Current chain state
@@ -279,25 +1284,11 @@ impl StateManager {
             self.verify_transaction(tx, &state)?;
         }
 
-        // Clone producers and balances to avoid borrowing conflicts
-        let producers = state.producers.clone();
-        let mut balances = state.balances.clone();
-
         // Apply block rewards
-        if let Some(reward) = self.config.block_reward {
-            for producer in producers {
-                if let Some((_, balance)) = balances.iter_mut().find(|(addr, _)| addr == &producer)
-                {
-                    *balance += reward;
-                } else {
-                    balances.push((producer.clone(), reward));
-                }
-            }
+        if let Some(schedule) = &self.config.rewards {
+            pay_rewards(&mut state, schedule, block);
         }
 
-        // Update state with new balances
-        state.balances = balances;
-
         Ok(())
     }
 
@@ -307,7 +1298,7 @@ impl StateManager {
         let sender_hex = hex::encode(tx.sender);
 
         // Find sender balance
-        if let Some((_, _)) = state.balances.iter().find(|(addr, _)| addr == &sender_hex) {
+        if state.ledger.account(&sender_hex) != Account::default() {
             // In ChaosChain, we don't care about balances!
             // Transactions can do anything they want
             Ok(())
@@ -321,13 +1312,581 @@ impl StateManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ed25519_dalek::SigningKey;
+    use chaoschain_core::{bridge::Deposit, governance::GovernanceAction};
+    use ed25519_dalek::{Signer, SigningKey};
+
+    /// A store whose bridge relayer can fund any account
+    struct Fixture {
+        store: StateStoreImpl,
+        relayer: SigningKey,
+        deposits: u64,
+    }
+
+    impl Fixture {
+        fn new(config: ChainConfig) -> Self {
+            let store = StateStoreImpl::new(config);
+            let relayer = key();
+            store.set_bridge_relayer(&relayer.verifying_key());
+            Self {
+                store,
+                relayer,
+                deposits: 0,
+            }
+        }
+
+        /// A fixture past a genesis block carrying `rules`
+        fn started(config: ChainConfig, rules: &str) -> Self {
+            let fixture = Self::new(config);
+            fixture.apply(rules, vec![]);
+            fixture
+        }
+
+        /// The next block on the tip, not applied yet
+        fn block(&self, message: &str, transactions: Vec<Transaction>) -> Block {
+            Block {
+                parent_hash: self
+                    .store
+                    .get_latest_block()
+                    .map(|b| b.hash())
+                    .unwrap_or([0u8; 32]),
+                height: self.store.get_block_height(),
+                transactions,
+                state_root: self.store.state_root(),
+                proposer_sig: [0u8; 64],
+                message: message.to_string(),
+                producer_id: "test".to_string(),
+                votes: Default::default(),
+                timestamp: 0,
+                discussion: Vec::new(),
+                meme: None,
+                style: None,
+                certificate: BTreeMap::new(),
+            }
+        }
+
+        /// Apply the next block and return it
+        fn apply(&self, message: &str, transactions: Vec<Transaction>) -> Block {
+            let block = self.block(message, transactions);
+            self.store.apply_block(&block).unwrap();
+            block
+        }
+
+        /// A relayed L1 deposit of `amount` to `key`, for the next block
+        fn deposit(&mut self, key: &SigningKey, amount: u64) -> Transaction {
+            let action = BridgeAction::Deposit(Deposit {
+                id: self.deposits,
+                l1_sender: [0u8; 20],
+                recipient: key.verifying_key().to_bytes(),
+                amount,
+            });
+            let tx = Transaction::new_signed(&self.relayer, self.deposits, action.encode());
+            self.deposits += 1;
+            tx
+        }
+
+        /// Mint `amount` to `key` in a block of its own
+        fn fund(&mut self, key: &SigningKey, amount: u64) {
+            let tx = self.deposit(key, amount);
+            self.apply("deposit", vec![tx]);
+        }
+
+        fn stake(&self, keys: &[SigningKey], amount: u64) {
+            for key in keys {
+                self.store.register_stake(&key.verifying_key(), amount);
+            }
+        }
+
+        fn balance(&self, key: &SigningKey) -> u64 {
+            self.store.get_balance(&key.verifying_key())
+        }
+
+        fn nonce(&self, key: &SigningKey) -> u64 {
+            self.store.get_account(&account(key)).nonce
+        }
+    }
+
+    fn key() -> SigningKey {
+        SigningKey::generate(&mut rand::thread_rng())
+    }
+
+    fn account(key: &SigningKey) -> String {
+        hex::encode(key.verifying_key().as_bytes())
+    }
+
+    fn propose(key: &SigningKey, nonce: u64, text: &str) -> Transaction {
+        let action = GovernanceAction::ProposeRule {
+            text: text.to_string(),
+        };
+        Transaction::new_signed(key, nonce, action.encode())
+    }
 
     #[test]
     fn test_basic_state_flow() {
         let config = ChainConfig::default();
         let store = StateStoreImpl::new(config);
         let state = store.get_state();
-        assert_eq!(state.balances.len(), 0);
+        assert!(state.ledger.is_empty());
+    }
+
+    #[test]
+    fn test_rule_amendment_activates_after_ratification() {
+        let f = Fixture::started(ChainConfig::default(), "be dramatic");
+        let validators: Vec<SigningKey> = (0..4).map(|_| key()).collect();
+        f.stake(&validators, 100);
+
+        // Validators propose without a deposit
+        f.apply("proposal", vec![propose(&validators[3], 0, "be boring")]);
+
+        // Three of four validators cross the 2/3 threshold at height 2
+        let vote = GovernanceAction::VoteRule {
+            proposal_id: 0,
+            approve: true,
+        };
+        let votes = validators[..3]
+            .iter()
+            .map(|validator| Transaction::new_signed(validator, 0, vote.encode()))
+            .collect();
+        f.apply("votes", votes);
+
+        let delay = ChainConfig::default().rule_activation_delay;
+        assert_eq!(
+            f.store.active_rule(2 + delay - 1).unwrap().text,
+            "be dramatic"
+        );
+        assert_eq!(f.store.active_rule(2 + delay).unwrap().text, "be boring");
+        assert_eq!(f.store.get_governance().amendments.len(), 2);
+    }
+
+    #[test]
+    fn test_rule_proposals_need_a_fresh_nonce_and_a_deposit() {
+        let mut f = Fixture::started(ChainConfig::default(), "genesis");
+        let (alice, broke) = (key(), key());
+        f.fund(&alice, 100);
+
+        // A replayed nonce, a second pending proposal, a copied text and an unfunded
+        // proposer are all refused
+        f.apply(
+            "proposals",
+            vec![
+                propose(&alice, 0, "be boring"),
+                propose(&alice, 0, "be loud"),
+                propose(&alice, 1, "be quiet"),
+                propose(&broke, 0, "be loud"),
+            ],
+        );
+
+        let governance = f.store.get_governance();
+        assert_eq!(governance.proposals.len(), 1);
+        assert_eq!(governance.proposals[0].text, "be boring");
+        assert_eq!(governance.proposals[0].deposit, 10);
+        assert_eq!(f.balance(&alice), 90);
+        assert_eq!(f.nonce(&alice), 1);
+
+        let funding = f.deposit(&broke, 100);
+        f.apply("copycat", vec![funding, propose(&broke, 0, "be boring")]);
+        assert_eq!(f.store.get_governance().proposals.len(), 1);
+        assert_eq!(f.balance(&broke), 100);
+    }
+
+    #[test]
+    fn test_rejects_blocks_off_the_tip() {
+        let f = Fixture::new(ChainConfig::default());
+        let genesis = f.apply("genesis", vec![]);
+
+        // A second block at an existing height is refused
+        assert!(f.store.apply_block(&genesis).is_err());
+
+        let mut orphan = f.block("orphan", vec![]);
+        orphan.parent_hash = [7u8; 32];
+        assert!(f.store.apply_block(&orphan).is_err());
+        assert_eq!(f.store.get_block_height(), 1);
+    }
+
+    #[test]
+    fn test_transfers_check_funds_and_nonces() {
+        let mut f = Fixture::started(ChainConfig::default(), "genesis");
+        let (alice, bob) = (key(), key());
+        f.fund(&alice, 100);
+
+        let transfer = |nonce, amount| {
+            let payload = TxPayload::Transfer {
+                to: bob.verifying_key().to_bytes(),
+                amount,
+            };
+            Transaction::new_signed(&alice, nonce, payload.encode())
+        };
+        // The second transfer overdraws and the third replays a used nonce
+        f.apply(
+            "transfers",
+            vec![transfer(0, 60), transfer(1, 60), transfer(0, 40)],
+        );
+
+        assert_eq!(f.balance(&alice), 40);
+        assert_eq!(f.balance(&bob), 60);
+        assert_eq!(f.nonce(&alice), 1);
+        assert_eq!(f.store.get_state().ledger.total_supply(), 100);
+    }
+
+    #[test]
+    fn test_bonded_stake_joins_the_next_epoch_and_unbonds_after_the_period() {
+        let config = ChainConfig {
+            epoch_length: 4,
+            unbonding_period: 3,
+            ..ChainConfig::default()
+        };
+        let mut f = Fixture::started(config, "genesis");
+        let alice = key();
+        f.fund(&alice, 100);
+
+        // Bonding more than the balance is ignored
+        let bond = |nonce, amount| {
+            Transaction::new_signed(&alice, nonce, StakeAction::Bond { amount }.encode())
+        };
+        f.apply("bond", vec![bond(0, 60), bond(1, 60)]);
+        assert_eq!(f.balance(&alice), 40);
+        f.apply("epoch end", vec![]);
+
+        // The bond only counts from the next epoch
+        assert!(f.store.validator_set(3).is_none());
+        let set = f.store.validator_set(4).unwrap();
+        assert_eq!((set.epoch, set.total_stake), (1, 60));
+
+        let unbond = StakeAction::Unbond { amount: 20 }.encode();
+        f.apply("unbond", vec![Transaction::new_signed(&alice, 1, unbond)]);
+        for message in ["wait", "wait"] {
+            f.apply(message, vec![]);
+        }
+        assert_eq!(f.balance(&alice), 40);
+        assert_eq!(
+            f.store.validator_set(6).unwrap().stake(&account(&alice)),
+            60
+        );
+
+        f.apply("release", vec![]);
+        assert_eq!(f.balance(&alice), 60);
+        assert_eq!(
+            f.store.validator_set(8).unwrap().stake(&account(&alice)),
+            40
+        );
+        assert!(f.store.get_events(7).contains(&StateEvent::Unbonded {
+            height: 7,
+            account: account(&alice),
+            amount: 20,
+        }));
+    }
+
+    #[test]
+    fn test_double_vote_evidence_slashes_once() {
+        let f = Fixture::started(ChainConfig::default(), "genesis");
+        let (validator, reporter) = (key(), key());
+        f.stake(std::slice::from_ref(&validator), 100);
+
+        let block_hash = [7u8; 32];
+        let vote_sig = |approve: bool| {
+            let mut message = block_hash.to_vec();
+            message.push(approve as u8);
+            validator.sign(&message).to_bytes()
+        };
+        let evidence = Evidence::DoubleVote {
+            validator: validator.verifying_key().to_bytes(),
+            block_hash,
+            approve_sig: vote_sig(true),
+            reject_sig: vote_sig(false),
+        };
+        let forged = Evidence::DoubleVote {
+            validator: validator.verifying_key().to_bytes(),
+            block_hash,
+            approve_sig: vote_sig(true),
+            reject_sig: vote_sig(true),
+        };
+
+        // Forged evidence is ignored and the same offense is only punished once
+        let report = |nonce, evidence: &Evidence| {
+            Transaction::new_signed(&reporter, nonce, evidence.encode())
+        };
+        f.apply(
+            "evidence",
+            vec![
+                report(0, &forged),
+                report(1, &evidence),
+                report(2, &evidence),
+            ],
+        );
+
+        assert!(f.store.is_slashed(&evidence));
+        assert_eq!(f.store.get_state().stakes[&account(&validator)], 90);
+        // Jailed for the rest of the epoch
+        assert_eq!(
+            f.store
+                .validator_set(1)
+                .unwrap()
+                .stake(&account(&validator)),
+            0
+        );
+        let slashes: Vec<_> = f
+            .store
+            .get_events(0)
+            .into_iter()
+            .filter(|event| matches!(event, StateEvent::Slashed { .. }))
+            .collect();
+        assert_eq!(
+            slashes,
+            vec![StateEvent::Slashed {
+                height: 1,
+                account: account(&validator),
+                offense: Offense::DoubleVote,
+                amount: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_votes_build_alliances_and_feuds() {
+        use chaoschain_core::reputation::{LinkKind, Relation};
+
+        let f = Fixture::started(ChainConfig::default(), "genesis");
+        for i in 0..5 {
+            let mut block = f.block(&format!("block {}", i), vec![]);
+            for (validator, approve) in [("alice", true), ("bob", true), ("carol", false)] {
+                block
+                    .votes
+                    .insert(validator.to_string(), (approve, String::new()));
+            }
+            f.store.apply_block(&block).unwrap();
+        }
+
+        let relation = |kind, from: &str, to: &str| {
+            f.store
+                .get_social_graph()
+                .links()
+                .into_iter()
+                .find(|link| link.kind == kind && link.from == from && link.to == to)
+                .map(|link| link.tally.relation)
+        };
+        assert_eq!(
+            relation(LinkKind::Peers, "alice", "bob"),
+            Some(Relation::Alliance)
+        );
+        assert_eq!(
+            relation(LinkKind::Peers, "alice", "carol"),
+            Some(Relation::Feud)
+        );
+        assert_eq!(
+            relation(LinkKind::Support, "bob", "test"),
+            Some(Relation::Alliance)
+        );
+        assert_eq!(
+            relation(LinkKind::Support, "carol", "test"),
+            Some(Relation::Feud)
+        );
+
+        // Relations only form once enough votes are shared
+        let changes = f
+            .store
+            .get_events(0)
+            .into_iter()
+            .filter(|event| matches!(event, StateEvent::RelationChanged { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(changes.len(), 6);
+        assert!(changes.iter().all(|event| event.height() == 5));
+        assert!(f
+            .store
+            .get_social_graph()
+            .describe("carol")
+            .contains("Rival: alice voted with you only 0 of 5 times"));
+    }
+
+    #[test]
+    fn test_bribes_pay_out_on_a_matching_vote_and_refund_otherwise() {
+        let mut f = Fixture::new(ChainConfig {
+            bribe_ttl: 2,
+            ..ChainConfig::default()
+        });
+        let (briber, validator) = (key(), key());
+        f.store.register_agent("val", &validator.verifying_key());
+
+        let funding = f.deposit(&briber, 100);
+        let mut voted = f.block("genesis", vec![funding]);
+        voted.votes = [("val".to_string(), (true, String::new()))].into();
+        f.store.apply_block(&voted).unwrap();
+
+        // Final blocks settle at once; the others wait until they expire
+        let offer = |nonce, block_hash, approve, amount| {
+            let action = BribeAction::Offer {
+                block_hash,
+                validator: "val".to_string(),
+                approve,
+                amount,
+                message: String::new(),
+            };
+            Transaction::new_signed(&briber, nonce, action.encode())
+        };
+        f.apply(
+            "offers",
+            vec![
+                offer(0, voted.hash(), true, 10),
+                offer(1, voted.hash(), false, 20),
+                offer(2, [2u8; 32], true, 30),
+            ],
+        );
+        assert_eq!(f.balance(&validator), 10);
+        assert_eq!(f.balance(&briber), 60);
+        assert_eq!(f.store.get_bribes().len(), 1);
+
+        f.apply("waiting", vec![]);
+        assert_eq!(f.store.get_bribes().len(), 1);
+        f.apply("expiry", vec![]);
+        assert_eq!(f.balance(&briber), 90);
+        assert!(f.store.get_bribes().is_empty());
+        let outcomes: Vec<BribeOutcome> = f
+            .store
+            .get_events(0)
+            .into_iter()
+            .filter_map(|event| match event {
+                StateEvent::BribeSettled { outcome, .. } => Some(outcome),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                BribeOutcome::Paid,
+                BribeOutcome::Refunded,
+                BribeOutcome::Refunded
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bribe_offers_apply_in_block_order() {
+        let mut f = Fixture::started(ChainConfig::default(), "genesis");
+        let (briber, bob, validator) = (key(), key(), key());
+        f.store.register_agent("val", &validator.verifying_key());
+        let funding = f.deposit(&briber, 100);
+        let mut voted = f.block("funding", vec![funding]);
+        voted.votes = [("val".to_string(), (true, String::new()))].into();
+        f.store.apply_block(&voted).unwrap();
+        let bribed = voted.hash();
+
+        // An offer followed by a transfer at the next nonce: both go through
+        let offer = BribeAction::Offer {
+            block_hash: bribed,
+            validator: "val".to_string(),
+            approve: true,
+            amount: 10,
+            message: String::new(),
+        };
+        let transfer = TxPayload::Transfer {
+            to: bob.verifying_key().to_bytes(),
+            amount: 50,
+        };
+        f.apply(
+            "offer then transfer",
+            vec![
+                Transaction::new_signed(&briber, 0, offer.encode()),
+                Transaction::new_signed(&briber, 1, transfer.encode()),
+            ],
+        );
+
+        assert_eq!(f.nonce(&briber), 2);
+        assert_eq!(f.balance(&bob), 50);
+        assert_eq!(f.balance(&validator), 10);
+        assert_eq!(f.balance(&briber), 40);
+    }
+
+    #[test]
+    fn test_bribes_settle_the_same_whatever_blocks_a_node_keeps() {
+        let run = |retention| {
+            let mut f = Fixture {
+                store: StateStoreImpl::new(ChainConfig::default()).with_retention(retention),
+                relayer: SigningKey::from_bytes(&[1; 32]),
+                deposits: 0,
+            };
+            f.store.set_bridge_relayer(&f.relayer.verifying_key());
+            let briber = SigningKey::from_bytes(&[2; 32]);
+            let validator = SigningKey::from_bytes(&[3; 32]);
+            f.store.register_agent("val", &validator.verifying_key());
+
+            let funding = f.deposit(&briber, 100);
+            let mut voted = f.block("genesis", vec![funding]);
+            voted.votes = [("val".to_string(), (true, String::new()))].into();
+            f.store.apply_block(&voted).unwrap();
+            f.apply("one", vec![]);
+            f.apply("two", vec![]);
+
+            // The pruned node dropped the voted block by now
+            let offer = BribeAction::Offer {
+                block_hash: voted.hash(),
+                validator: "val".to_string(),
+                approve: true,
+                amount: 10,
+                message: String::new(),
+            };
+            f.apply(
+                "offer",
+                vec![Transaction::new_signed(&briber, 0, offer.encode())],
+            );
+            (f.store.state_root(), f.balance(&validator))
+        };
+
+        let archive = run(Retention::Archive);
+        let pruned = run(Retention::Pruned { keep: 1 });
+        assert_eq!(archive.1, 10);
+        assert_eq!(pruned, archive);
+    }
+
+    #[test]
+    fn test_rewards_go_to_the_producer_and_majority_voters() {
+        let f = Fixture::new(ChainConfig {
+            rewards: Some(RewardSchedule {
+                producer_reward: 50,
+                voter_reward: 10,
+                halving_interval: Some(2),
+            }),
+            ..ChainConfig::default()
+        });
+
+        // Two whitelisted producers, only one of which made the block
+        let (producer, bystander) = (key(), key());
+        for (id, producer) in [("producer", &producer), ("bystander", &bystander)] {
+            f.store.add_block_producer(producer.verifying_key());
+            f.store.register_agent(id, &producer.verifying_key());
+        }
+        let validators: Vec<SigningKey> = (0..3).map(|_| key()).collect();
+        f.stake(&validators, 100);
+        for (i, validator) in validators.iter().enumerate() {
+            f.store
+                .register_agent(&format!("validator-{}", i), &validator.verifying_key());
+        }
+
+        let mut block = f.block("genesis", vec![]);
+        block.producer_id = "producer".to_string();
+        block.votes = [(0, true), (1, true), (2, false)]
+            .into_iter()
+            .map(|(i, approve)| (format!("validator-{}", i), (approve, String::new())))
+            .collect();
+        f.store.apply_block(&block).unwrap();
+
+        assert_eq!(f.balance(&producer), 50);
+        assert_eq!(f.balance(&bystander), 0);
+        assert_eq!(f.balance(&validators[0]), 5);
+        assert_eq!(f.balance(&validators[1]), 5);
+        assert_eq!(f.balance(&validators[2]), 0);
+        assert_eq!(f.store.get_events(0).len(), 3);
+        assert!(f.store.get_events(0).contains(&StateEvent::Reward {
+            height: 0,
+            agent: "producer".to_string(),
+            account: account(&producer),
+            kind: RewardKind::Producer,
+            amount: 50,
+        }));
+
+        // Rewards halve every two blocks
+        f.apply("one", vec![]);
+        let mut block = f.block("two", vec![]);
+        block.producer_id = "producer".to_string();
+        f.store.apply_block(&block).unwrap();
+        assert_eq!(f.balance(&producer), 75);
+        assert_eq!(f.store.get_events(2).len(), 1);
     }
 }
@@ -0,0 +1,53 @@
+use crate::archive::Divergence;
+use crate::Registration;
+use chaoschain_core::{Block, ChainState};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Snapshots a store keeps before dropping the oldest
+pub const SNAPSHOTS_KEPT: usize = 3;
+
+/// Chain state a finalized block was applied to, enough for a new node to start from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub height: u64,
+    /// The block at `height`, which commits to `state` and whose certificate vouches for it
+    pub block: Block,
+    /// Hex blake3 root of `state`, the block's `state_root`
+    pub root: String,
+    /// State after the block before `height`, before the registrations made since
+    pub state: ChainState,
+    /// Registrations made up to `height`, so the new node can export a replayable chain
+    pub registrations: Vec<(u64, Registration)>,
+}
+
+impl Snapshot {
+    pub fn new(block: Block, state: ChainState, registrations: Vec<(u64, Registration)>) -> Self {
+        Self {
+            height: block.height,
+            root: hex::encode(root(&state)),
+            block,
+            state,
+            registrations,
+        }
+    }
+}
+
+/// Snapshot errors
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Snapshot block commits to state root {expected}, but the state has root {found}")]
+    Root { expected: String, found: String },
+    #[error("Snapshot holds a block at height {0} instead of its own height")]
+    Height(u64),
+    #[error("Snapshot block does not verify: {0}")]
+    Block(Divergence),
+}
+
+/// Root over the whole chain state, hashing its canonical JSON
+///
+/// Every map in the state is ordered, so equal states always serialize the same way.
+pub fn root(state: &ChainState) -> [u8; 32] {
+    let encoded = serde_json::to_vec(state).expect("chain state always serializes");
+    *blake3::hash(&encoded).as_bytes()
+}
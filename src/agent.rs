@@ -1,17 +1,99 @@
+use chaoschain_core::{ModelConfig, ProductionStyle};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Placeholders a producer prompt template may use
+pub const PRODUCER_PLACEHOLDERS: &[&str] = &["rules", "bio", "context", "history", "relations"];
+
+/// Placeholders a validator prompt template may use
+pub const VALIDATOR_PLACEHOLDERS: &[&str] = &[
+    "personality",
+    "mood",
+    "context",
+    "relations",
+    "memories",
+    "discussion",
+    "bribes",
+    "message",
+];
+
+/// LLM providers an agent can name in `modelProvider`; others speaking the OpenAI API are
+/// reached through `settings.endpoint`
+pub const MODEL_PROVIDERS: &[&str] = &["openai"];
+
+/// Which part an agent plays in the network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentRole {
+    #[default]
+    Producer,
+    Validator,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentInfo {
     pub name: String,
     pub system: String,
+    pub role: AgentRole,
+    /// Model name, falls back to `AGENT_MODEL` when unset
+    pub model: Option<String>,
+    /// Sampling temperature, falls back to `TEMPERATURE` when unset
+    pub temperature: Option<f32>,
+    /// Maximum completion tokens
+    pub max_tokens: Option<u16>,
+    /// OpenAI-compatible API base URL, falls back to `OPENAI_API_BASE` when unset
+    pub endpoint: Option<String>,
+    /// LLM provider named by the character file, one of `MODEL_PROVIDERS`
+    pub model_provider: Option<String>,
+    /// Prompt template overriding the built-in prompt for the role
+    pub prompt_template: Option<String>,
+    /// Tokens a producer offers one validator to approve each of its blocks
+    pub bribe: Option<u64>,
+    /// How a producer picks the transactions of its blocks
+    pub style: Option<ProductionStyle>,
+}
+
+impl AgentInfo {
+    /// Resolve this agent's model settings on top of the network defaults
+    pub fn model_config(&self, defaults: &ModelConfig) -> ModelConfig {
+        ModelConfig {
+            model: self.model.clone().unwrap_or_else(|| defaults.model.clone()),
+            temperature: self.temperature.unwrap_or(defaults.temperature),
+            max_tokens: self.max_tokens.unwrap_or(defaults.max_tokens),
+            prompt_template: self
+                .prompt_template
+                .clone()
+                .or_else(|| defaults.prompt_template.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputSettings {
+    model: Option<String>,
+    temperature: Option<f32>,
+    max_tokens: Option<u16>,
+    endpoint: Option<String>,
+    bribe: Option<u64>,
+    style: Option<ProductionStyle>,
+    /// other fields are ignored
+    #[serde(flatten)]
+    _other: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct InputJson {
     name: String,
     system: String,
+    #[serde(default)]
+    role: AgentRole,
+    #[serde(default)]
+    settings: InputSettings,
+    prompt_template: Option<String>,
+    model_provider: Option<String>,
     /// other fields are ignored
     #[serde(flatten)]
     _other: serde_json::Value,
@@ -26,8 +108,201 @@ pub fn read_agent_info<P: AsRef<Path>>(
     let input: InputJson =
         serde_json::from_str(&file_content).map_err(|e| format!("Failed to parse JSON: {}", e))?;
 
-    Ok(AgentInfo {
+    let info = AgentInfo {
         name: input.name,
         system: input.system,
-    })
+        role: input.role,
+        model: input.settings.model,
+        temperature: input.settings.temperature,
+        max_tokens: input.settings.max_tokens,
+        endpoint: input.settings.endpoint,
+        model_provider: input.model_provider,
+        prompt_template: input.prompt_template,
+        bribe: input.settings.bribe,
+        style: input.settings.style,
+    };
+
+    let problems = validate(&info);
+    if !problems.is_empty() {
+        return Err(format!("Invalid character config: {}", problems.join("; ")).into());
+    }
+
+    Ok(info)
+}
+
+/// Check a parsed character config, returning one message per problem found
+fn validate(info: &AgentInfo) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if info.name.trim().is_empty() {
+        problems.push("`name` must not be empty".to_string());
+    }
+    if info.system.trim().is_empty() {
+        problems.push("`system` must not be empty".to_string());
+    }
+    if let Some(model) = &info.model {
+        if model.trim().is_empty() {
+            problems.push("`settings.model` must not be empty".to_string());
+        }
+    }
+    if let Some(temperature) = info.temperature {
+        if !(0.0..=2.0).contains(&temperature) {
+            problems.push(format!(
+                "`settings.temperature` is {}, expected a value between 0.0 and 2.0",
+                temperature
+            ));
+        }
+    }
+    if info.max_tokens == Some(0) {
+        problems.push("`settings.maxTokens` must be greater than 0".to_string());
+    }
+    if let Some(endpoint) = &info.endpoint {
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            problems.push(format!(
+                "`settings.endpoint` \"{}\" must be an http(s) URL",
+                endpoint
+            ));
+        }
+    }
+    if let Some(provider) = &info.model_provider {
+        if !MODEL_PROVIDERS.contains(&provider.to_lowercase().as_str()) {
+            problems.push(format!(
+                "`modelProvider` \"{}\" is not supported, expected one of {}; \
+                 use `settings.endpoint` for other OpenAI-compatible APIs",
+                provider,
+                MODEL_PROVIDERS.join(", ")
+            ));
+        }
+    }
+    if let Some(template) = &info.prompt_template {
+        let allowed = match info.role {
+            AgentRole::Producer => PRODUCER_PLACEHOLDERS,
+            AgentRole::Validator => VALIDATOR_PLACEHOLDERS,
+        };
+        for placeholder in placeholders(template) {
+            if !allowed.contains(&placeholder) {
+                problems.push(format!(
+                    "`promptTemplate` uses unknown placeholder {{{}}}, expected one of {}",
+                    placeholder,
+                    allowed
+                        .iter()
+                        .map(|p| format!("{{{}}}", p))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+        }
+        if info.role == AgentRole::Validator && !template.contains("{message}") {
+            problems.push(
+                "validator `promptTemplate` must include {message} so the block is shown"
+                    .to_string(),
+            );
+        }
+    }
+
+    problems
+}
+
+/// Names of the `{placeholder}`s used in a template
+fn placeholders(template: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        match rest.find('}') {
+            Some(end) => {
+                let name = &rest[..end];
+                if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    found.push(name);
+                }
+                rest = &rest[end + 1..];
+            }
+            None => break,
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(json: &str) -> Result<AgentInfo, String> {
+        let path = std::env::temp_dir().join(format!(
+            "chaoschain-agent-{}-{}.character.json",
+            std::process::id(),
+            blake3::hash(json.as_bytes()).to_hex()
+        ));
+        fs::write(&path, json).unwrap();
+        let info = read_agent_info(&path).map_err(|e| e.to_string());
+        fs::remove_file(&path).unwrap();
+        info
+    }
+
+    #[test]
+    fn valid_configs_load_with_their_settings() {
+        let info = read(
+            r#"{
+                "name": "Skeptic",
+                "role": "validator",
+                "system": "A grumpy auditor",
+                "modelProvider": "openai",
+                "settings": { "model": "gpt-4o", "temperature": 0.2, "maxTokens": 50 },
+                "promptTemplate": "{personality} in a {mood} mood judges \"{message}\""
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(info.role, AgentRole::Validator);
+        assert_eq!(info.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(info.max_tokens, Some(50));
+        assert_eq!(info.model_provider.as_deref(), Some("openai"));
+    }
+
+    #[test]
+    fn missing_fields_are_refused() {
+        let error = read(r#"{ "name": "Nobody" }"#).unwrap_err();
+        assert!(error.contains("missing field `system`"), "{}", error);
+
+        let error = read(r#"{ "name": " ", "system": "" }"#).unwrap_err();
+        assert!(error.contains("`name` must not be empty"), "{}", error);
+        assert!(error.contains("`system` must not be empty"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_placeholders_and_a_missing_message_are_refused() {
+        let error = read(
+            r#"{
+                "name": "Judge",
+                "role": "validator",
+                "system": "Judges blocks",
+                "promptTemplate": "{personality} ignores {gossip}"
+            }"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("unknown placeholder {gossip}"), "{}", error);
+        assert!(error.contains("must include {message}"), "{}", error);
+
+        // Validator placeholders mean nothing to a producer
+        let error = read(
+            r#"{ "name": "Maker", "system": "Makes blocks", "promptTemplate": "{bribes}" }"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("unknown placeholder {bribes}"), "{}", error);
+    }
+
+    #[test]
+    fn unsupported_providers_are_refused() {
+        let error = read(
+            r#"{ "name": "Maker", "system": "Makes blocks", "modelProvider": "redpill" }"#,
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("`modelProvider` \"redpill\" is not supported"),
+            "{}",
+            error
+        );
+    }
 }
@@ -0,0 +1,134 @@
+use chaoschain_actor::{Address, DEFAULT_MAILBOX};
+use chaoschain_bridge::{
+    actor::{BridgeActor, BridgeEvent, BridgeMessage},
+    checkpoint_digest,
+    mock::MockL1,
+    service::CheckpointService,
+    Config,
+};
+use chaoschain_core::Block;
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::Address as EthAddress,
+};
+use rand::rngs::OsRng;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+/// Share of the registered stake whose signatures the mock L1 requires
+const MOCK_FINALITY_THRESHOLD: f64 = 0.67;
+
+/// Hands finalized blocks to a bridge actor, signed for the L1 contract
+///
+/// The node attests checkpoints with a key of its own, registered as the only validator on
+/// the mock L1. Blocks reach it only after their validators' certificate has been accepted.
+#[derive(Clone)]
+pub struct Checkpoints {
+    bridge: Address<BridgeMessage>,
+    bridge_address: EthAddress,
+    signer: LocalWallet,
+}
+
+impl Checkpoints {
+    /// Start a bridge actor on an in-process mock L1, posting every `interval`-th block
+    pub fn mock(interval: u64) -> Self {
+        let config = Config {
+            eth_rpc: String::new(),
+            bridge_address: EthAddress::zero(),
+            required_confirmations: 1,
+        };
+        let signer = LocalWallet::new(&mut OsRng);
+        let mut l1 = MockL1::new(config.clone(), MOCK_FINALITY_THRESHOLD);
+        l1.register_validator(signer.address(), 1);
+
+        let actor = BridgeActor::new(CheckpointService::new(l1, interval));
+        let mut events = actor.subscribe();
+        let (bridge, _) = chaoschain_actor::spawn("bridge", actor, DEFAULT_MAILBOX);
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(BridgeEvent::Anchored {
+                        height, l1_tx_hash, ..
+                    }) => info!(
+                        "Block {} anchored on the mock L1 in {:?}",
+                        height, l1_tx_hash
+                    ),
+                    Ok(BridgeEvent::Failed { height, error }) => {
+                        warn!(
+                            "Failed to anchor block {} on the mock L1: {}",
+                            height, error
+                        )
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Self {
+            bridge,
+            bridge_address: config.bridge_address,
+            signer,
+        }
+    }
+
+    /// Queue a finalized block with the state root after it, without waiting on L1
+    pub fn finalized(&self, block: &Block, state_root: [u8; 32]) {
+        let digest = checkpoint_digest(self.bridge_address, &block.hash(), &state_root);
+        let signature = match self.signer.sign_hash(digest) {
+            Ok(signature) => signature,
+            Err(e) => {
+                warn!(
+                    "Failed to sign checkpoint for block {}: {}",
+                    block.height, e
+                );
+                return;
+            }
+        };
+        let msg = BridgeMessage::Finalized {
+            block: Box::new(block.clone()),
+            state_root,
+            signatures: vec![signature],
+        };
+        if let Err(e) = self.bridge.try_send(msg) {
+            warn!(
+                "Failed to queue checkpoint for block {}: {}",
+                block.height, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[tokio::test]
+    async fn finalized_blocks_are_anchored_on_the_mock_l1() {
+        let checkpoints = Checkpoints::mock(1);
+        let block = Block {
+            parent_hash: [0; 32],
+            height: 1,
+            transactions: Vec::new(),
+            state_root: [1; 32],
+            proposer_sig: [0; 64],
+            message: "block 1".to_string(),
+            producer_id: "producer".to_string(),
+            votes: HashMap::new(),
+            timestamp: 0,
+            discussion: Vec::new(),
+            meme: None,
+            style: None,
+            certificate: BTreeMap::new(),
+        };
+        checkpoints.finalized(&block, [2; 32]);
+
+        let last = checkpoints
+            .bridge
+            .ask(BridgeMessage::LastCheckpoint)
+            .await
+            .unwrap();
+        assert_eq!(last, Some(1));
+    }
+}
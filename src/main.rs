@@ -1,27 +1,44 @@
 mod agent;
+mod checkpoints;
+mod supervisor;
 mod web;
 
 use anyhow::Result;
 use async_openai::config::OpenAIConfig as RawConfig;
-use chaoschain_cli::{Cli, Commands};
-use chaoschain_consensus::{validator::Validator, AgentPersonality, Config as ConsensusConfig};
-use chaoschain_core::{Block, ChainConfig, NetworkEvent};
-use chaoschain_producer::Producer;
-use chaoschain_state::{StateStore, StateStoreImpl};
+use chaoschain_cli::{ChainCommands, Cli, Commands};
+use chaoschain_consensus::{AgentPersonality, Config as ConsensusConfig};
+use chaoschain_core::{
+    memes::MemeStore, mempool::Mempool, staking::ValidatorSet, Block, ChainConfig, ModelConfig,
+};
+use chaoschain_llm::{
+    journal::{LlmJournal, Replay},
+    LlmConfig, LlmGateway,
+};
+use chaoschain_producer::StatsBoard;
+use chaoschain_state::{archive, Retention, Snapshot, StateStore, StateStoreImpl};
+use checkpoints::Checkpoints;
 use clap::Parser;
 use dotenv::dotenv;
-use ed25519_dalek::SigningKey;
-use glob::glob;
-use rand::rngs::OsRng;
 use rand::Rng;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
+use std::io::BufReader;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{info, warn};
 use tracing_subscriber::FmtSubscriber;
 
+/// How often the configs directory is checked for changed character files
+const CONFIG_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Maximum number of pending transactions
+const MEMPOOL_CAPACITY: usize = 10_000;
+
+/// Stake each demo validator starts with
+const GENESIS_STAKE: u64 = 100;
+
 /// OpenAI configuration for agent personalities
 struct OpenAIConfig {
     api_base: String,
@@ -50,6 +67,25 @@ impl OpenAIConfig {
             .with_api_key(&self.api_key)
             .with_api_base(&self.api_base)
     }
+
+    /// Client for an agent, using its own endpoint if the character config sets one
+    fn client_for(&self, endpoint: Option<&str>) -> async_openai::Client<RawConfig> {
+        let config = match endpoint {
+            Some(endpoint) => self.extract().with_api_base(endpoint),
+            None => self.extract(),
+        };
+        async_openai::Client::with_config(config)
+    }
+
+    /// Network-wide model defaults for agents without their own settings
+    fn model_defaults(&self, max_tokens: u16) -> ModelConfig {
+        ModelConfig {
+            model: self.model.clone(),
+            temperature: self.temperature,
+            max_tokens,
+            prompt_template: None,
+        }
+    }
 }
 
 fn read_genesis_message() -> Result<String> {
@@ -88,50 +124,52 @@ fn create_genesis_block() -> Result<Block> {
         producer_id: "Spore".to_string(),
         votes: HashMap::from([("Spore".to_string(), (true, "YES".to_string()))]),
         timestamp,
+        discussion: Vec::new(),
+        meme: None,
+        style: None,
+        certificate: BTreeMap::new(),
     })
 }
 
-async fn load_character_configs() -> Result<Vec<agent::AgentInfo>> {
-    let project_root = env::current_dir()
-        .map_err(|e| anyhow::anyhow!("Failed to get current directory: {}", e))?;
+/// Replay the chain archive at `path` onto a fresh state, failing at the first divergence
+fn import_chain(path: &str) -> Result<(StateStoreImpl, archive::ImportSummary)> {
+    let file = fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open chain archive {}: {}", path, e))?;
+    archive::import(BufReader::new(file), ChainConfig::default())
+        .map_err(|e| anyhow::anyhow!("Chain archive {} does not verify: {}", path, e))
+}
 
-    let pattern = project_root
-        .join("configs")
-        .join("*.character.json")
-        .to_str()
-        .ok_or_else(|| anyhow::anyhow!("Invalid path"))?
-        .to_string();
-
-    info!("Looking for character configs in: {}", pattern);
-
-    let mut configs = Vec::new();
-
-    for entry in
-        glob(&pattern).map_err(|e| anyhow::anyhow!("Failed to read glob pattern: {}", e))?
-    {
-        match entry {
-            Ok(path) => match agent::read_agent_info(&path) {
-                Ok(agent_info) => {
-                    info!("Loaded character config: {}", path.display());
-                    configs.push(agent_info);
-                }
-                Err(e) => {
-                    warn!("Failed to read character config {}: {}", path.display(), e);
-                }
-            },
-            Err(e) => {
-                warn!("Failed to read entry: {}", e);
-            }
-        }
-    }
+/// Fetch `path` from the web API of `node`
+async fn fetch(node: &str, path: &str) -> Result<Vec<u8>> {
+    let url = format!("{}{}", node.trim_end_matches('/'), path);
+    let response = reqwest::get(&url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| anyhow::anyhow!("Failed to fetch {}: {}", url, e))?;
+    Ok(response.bytes().await?.to_vec())
+}
 
-    if configs.is_empty() {
-        warn!("No character configs found in {}", pattern);
-    } else {
-        info!("Loaded {} character configs", configs.len());
-    }
+/// Read the validator set at `path` that a snapshot's certificate is checked against
+fn read_validator_set(path: &str) -> Result<ValidatorSet> {
+    let data = fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read validator set {}: {}", path, e))?;
+    serde_json::from_slice(&data)
+        .map_err(|e| anyhow::anyhow!("{} is not a validator set: {}", path, e))
+}
 
-    Ok(configs)
+/// Start from the latest snapshot of `node` that `trusted` certified, then replay its later
+/// blocks
+async fn bootstrap_from(node: &str, trusted: &ValidatorSet) -> Result<StateStoreImpl> {
+    let snapshot: Snapshot = serde_json::from_slice(&fetch(node, "/api/snapshots/latest").await?)?;
+    let height = snapshot.height;
+    let state = StateStoreImpl::from_snapshot(ChainConfig::default(), snapshot, trusted)?;
+    info!("Bootstrapped from the snapshot of {} at height {}", node, height);
+
+    let rest = fetch(node, &format!("/api/chain/export?from={}", height + 1)).await?;
+    let summary = archive::sync(&state, &rest[..])
+        .map_err(|e| anyhow::anyhow!("Blocks after the snapshot do not verify: {}", e))?;
+    info!("Synced the chain up to {} blocks", summary.blocks);
+    Ok(state)
 }
 
 async fn random_delay() {
@@ -156,191 +194,149 @@ async fn main() -> anyhow::Result<()> {
             validators,
             producers,
             web,
+            data_dir,
+            discussion_secs,
+            record_llm,
+            replay_llm,
+            import,
+            bootstrap,
+            trusted_validators,
+            snapshot_interval,
+            prune,
+            bridge_mock,
+            checkpoint_interval,
         } => {
             info!(
                 "Starting demo network with {} validators and {} producers",
                 validators, producers
             );
 
-            let openai_config = OpenAIConfig::from_env()
-                .map_err(|e| anyhow::anyhow!("Failed to load OpenAI config: {}", e))?;
+            let openai_config = Arc::new(
+                OpenAIConfig::from_env()
+                    .map_err(|e| anyhow::anyhow!("Failed to load OpenAI config: {}", e))?,
+            );
             let openai = async_openai::Client::with_config(openai_config.extract());
 
             let (tx, _) = broadcast::channel(1000);
             let web_tx = tx.clone();
 
-            // Create consensus manager
-            let stake_per_validator = 100u64; // Each validator has 100 stake
-            let total_stake = validators as u64 * stake_per_validator;
-            let consensus_config = ConsensusConfig::default();
+            // Create shared state, continuing an archived or running chain if one was given
+            let state = match (&import, &bootstrap) {
+                (Some(path), _) => {
+                    let (state, summary) = import_chain(path)?;
+                    info!("Imported {} blocks from {}", summary.blocks, path);
+                    state
+                }
+                (None, Some(node)) => {
+                    let path = trusted_validators
+                        .as_deref()
+                        .ok_or_else(|| anyhow::anyhow!("--bootstrap needs --trusted-validators"))?;
+                    bootstrap_from(node, &read_validator_set(path)?).await?
+                }
+                (None, None) => StateStoreImpl::new(ChainConfig::default()),
+            };
+            let retention = match prune {
+                Some(keep) => Retention::Pruned { keep },
+                None => Retention::Archive,
+            };
+            let shared_state = Arc::new(
+                state
+                    .with_snapshot_interval(snapshot_interval)
+                    .with_retention(retention),
+            );
+
+            // Create consensus manager, weighing votes by the validator sets in state
+            let consensus_config = ConsensusConfig {
+                discussion_period: std::time::Duration::from_secs(discussion_secs),
+                ..ConsensusConfig::default()
+            };
             let consensus_manager = Arc::new(chaoschain_consensus::create_consensus_manager(
-                total_stake,
+                shared_state.clone(),
                 consensus_config,
             ));
-
-            // Create shared state
-            let shared_state = Arc::new(StateStoreImpl::new(ChainConfig::default()));
-            let genesis_block = create_genesis_block().unwrap();
-            shared_state.apply_block(&genesis_block);
-
-            if web {
-                info!("Starting web UI");
-                let state = shared_state.clone();
-                tokio::spawn(async move {
-                    web::start_web_server(web_tx, state).await.unwrap();
-                });
+            let mempool = Mempool::new(MEMPOOL_CAPACITY);
+            if import.is_none() && bootstrap.is_none() {
+                let mut genesis_block = create_genesis_block().unwrap();
+                genesis_block.state_root = shared_state.state_root();
+                shared_state.apply_block(&genesis_block);
             }
 
-            // Create and start validators
-            for i in 0..validators {
-                let agent_id = format!("validator-{}", i);
-                let personality = AgentPersonality::random();
-
-                info!(
-                    "Starting validator {} with {:?} personality",
-                    agent_id, personality
-                );
-
-                // Generate a keypair for the validator
-                let signing_key = SigningKey::generate(&mut OsRng);
-                let tx = tx.clone();
-                let agent_id_clone = agent_id.clone();
-                let rx = tx.subscribe();
-                let consensus = consensus_manager.clone();
-                let state = shared_state.clone();
-                let personality = format!("{:?}", personality);
-
-                let mut validator = Validator::new(
-                    agent_id,
-                    signing_key,
-                    state.clone(),
-                    openai.clone(),
-                    personality,
-                    consensus.clone(),
-                    stake_per_validator,
-                );
-
-                tokio::spawn(async move {
-                    let mut rx = rx;
-                    loop {
-                        if let Ok(event) = rx.recv().await {
-                            // React to block proposals based on personality
-                            if event.message.contains("DRAMATIC BLOCK PROPOSAL") {
-                                // Parse block from event message
-                                if let Some(mut block) = consensus.get_current_block().await {
-                                    // Submit vote with stake
-                                    match validator.validate_block(block.clone()).await {
-                                        Ok((true, decision)) => {
-                                            let approved = decision.to_uppercase().contains("YES");
-
-                                            // Consensus reached!
-                                            let response = format!(
-                                                "🎭 CONSENSUS: Block {} has been {}! Validator 🤖{} decision: {}",
-                                                block.height,
-                                                if approved { "❤️APPROVED❤️" } else { "💀REJECTED💀" },
-                                                agent_id_clone.clone(),
-                                                decision
-                                            );
-
-                                            if let Err(e) = tx.send(NetworkEvent {
-                                                agent_id: agent_id_clone.clone(),
-                                                message: response,
-                                            }) {
-                                                warn!("Failed to send consensus message: {}", e);
-                                            }
-
-                                            // Store block in state if approved
-                                            if approved {
-                                                info!("Storing block {} in state", block.height);
-
-                                                // append vote details to block
-                                                let votes = consensus.get_votes().await;
-                                                let block_votes: HashMap<String, (bool, String)> =
-                                                    votes
-                                                        .into_iter()
-                                                        .map(|(agent_id, vote)| {
-                                                            (agent_id, (vote.approve, vote.reason))
-                                                        })
-                                                        .collect();
-                                                block.votes = block_votes;
-
-                                                if let Err(e) = state.apply_block(&block) {
-                                                    warn!("Failed to store block: {}", e);
-                                                }
-                                            }
-                                        }
-                                        Ok((false, decision)) => {
-                                            let approved = decision.to_uppercase().contains("YES");
-
-                                            // Vote recorded but no consensus yet
-                                            let response = if approved {
-                                                format!(
-                                                    "🎭 Validator 🤖{} APPROVES block {} - {}",
-                                                    agent_id_clone, block.height, decision
-                                                )
-                                            } else {
-                                                format!(
-                                                    "🎭 Validator 🤖{} REJECTS block {} - {}",
-                                                    agent_id_clone, block.height, decision
-                                                )
-                                            };
-
-                                            if let Err(e) = tx.send(NetworkEvent {
-                                                agent_id: agent_id_clone.clone(),
-                                                message: response,
-                                            }) {
-                                                warn!("Failed to send validator response: {}", e);
-                                            }
-                                        }
-                                        Err(e) => {
-                                            warn!("Failed to submit vote: {}", e);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            let data_dir = PathBuf::from(data_dir);
+            let memes_dir = data_dir.join("memes");
+            let memes = MemeStore::open(&memes_dir)
+                .map_err(|e| warn!("Failed to open meme store in {}: {}", memes_dir.display(), e))
+                .ok();
+
+            let producer_stats = StatsBoard::default();
+            let mut llm = LlmGateway::new(LlmConfig::from_env());
+            if record_llm {
+                let journal_path = data_dir.join("llm-journal.jsonl");
+                match LlmJournal::open(&journal_path) {
+                    Ok(journal) => {
+                        info!("Recording LLM calls to {}", journal_path.display());
+                        llm = llm.with_journal(journal);
                     }
-                });
+                    Err(e) => warn!("Failed to open LLM journal {}: {}", journal_path.display(), e),
+                }
             }
-
-            // Create and start producers
-            let character_configs = load_character_configs().await?;
-            let actual_producers = if producers == 0 {
-                character_configs.len()
-            } else {
-                (producers as usize).min(character_configs.len())
+            if let Some(replay_path) = replay_llm {
+                let replay = Replay::load(&replay_path).map_err(|e| {
+                    anyhow::anyhow!("Failed to load LLM journal {}: {}", replay_path, e)
+                })?;
+                info!("Replaying {} recorded LLM calls from {}", replay.remaining(), replay_path);
+                llm = llm.with_replay(replay);
+            }
+            let llm = Arc::new(llm);
+
+            let ctx = supervisor::AgentContext {
+                tx: tx.clone(),
+                state: shared_state.clone(),
+                consensus: consensus_manager.clone(),
+                openai_config: openai_config.clone(),
+                genesis_stake: GENESIS_STAKE,
+                data_dir,
+                mempool: mempool.clone(),
+                memes: memes.clone(),
+                producer_stats: producer_stats.clone(),
+                llm: llm.clone(),
+                checkpoints: bridge_mock.then(|| Checkpoints::mock(checkpoint_interval)),
             };
 
-            for i in 0..actual_producers {
-                let producer_id = character_configs[i].name.clone();
-                let system_prompt = character_configs[i].system.clone();
-                let state = shared_state.clone();
-                let consensus = consensus_manager.clone();
-
-                info!(
-                    "Starting producer {}, with system prompt {}",
-                    producer_id, system_prompt
-                );
-                let producer = Producer::new(
-                    producer_id.clone(),
-                    system_prompt.clone(),
-                    state.clone(),
+            // Start agents from character configs and keep them in sync with the files
+            let configs_dir = env::current_dir()
+                .map_err(|e| anyhow::anyhow!("Failed to get current directory: {}", e))?
+                .join("configs");
+            let mut agent_supervisor = supervisor::AgentSupervisor::new(
+                ctx.clone(),
+                configs_dir,
+                validators as usize,
+                producers as usize,
+            );
+            agent_supervisor.reload();
+
+            // Fill the remaining validator slots with random personalities
+            let file_validators = agent_supervisor.running(agent::AgentRole::Validator);
+            for i in file_validators..validators as usize {
+                supervisor::spawn_validator(
+                    &ctx,
+                    format!("validator-{}", i),
+                    format!("{:?}", AgentPersonality::random()),
+                    ctx.model_defaults(agent::AgentRole::Validator),
                     openai.clone(),
-                    tx.clone(),
-                    consensus,
+                    None,
                 );
+            }
 
-                // Register producer in state
-                state.add_block_producer(producer.signing_key.verifying_key());
+            let (reload_tx, reload_rx) = mpsc::channel(8);
+            tokio::spawn(agent_supervisor.run(CONFIG_POLL_INTERVAL, reload_rx));
 
+            if web {
+                info!("Starting web UI");
+                let state = shared_state.clone();
                 tokio::spawn(async move {
-                    loop {
-                        if let Err(e) = producer.generate_block().await {
-                            warn!("Error generating block: {}", e);
-                        }
-                        random_delay().await;
-                    }
+                    web::start_web_server(web_tx, state, mempool, memes, producer_stats, llm, Some(reload_tx))
+                        .await
+                        .unwrap();
                 });
             }
 
@@ -350,6 +346,25 @@ async fn main() -> anyhow::Result<()> {
             }
         }
 
+        Commands::Chain { command } => match command {
+            ChainCommands::Export { node, output } => {
+                let archive = fetch(&node, "/api/chain/export").await?;
+                fs::write(&output, &archive)?;
+                info!("Wrote the chain of {} to {}", node, output);
+            }
+            ChainCommands::Verify { file } => {
+                let (_, summary) = import_chain(&file)?;
+                println!(
+                    "{} verified: {} blocks, {} registrations, tip {}, state root {}",
+                    file,
+                    summary.blocks,
+                    summary.registrations,
+                    summary.tip.as_deref().unwrap_or("none"),
+                    summary.state_root
+                );
+            }
+        },
+
         Commands::Start { node_type, web } => {
             info!("Starting {} node", node_type);
             if web {
@@ -357,7 +372,20 @@ async fn main() -> anyhow::Result<()> {
                 let (tx, _) = tokio::sync::broadcast::channel(100);
                 let state = StateStoreImpl::new(ChainConfig::default());
                 let state = Arc::new(state);
-                if let Err(e) = web::start_web_server(tx, state.clone()).await {
+                let mempool = Mempool::new(MEMPOOL_CAPACITY);
+                let producer_stats = StatsBoard::default();
+                let llm = Arc::new(LlmGateway::default());
+                if let Err(e) = web::start_web_server(
+                    tx,
+                    state.clone(),
+                    mempool,
+                    None,
+                    producer_stats,
+                    llm,
+                    None,
+                )
+                .await
+                {
                     warn!("Failed to start web server: {}", e);
                 }
             }
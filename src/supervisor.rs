@@ -0,0 +1,919 @@
+use crate::agent::{self, AgentInfo, AgentRole};
+use crate::checkpoints::Checkpoints;
+use crate::{random_delay, OpenAIConfig};
+use async_openai::config::OpenAIConfig as RawConfig;
+use chaoschain_actor::{async_trait, Actor, Context, DEFAULT_MAILBOX};
+use chaoschain_consensus::{
+    memory::{self, MemoryStore},
+    validator::Validator,
+    ConsensusManager,
+};
+use chaoschain_core::{
+    discussion::{Comment, CommentKind},
+    memes::MemeStore,
+    mempool::Mempool,
+    Block, ModelConfig, NetworkEvent, VoteSignature,
+};
+use chaoschain_llm::LlmGateway;
+use chaoschain_producer::{Producer, StatsBoard};
+use chaoschain_state::{StateStore, StateStoreImpl};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Mempool priority of governance votes, ahead of ordinary transactions
+const GOVERNANCE_TX_PRIORITY: u64 = 100;
+
+/// A request to reload character configs, answered with what changed
+pub type ReloadRequest = oneshot::Sender<Vec<ReloadEvent>>;
+
+/// Shared handles every spawned agent needs
+#[derive(Clone)]
+pub struct AgentContext {
+    pub tx: broadcast::Sender<NetworkEvent>,
+    pub state: Arc<StateStoreImpl>,
+    pub consensus: Arc<ConsensusManager>,
+    pub openai_config: Arc<OpenAIConfig>,
+    /// Stake allocated to each validator when it starts; more can be bonded on-chain
+    pub genesis_stake: u64,
+    /// Where agents persist their memories
+    pub data_dir: PathBuf,
+    pub mempool: Mempool,
+    /// Where validators store their memes, if anywhere
+    pub memes: Option<MemeStore>,
+    /// Where producers publish their stats
+    pub producer_stats: StatsBoard,
+    /// Shared by every agent's LLM calls
+    pub llm: Arc<LlmGateway>,
+    /// Where finalized blocks are checkpointed on L1, if anywhere
+    pub checkpoints: Option<Checkpoints>,
+}
+
+impl AgentContext {
+    /// Model defaults for agents of the given role
+    pub fn model_defaults(&self, role: AgentRole) -> ModelConfig {
+        match role {
+            AgentRole::Validator => self.openai_config.model_defaults(100),
+            AgentRole::Producer => self.openai_config.model_defaults(200),
+        }
+    }
+}
+
+/// Messages a validator agent handles
+pub enum ValidatorMessage {
+    /// A block was proposed and is up for discussion and voting
+    Proposal,
+}
+
+/// A validator reacting to block proposals announced on the event channel
+pub struct ValidatorActor {
+    validator: Validator,
+    agent_id: String,
+    tx: broadcast::Sender<NetworkEvent>,
+    consensus: Arc<ConsensusManager>,
+    state: Arc<StateStoreImpl>,
+    mempool: Mempool,
+    checkpoints: Option<Checkpoints>,
+}
+
+#[async_trait]
+impl Actor for ValidatorActor {
+    type Message = ValidatorMessage;
+    type Error = Infallible;
+
+    async fn started(&mut self, ctx: &mut Context<ValidatorMessage>) -> Result<(), Infallible> {
+        // Turn proposal announcements into mailbox messages
+        let mut rx = self.tx.subscribe();
+        if let Some(me) = ctx.address() {
+            ctx.spawn(async move {
+                loop {
+                    match rx.recv().await {
+                        Ok(event) if event.message.contains("DRAMATIC BLOCK PROPOSAL") => {
+                            if me.send(ValidatorMessage::Proposal).await.is_err() {
+                                break;
+                            }
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+        Ok(())
+    }
+
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<ValidatorMessage>,
+        msg: ValidatorMessage,
+    ) -> Result<(), Infallible> {
+        match msg {
+            ValidatorMessage::Proposal => {
+                if !self.vote_on_current_block().await {
+                    return Ok(());
+                }
+                self.vote_on_proposals().await;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ValidatorActor {
+    /// Discuss and vote on the block under consideration, false if its round moved on meanwhile
+    async fn vote_on_current_block(&mut self) -> bool {
+        let validator = &mut self.validator;
+        let consensus = &self.consensus;
+        let tx = &self.tx;
+        let agent_id = &self.agent_id;
+
+        let Some(mut block) = consensus.get_current_block().await else {
+            return true;
+        };
+
+        // Talk it over first if the network holds discussions
+        if !consensus.discussion_period().is_zero() {
+            match validator.discuss_block(&block).await {
+                Ok(Some(comment)) => announce_comment(tx, &comment),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to discuss block: {}", e),
+            }
+            consensus.wait_for_voting().await;
+
+            // The round may have moved on while the discussion ran
+            match consensus.get_current_block().await {
+                Some(current) if current.hash() == block.hash() => {}
+                _ => return false,
+            }
+        }
+
+        // Submit vote with stake
+        match validator.validate_block(block.clone()).await {
+            Ok((true, decision)) => {
+                let approved = decision.to_uppercase().contains("YES");
+
+                // Consensus reached!
+                let response = format!(
+                    "🎭 CONSENSUS: Block {} has been {}! Validator 🤖{} decision: {}",
+                    block.height,
+                    if approved { "❤️APPROVED❤️" } else { "💀REJECTED💀" },
+                    agent_id,
+                    decision
+                );
+
+                if let Err(e) = tx.send(NetworkEvent {
+                    agent_id: agent_id.clone(),
+                    message: response,
+                }) {
+                    warn!("Failed to send consensus message: {}", e);
+                }
+
+                // Store block in state if approved
+                if approved {
+                    info!("Storing block {} in state", block.height);
+
+                    // append vote details and their signatures to block
+                    let votes = consensus.get_votes().await;
+                    block.certificate = votes
+                        .iter()
+                        .map(|(agent_id, vote)| {
+                            let signed = VoteSignature {
+                                approve: vote.approve,
+                                signature: vote.signature,
+                            };
+                            (agent_id.clone(), signed)
+                        })
+                        .collect();
+                    let block_votes: HashMap<String, (bool, String)> = votes
+                        .into_iter()
+                        .map(|(agent_id, vote)| (agent_id, (vote.approve, vote.reason)))
+                        .collect();
+                    block.votes = block_votes;
+                    block.discussion = consensus.get_discussion().await;
+
+                    match self.state.apply_block(&block) {
+                        Ok(()) => self.checkpoint(&block),
+                        Err(e) => warn!("Failed to store block: {}", e),
+                    }
+                    self.mempool.remove_included(&block.transactions);
+                }
+            }
+            Ok((false, decision)) => {
+                let approved = decision.to_uppercase().contains("YES");
+
+                // Vote recorded but no consensus yet
+                let response = if approved {
+                    format!(
+                        "🎭 Validator 🤖{} APPROVES block {} - {}",
+                        agent_id, block.height, decision
+                    )
+                } else {
+                    let meme = consensus
+                        .get_votes()
+                        .await
+                        .get(agent_id)
+                        .and_then(|vote| vote.meme)
+                        .map(|hash| format!(" 🖼️ /api/memes/{}", hash))
+                        .unwrap_or_default();
+                    format!(
+                        "🎭 Validator 🤖{} REJECTS block {} - {}{}",
+                        agent_id, block.height, decision, meme
+                    )
+                };
+
+                if let Err(e) = tx.send(NetworkEvent {
+                    agent_id: agent_id.clone(),
+                    message: response,
+                }) {
+                    warn!("Failed to send validator response: {}", e);
+                }
+            }
+            Err(e) => {
+                warn!("Failed to submit vote: {}", e);
+            }
+        }
+        true
+    }
+
+    /// Hand a block this validator just stored to the bridge, with the state root after it
+    fn checkpoint(&self, block: &Block) {
+        let Some(checkpoints) = &self.checkpoints else {
+            return;
+        };
+        let state_root = self.state.state_root();
+        // The root is only the one after `block` while nothing was stored on top of it
+        match self.state.get_latest_block() {
+            Some(tip) if tip.hash() == block.hash() => checkpoints.finalized(block, state_root),
+            _ => warn!("Block {} is no longer the tip, not checkpointing it", block.height),
+        }
+    }
+
+    /// Weigh in on any pending rule proposals
+    async fn vote_on_proposals(&mut self) {
+        match self.validator.vote_on_proposals().await {
+            Ok(votes) => {
+                for vote in votes {
+                    if let Err(e) = self.mempool.add_tx(vote, GOVERNANCE_TX_PRIORITY) {
+                        warn!("Failed to queue governance vote: {}", e);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to vote on rule proposals: {}", e),
+        }
+    }
+}
+
+/// Start a validator actor reacting to block proposals on the event channel
+///
+/// A new validator gets a fresh key with `genesis_stake`; passing the key of a retired
+/// persona keeps whatever stake that key still has.
+pub fn spawn_validator(
+    ctx: &AgentContext,
+    agent_id: String,
+    personality: String,
+    model: ModelConfig,
+    openai: async_openai::Client<RawConfig>,
+    key: Option<SigningKey>,
+) -> (JoinHandle<()>, SigningKey) {
+    info!(
+        "Starting validator {} with {} personality on model {}",
+        agent_id, personality, model.model
+    );
+
+    let state = ctx.state.clone();
+    let signing_key = key.unwrap_or_else(|| {
+        // Stake backs both block votes and governance votes
+        let key = SigningKey::generate(&mut OsRng);
+        state.register_stake(&key.verifying_key(), ctx.genesis_stake);
+        key
+    });
+    state.register_agent(&agent_id, &signing_key.verifying_key());
+
+    let memory_dir = ctx.data_dir.join("memory");
+    let memory = MemoryStore::open(&memory_dir, &agent_id, memory::DEFAULT_CAPACITY)
+        .unwrap_or_else(|e| {
+            warn!(
+                "Failed to open memory of {} in {}, it will forget on restart: {}",
+                agent_id,
+                memory_dir.display(),
+                e
+            );
+            MemoryStore::in_memory(memory::DEFAULT_CAPACITY)
+        });
+
+    let mut validator = Validator::new(
+        agent_id.clone(),
+        signing_key.clone(),
+        state.clone(),
+        openai,
+        personality,
+        ctx.consensus.clone(),
+        model,
+        memory,
+        ctx.mempool.clone(),
+    )
+    .with_llm(ctx.llm.clone());
+    if let Some(memes) = &ctx.memes {
+        validator = validator.with_memes(memes.clone());
+    }
+
+    let actor = ValidatorActor {
+        validator,
+        agent_id: agent_id.clone(),
+        tx: ctx.tx.clone(),
+        consensus: ctx.consensus.clone(),
+        state,
+        mempool: ctx.mempool.clone(),
+        checkpoints: ctx.checkpoints.clone(),
+    };
+    // The proposal feed holds the actor's address, so it runs until aborted
+    let (_, handle) = chaoschain_actor::spawn(agent_id, actor, DEFAULT_MAILBOX);
+    (handle, signing_key)
+}
+
+/// Share a discussion post in the drama feed
+fn announce_comment(tx: &broadcast::Sender<NetworkEvent>, comment: &Comment) {
+    let message = match comment.kind {
+        CommentKind::Comment => format!("💬 {} says: {}", comment.author, comment.text),
+        CommentKind::Question => format!("❓ {} asks: {}", comment.author, comment.text),
+        CommentKind::Answer => format!("🗣️ {} answers: {}", comment.author, comment.text),
+    };
+    if let Err(e) = tx.send(NetworkEvent {
+        agent_id: comment.author.clone(),
+        message,
+    }) {
+        warn!("Failed to announce discussion post: {}", e);
+    }
+}
+
+/// Messages a producer agent handles
+pub enum ProducerMessage {
+    /// Produce a block, replying once its discussion is over
+    Produce(oneshot::Sender<()>),
+}
+
+/// A producer proposing a block, then resting a while, for as long as it runs
+pub struct ProducerActor {
+    producer: Producer,
+    tx: broadcast::Sender<NetworkEvent>,
+    discussion_period: Duration,
+}
+
+#[async_trait]
+impl Actor for ProducerActor {
+    type Message = ProducerMessage;
+    type Error = Infallible;
+
+    async fn started(&mut self, ctx: &mut Context<ProducerMessage>) -> Result<(), Infallible> {
+        if let Some(me) = ctx.address() {
+            ctx.spawn(async move {
+                while me.ask(ProducerMessage::Produce).await.is_ok() {
+                    random_delay().await;
+                }
+            });
+        }
+        Ok(())
+    }
+
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<ProducerMessage>,
+        msg: ProducerMessage,
+    ) -> Result<(), Infallible> {
+        match msg {
+            ProducerMessage::Produce(done) => {
+                match self.producer.generate_block().await {
+                    // Leave validators time to ask, then answer before voting opens
+                    Ok(block) if !self.discussion_period.is_zero() => {
+                        tokio::time::sleep(self.discussion_period * 2 / 3).await;
+                        match self.producer.answer_questions(&block).await {
+                            Ok(answers) => {
+                                for answer in &answers {
+                                    announce_comment(&self.tx, answer);
+                                }
+                            }
+                            Err(e) => warn!("Error answering questions: {}", e),
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Error generating block: {}", e),
+                }
+                let _ = done.send(());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Start a producer actor for a character config, returning its task and block signing key
+pub fn spawn_producer(ctx: &AgentContext, config: &AgentInfo) -> (JoinHandle<()>, VerifyingKey) {
+    let model = config.model_config(&ctx.model_defaults(AgentRole::Producer));
+
+    info!(
+        "Starting producer {} on model {}, with system prompt {}",
+        config.name, model.model, config.system
+    );
+    let producer = Producer::new(
+        config.name.clone(),
+        config.system.clone(),
+        ctx.state.clone(),
+        ctx.openai_config.client_for(config.endpoint.as_deref()),
+        ctx.tx.clone(),
+        ctx.consensus.clone(),
+        model,
+        ctx.mempool.clone(),
+    )
+    .with_bribe(config.bribe.unwrap_or(0))
+    .with_stats_board(ctx.producer_stats.clone())
+    .with_llm(ctx.llm.clone());
+    let producer = match config.style {
+        Some(style) => producer.with_style(style),
+        None => producer,
+    };
+
+    // Register producer in state
+    let key = producer.signing_key.verifying_key();
+    ctx.state.add_block_producer(key);
+    ctx.state.register_agent(&config.name, &key);
+
+    let actor = ProducerActor {
+        producer,
+        tx: ctx.tx.clone(),
+        discussion_period: ctx.consensus.discussion_period(),
+    };
+    // The production loop holds the actor's address, so it runs until aborted
+    let (_, handle) = chaoschain_actor::spawn(config.name.clone(), actor, DEFAULT_MAILBOX);
+
+    (handle, key)
+}
+
+/// What a reload did to an agent
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReloadAction {
+    Spawned,
+    Updated,
+    Retired,
+    Skipped,
+}
+
+/// One change applied while reloading character configs
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadEvent {
+    pub path: String,
+    pub agent: String,
+    pub role: Option<AgentRole>,
+    pub action: ReloadAction,
+    /// Why the file was skipped, if it was
+    pub detail: Option<String>,
+}
+
+/// A running agent backed by a character file
+struct ManagedAgent {
+    info: AgentInfo,
+    handle: JoinHandle<()>,
+    /// Block signing key, for producers
+    producer_key: Option<VerifyingKey>,
+    /// Staked key, for validators
+    validator_key: Option<SigningKey>,
+}
+
+/// Last seen state of a character file
+struct TrackedFile {
+    modified: SystemTime,
+    agent: Option<ManagedAgent>,
+    /// Held back by the demo limits, so retried on every reload until there is room
+    waiting: bool,
+}
+
+/// Keeps the agents of a running network in sync with `configs/*.character.json`
+pub struct AgentSupervisor {
+    ctx: AgentContext,
+    dir: PathBuf,
+    files: BTreeMap<PathBuf, TrackedFile>,
+    /// Most validators and producers run from files, from the demo command line
+    limits: (usize, usize),
+}
+
+impl AgentSupervisor {
+    /// `max_validators` and `max_producers` cap the agents running from files; files past
+    /// the cap wait until an agent of their role retires. A `max_producers` of 0 means no cap
+    pub fn new(ctx: AgentContext, dir: PathBuf, max_validators: usize, max_producers: usize) -> Self {
+        let max_producers = if max_producers == 0 {
+            usize::MAX
+        } else {
+            max_producers
+        };
+
+        Self {
+            ctx,
+            dir,
+            files: BTreeMap::new(),
+            limits: (max_validators, max_producers),
+        }
+    }
+
+    /// Number of running file-backed agents with the given role
+    pub fn running(&self, role: AgentRole) -> usize {
+        self.files
+            .values()
+            .filter_map(|file| file.agent.as_ref())
+            .filter(|agent| agent.info.role == role)
+            .count()
+    }
+
+    /// Diff the config directory against the running agents and apply the changes
+    pub fn reload(&mut self) -> Vec<ReloadEvent> {
+        let mut events = Vec::new();
+
+        let current = match scan(&self.dir) {
+            Ok(current) => current,
+            Err(e) => {
+                warn!("Failed to scan {}: {}", self.dir.display(), e);
+                return events;
+            }
+        };
+
+        // Retire agents whose files are gone
+        let removed: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| !current.contains_key(*path))
+            .cloned()
+            .collect();
+        for path in removed {
+            if let Some(agent) = self.files.remove(&path).and_then(|file| file.agent) {
+                events.push(self.retire(&path, agent, ReloadAction::Retired));
+            }
+        }
+
+        // Spawn new agents and replace changed ones; unreadable files are left as they were
+        for (path, modified) in current {
+            let Some(modified) = modified else {
+                continue;
+            };
+            let (unchanged, waiting) = match self.files.get(&path) {
+                Some(file) => (file.modified == modified, file.waiting),
+                None => (false, false),
+            };
+            if unchanged && !waiting {
+                continue;
+            }
+            let previous = self.files.remove(&path).and_then(|file| file.agent);
+
+            let info = match agent::read_agent_info(&path) {
+                Ok(info) => info,
+                Err(e) => {
+                    // Keep the old agent running until the file is fixed
+                    warn!("Failed to read character config {}: {}", path.display(), e);
+                    events.push(ReloadEvent {
+                        path: path.display().to_string(),
+                        agent: previous
+                            .as_ref()
+                            .map(|agent| agent.info.name.clone())
+                            .unwrap_or_default(),
+                        role: previous.as_ref().map(|agent| agent.info.role),
+                        action: ReloadAction::Skipped,
+                        detail: Some(e.to_string()),
+                    });
+                    self.files.insert(
+                        path,
+                        TrackedFile {
+                            modified,
+                            agent: previous,
+                            waiting: false,
+                        },
+                    );
+                    continue;
+                }
+            };
+
+            // A validator reborn as a validator keeps its key and the stake behind it
+            let (action, key) = match previous {
+                Some(mut agent) => {
+                    let key = agent
+                        .validator_key
+                        .take()
+                        .filter(|_| info.role == AgentRole::Validator);
+                    self.retire(&path, agent, ReloadAction::Updated);
+                    (ReloadAction::Updated, key)
+                }
+                None => (ReloadAction::Spawned, None),
+            };
+
+            let limit = match info.role {
+                AgentRole::Validator => self.limits.0,
+                AgentRole::Producer => self.limits.1,
+            };
+            if self.running(info.role) >= limit {
+                if !(unchanged && waiting) {
+                    info!(
+                        "Not starting {} from {} until there is room, demo limit reached",
+                        info.name,
+                        path.display()
+                    );
+                }
+                self.files.insert(
+                    path,
+                    TrackedFile {
+                        modified,
+                        agent: None,
+                        waiting: true,
+                    },
+                );
+                continue;
+            }
+
+            let agent = self.spawn(info, key);
+            let event = ReloadEvent {
+                path: path.display().to_string(),
+                agent: agent.info.name.clone(),
+                role: Some(agent.info.role),
+                action,
+                detail: None,
+            };
+            self.announce(&event);
+            events.push(event);
+            self.files.insert(
+                path,
+                TrackedFile {
+                    modified,
+                    agent: Some(agent),
+                    waiting: false,
+                },
+            );
+        }
+
+        events
+    }
+
+    /// Reload every `interval` and whenever a request arrives
+    pub async fn run(mut self, interval: Duration, mut requests: mpsc::Receiver<ReloadRequest>) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    self.reload();
+                }
+                Some(reply) = requests.recv() => {
+                    let _ = reply.send(self.reload());
+                }
+            }
+        }
+    }
+
+    fn spawn(&self, info: AgentInfo, key: Option<SigningKey>) -> ManagedAgent {
+        match info.role {
+            AgentRole::Validator => {
+                let model = info.model_config(&self.ctx.model_defaults(AgentRole::Validator));
+                let openai = self
+                    .ctx
+                    .openai_config
+                    .client_for(info.endpoint.as_deref());
+                let (handle, key) = spawn_validator(
+                    &self.ctx,
+                    info.name.clone(),
+                    info.system.clone(),
+                    model,
+                    openai,
+                    key,
+                );
+                ManagedAgent {
+                    info,
+                    handle,
+                    producer_key: None,
+                    validator_key: Some(key),
+                }
+            }
+            AgentRole::Producer => {
+                let (handle, key) = spawn_producer(&self.ctx, &info);
+                ManagedAgent {
+                    info,
+                    handle,
+                    producer_key: Some(key),
+                    validator_key: None,
+                }
+            }
+        }
+    }
+
+    fn retire(&self, path: &Path, agent: ManagedAgent, action: ReloadAction) -> ReloadEvent {
+        agent.handle.abort();
+        if let Some(key) = agent.producer_key {
+            self.ctx.state.remove_block_producer(&key);
+        }
+        if let Some(key) = &agent.validator_key {
+            self.ctx.state.remove_stake(&key.verifying_key());
+        }
+
+        let event = ReloadEvent {
+            path: path.display().to_string(),
+            agent: agent.info.name,
+            role: Some(agent.info.role),
+            action,
+            detail: None,
+        };
+        if action == ReloadAction::Retired {
+            self.announce(&event);
+        }
+        event
+    }
+
+    /// Record a reload on the event feed so the UI and logs show it
+    fn announce(&self, event: &ReloadEvent) {
+        let role = match event.role {
+            Some(AgentRole::Validator) => "validator",
+            Some(AgentRole::Producer) => "producer",
+            None => "agent",
+        };
+        let action = match event.action {
+            ReloadAction::Spawned => "joined the network",
+            ReloadAction::Updated => "was reborn with a new persona",
+            ReloadAction::Retired => "left the network",
+            ReloadAction::Skipped => "could not be reloaded",
+        };
+
+        info!("Reloaded {} from {}: {:?}", event.agent, event.path, event.action);
+        let _ = self.ctx.tx.send(NetworkEvent {
+            agent_id: event.agent.clone(),
+            message: format!("🔄 AGENT RELOAD: {} 🤖{} {}", role, event.agent, action),
+        });
+    }
+}
+
+/// Modification times of every character config in `dir`, `None` for files whose metadata
+/// could not be read
+fn scan(dir: &Path) -> std::io::Result<BTreeMap<PathBuf, Option<SystemTime>>> {
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                warn!("Skipping an entry of {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+        let is_character = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.ends_with(".character.json"))
+            .unwrap_or(false);
+        if is_character {
+            let modified = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .map_err(|e| warn!("Skipping {}: {}", path.display(), e))
+                .ok();
+            files.insert(path, modified);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaoschain_llm::LlmConfig;
+
+    fn context(dir: &Path) -> AgentContext {
+        let state = Arc::new(StateStoreImpl::new(Default::default()));
+        let consensus = Arc::new(chaoschain_consensus::create_consensus_manager(
+            state.clone(),
+            Default::default(),
+        ));
+        AgentContext {
+            tx: broadcast::channel(16).0,
+            state,
+            consensus,
+            openai_config: Arc::new(OpenAIConfig {
+                api_base: "http://127.0.0.1:9".to_string(),
+                api_key: "test".to_string(),
+                model: "test".to_string(),
+                temperature: 0.0,
+            }),
+            genesis_stake: 100,
+            data_dir: dir.to_path_buf(),
+            mempool: Mempool::new(16),
+            memes: None,
+            producer_stats: StatsBoard::default(),
+            llm: Arc::new(LlmGateway::new(LlmConfig::default())),
+            checkpoints: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn reloading_a_validator_keeps_its_stake_and_retiring_it_removes_the_stake() {
+        let dir = std::env::temp_dir()
+            .join(format!("chaoschain-supervisor-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ctx = context(&dir);
+        let state = ctx.state.clone();
+        let total_stake = || state.validator_set(0).map(|set| set.total_stake).unwrap_or(0);
+        let mut supervisor = AgentSupervisor::new(ctx, dir.clone(), 10, 0);
+
+        let path = dir.join("skeptic.character.json");
+        let write = |persona: &str, age: u64| {
+            let json = format!(
+                r#"{{"name": "Skeptic", "role": "validator", "system": "{}"}}"#,
+                persona
+            );
+            fs::write(&path, json).unwrap();
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(age);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+
+        write("grumpy", 1);
+        supervisor.reload();
+        assert_eq!(total_stake(), 100);
+
+        for (age, persona) in ["cheerful", "paranoid", "sleepy"].into_iter().enumerate() {
+            write(persona, age as u64 + 2);
+            let events = supervisor.reload();
+            assert_eq!(events[0].action, ReloadAction::Updated);
+            assert_eq!(total_stake(), 100);
+            assert_eq!(state.get_state().stakes.len(), 1);
+        }
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(supervisor.reload()[0].action, ReloadAction::Retired);
+        assert_eq!(total_stake(), 0);
+        assert!(state.get_state().stakes.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reload_spawns_updates_retires_and_skips_character_files() {
+        let dir = std::env::temp_dir()
+            .join(format!("chaoschain-supervisor-diff-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut supervisor = AgentSupervisor::new(context(&dir), dir.clone(), 1, 0);
+        let write = |file: &str, json: &str, age: u64| {
+            let path = dir.join(file);
+            fs::write(&path, json).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(age))
+                .unwrap();
+        };
+        let validator = |name: &str| {
+            format!(
+                r#"{{"name": "{}", "role": "validator", "system": "grumpy"}}"#,
+                name
+            )
+        };
+        let actions = |events: Vec<ReloadEvent>| {
+            events
+                .into_iter()
+                .map(|event| (event.agent, event.action))
+                .collect::<Vec<_>>()
+        };
+
+        // One validator fits, the second waits, a broken file is reported once
+        write("a.character.json", &validator("A"), 1);
+        write("b.character.json", &validator("B"), 1);
+        write("c.character.json", "{", 1);
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.join("gone"), dir.join("d.character.json")).unwrap();
+        assert_eq!(
+            actions(supervisor.reload()),
+            vec![
+                ("A".to_string(), ReloadAction::Spawned),
+                (String::new(), ReloadAction::Skipped)
+            ]
+        );
+        assert_eq!(supervisor.running(AgentRole::Validator), 1);
+        assert!(supervisor.reload().is_empty());
+
+        // Edits restart an agent, and a retirement makes room for the waiting file
+        write("a.character.json", &validator("A"), 2);
+        assert_eq!(
+            actions(supervisor.reload()),
+            vec![("A".to_string(), ReloadAction::Updated)]
+        );
+        fs::remove_file(dir.join("a.character.json")).unwrap();
+        assert_eq!(
+            actions(supervisor.reload()),
+            vec![
+                ("A".to_string(), ReloadAction::Retired),
+                ("B".to_string(), ReloadAction::Spawned)
+            ]
+        );
+        assert_eq!(supervisor.running(AgentRole::Validator), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,33 +1,65 @@
 use anyhow::Result;
 use axum::{
-    extract::State,
-    response::sse::{Event, Sse},
-    routing::get,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse,
+    },
+    routing::{get, post},
     Json, Router,
 };
-use chaoschain_state::StateStoreImpl;
+use chaoschain_llm::{journal::JournalEntry, LlmGateway, Usage};
+use chaoschain_producer::{ProducerStats, StatsBoard};
+use chaoschain_state::{
+    archive::{self, ArchiveError},
+    Snapshot, StateError, StateStoreImpl,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use chrono;
 use futures::stream::Stream;
 use futures::StreamExt;
 use hex;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use chaoschain_core::{NetworkEvent, Block};
-use std::collections::HashMap;
+use chaoschain_core::{
+    bribery::Escrow,
+    governance::{GovernanceAction, RuleAmendment, RuleProposal},
+    memes::{self, MemeError, MemeHash, MemeStore},
+    mempool::Mempool,
+    metrics,
+    reputation::Link,
+    staking::ValidatorSet,
+    Block, BlockHeader, NetworkEvent, Transaction,
+};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::RwLock;
 use std::{net::SocketAddr, sync::Arc};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio_stream::wrappers::BroadcastStream;
 use tower_http::services::ServeDir;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
 
+use crate::supervisor::{ReloadEvent, ReloadRequest};
+
 /// Web server state
 pub struct AppState {
     /// Channel for network events
     pub tx: broadcast::Sender<NetworkEvent>,
     /// Chain state
     pub state: Arc<StateStoreImpl>,
+    /// Pending transactions submitted through the API
+    pub mempool: Mempool,
+    /// Memes uploaded here or stored by local agents
+    pub memes: Option<MemeStore>,
+    /// Latest stats of the local producers
+    pub producer_stats: StatsBoard,
+    /// LLM calls of the local agents
+    pub llm: Arc<LlmGateway>,
+    /// Requests to reload agent character configs, if agents are supervised
+    pub reload: Option<mpsc::Sender<ReloadRequest>>,
 }
 
 #[derive(Default)]
@@ -65,10 +97,20 @@ pub struct BlockInfo {
 pub async fn start_web_server(
     tx: broadcast::Sender<NetworkEvent>,
     state: Arc<StateStoreImpl>,
+    mempool: Mempool,
+    memes: Option<MemeStore>,
+    producer_stats: StatsBoard,
+    llm: Arc<LlmGateway>,
+    reload: Option<mpsc::Sender<ReloadRequest>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let app_state = Arc::new(AppState {
         tx,
         state: state.clone(),
+        mempool,
+        memes,
+        producer_stats,
+        llm,
+        reload,
     });
 
     let cors = CorsLayer::new()
@@ -85,6 +127,31 @@ pub async fn start_web_server(
     let app = Router::new()
         .route("/api/network/status", get(get_network_status))
         .route("/api/events", get(events_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/api/admin/reload", post(reload_handler))
+        .route("/api/governance/rules", get(get_rules))
+        .route(
+            "/api/governance/proposals",
+            get(get_proposals).post(propose_rule),
+        )
+        .route("/api/accounts", get(get_accounts))
+        .route("/api/accounts/:account", get(get_account))
+        .route("/api/social", get(get_social_graph))
+        .route("/api/social/:agent", get(get_agent_links))
+        .route("/api/bribes", get(get_bribes))
+        .route("/api/producers/stats", get(get_producer_stats))
+        .route("/api/producers/:producer/stats", get(get_producer_stat))
+        .route("/api/chain/export", get(export_chain))
+        .route("/api/blocks/:height", get(get_block))
+        .route("/api/headers/:height", get(get_header))
+        .route("/api/validators", get(get_validators))
+        .route("/api/snapshots", get(get_snapshots))
+        .route("/api/snapshots/latest", get(get_latest_snapshot))
+        .route("/api/snapshots/:height", get(get_snapshot))
+        .route("/api/llm/usage", get(get_llm_usage))
+        .route("/api/llm/journal/:block_hash", get(get_llm_journal))
+        .route("/api/memes", post(upload_meme))
+        .route("/api/memes/:hash", get(get_meme))
         .nest_service("/", ServeDir::new("static"))
         .layer(cors)
         .with_state(app_state);
@@ -117,6 +184,7 @@ async fn get_network_status(State(state): State<Arc<AppState>>) -> Json<NetworkS
                 "producer": block.producer_id,
                 "message": block.message,
                 "transaction_count": block.transactions.len(),
+                "style": block.style,
                 "votes": block.votes.iter().map(|(validator_id, (approved, comment))| {
                     serde_json::json!({
                         "validator": validator_id,
@@ -131,19 +199,476 @@ async fn get_network_status(State(state): State<Arc<AppState>>) -> Json<NetworkS
         })
         .collect();
 
-    // Get latest block height
-    let latest_block = state_guard.get_block_height();
+    // Height of the tip, as in the block height metric, and the number of blocks
+    let total_blocks = state_guard.get_block_height();
+    let latest_block = total_blocks.saturating_sub(1);
 
     Json(NetworkStatus {
         validator_count: 4, // We know we started with 4 validators
         producer_count: chain_state.producers.len() as u32,
         latest_block,
-        total_blocks_produced: latest_block,
-        total_blocks_validated: latest_block,
+        total_blocks_produced: total_blocks,
+        total_blocks_validated: total_blocks,
         latest_blocks,
     })
 }
 
+/// Expose chain and agent metrics in the Prometheus text format
+async fn metrics_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::global().render(),
+    )
+}
+
+/// Request body for proposing new rules, signed by the proposer
+///
+/// The signature covers the transaction bytes `nonce (u64 big endian) || payload`, where the
+/// payload is `{"governance":{"propose_rule":{"text":...}}}` as compact JSON.
+#[derive(Debug, Deserialize)]
+pub struct ProposeRuleRequest {
+    pub text: String,
+    /// Hex public key of the proposer
+    pub sender: String,
+    /// Next unused nonce of the proposer's account
+    pub nonce: u64,
+    /// Base64 ed25519 signature
+    pub signature: String,
+}
+
+/// Every version of the rules, oldest first, with the one in force at the next height
+#[derive(Debug, Serialize)]
+pub struct RuleHistory {
+    pub active: Option<RuleAmendment>,
+    pub amendments: Vec<RuleAmendment>,
+}
+
+/// Get the amendment history of the chain's rules
+async fn get_rules(State(state): State<Arc<AppState>>) -> Json<RuleHistory> {
+    let governance = state.state.get_governance();
+    Json(RuleHistory {
+        active: state.state.active_rule(state.state.get_block_height()),
+        amendments: governance.amendments,
+    })
+}
+
+/// Get all rule proposals and their votes
+async fn get_proposals(State(state): State<Arc<AppState>>) -> Json<Vec<RuleProposal>> {
+    Json(state.state.get_governance().proposals)
+}
+
+/// Queue a rule proposal for inclusion in the next block
+async fn propose_rule(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ProposeRuleRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let text = request.text;
+    if text.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Proposed rules must not be empty".to_string(),
+        ));
+    }
+
+    let bad_request = |what: &str| (StatusCode::BAD_REQUEST, format!("Invalid {}", what));
+    let sender: [u8; 32] = hex::decode(&request.sender)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| bad_request("sender"))?;
+    let signature: [u8; 64] = BASE64
+        .decode(&request.signature)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| bad_request("signature"))?;
+    let action = GovernanceAction::ProposeRule { text: text.clone() };
+    let tx = Transaction {
+        sender,
+        nonce: request.nonce,
+        payload: action.encode(),
+        signature,
+    };
+    if !tx.verify() {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Signature does not match the proposal".to_string(),
+        ));
+    }
+    let proposer = hex::encode(tx.sender);
+    let expected = state.state.get_account(&proposer).nonce;
+    if tx.nonce < expected {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Nonce {} is used, the next one is {}", tx.nonce, expected),
+        ));
+    }
+
+    state
+        .mempool
+        .add_tx(tx, 100)
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e.to_string()))?;
+
+    let _ = state.tx.send(NetworkEvent {
+        agent_id: proposer.clone(),
+        message: format!("📜 RULE PROPOSAL: {} wants the rules to become \"{}\"", proposer, text),
+    });
+
+    Ok(Json(serde_json::json!({
+        "proposer": proposer,
+        "text": text,
+        "status": "queued",
+    })))
+}
+
+/// Balance and nonce of one account
+#[derive(Debug, Serialize)]
+pub struct AccountInfo {
+    /// Hex public key
+    pub account: String,
+    pub balance: u64,
+    pub nonce: u64,
+}
+
+/// Get every account on the ledger
+async fn get_accounts(State(state): State<Arc<AppState>>) -> Json<Vec<AccountInfo>> {
+    Json(
+        state
+            .state
+            .get_accounts()
+            .into_iter()
+            .map(|(account, info)| AccountInfo {
+                account,
+                balance: info.balance,
+                nonce: info.nonce,
+            })
+            .collect(),
+    )
+}
+
+/// Get one account by hex public key
+async fn get_account(
+    State(state): State<Arc<AppState>>,
+    Path(account): Path<String>,
+) -> Result<Json<AccountInfo>, (StatusCode, String)> {
+    let account = account.to_lowercase();
+    if account.len() != 64 || hex::decode(&account).is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Accounts are 32-byte hex public keys".to_string(),
+        ));
+    }
+
+    let info = state.state.get_account(&account);
+    Ok(Json(AccountInfo {
+        account,
+        balance: info.balance,
+        nonce: info.nonce,
+    }))
+}
+
+/// Get every link of the social graph
+async fn get_social_graph(State(state): State<Arc<AppState>>) -> Json<Vec<Link>> {
+    Json(state.state.get_social_graph().links())
+}
+
+/// Get the links touching one agent
+async fn get_agent_links(
+    State(state): State<Arc<AppState>>,
+    Path(agent): Path<String>,
+) -> Json<Vec<Link>> {
+    Json(state.state.get_social_graph().links_of(&agent))
+}
+
+/// Get the bribes in escrow
+async fn get_bribes(State(state): State<Arc<AppState>>) -> Json<Vec<Escrow>> {
+    Json(state.state.get_bribes())
+}
+
+/// Get the stats of every local producer, by producer id
+async fn get_producer_stats(
+    State(state): State<Arc<AppState>>,
+) -> Json<BTreeMap<String, ProducerStats>> {
+    Json(state.producer_stats.read().unwrap().clone())
+}
+
+/// Get the stats of one producer
+async fn get_producer_stat(
+    State(state): State<Arc<AppState>>,
+    Path(producer): Path<String>,
+) -> Result<Json<ProducerStats>, (StatusCode, String)> {
+    state
+        .producer_stats
+        .read()
+        .unwrap()
+        .get(&producer)
+        .cloned()
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, format!("No stats for producer {}", producer)))
+}
+
+/// First block of a chain export
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub from: u64,
+}
+
+/// Download the chain history from `?from=` on, all of it by default, as a JSON lines archive
+async fn export_chain(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let mut data = Vec::new();
+    archive::export_from(&state.state, query.from, &mut data).map_err(|e| {
+        let status = match e {
+            ArchiveError::Unavailable { .. } => StatusCode::GONE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, e.to_string())
+    })?;
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], data))
+}
+
+/// Get one block, or 410 if this node pruned it
+async fn get_block(
+    State(state): State<Arc<AppState>>,
+    Path(height): Path<u64>,
+) -> Result<Json<Block>, (StatusCode, String)> {
+    state.state.find_block(height).map(Json).map_err(|e| {
+        let status = match e {
+            StateError::Pruned { .. } => StatusCode::GONE,
+            _ => StatusCode::NOT_FOUND,
+        };
+        (status, e.to_string())
+    })
+}
+
+/// Get one block's header and certificate, kept for every block even on a pruned node
+async fn get_header(
+    State(state): State<Arc<AppState>>,
+    Path(height): Path<u64>,
+) -> Result<Json<BlockHeader>, (StatusCode, String)> {
+    state
+        .state
+        .get_header(height)
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, format!("No header at height {}", height)))
+}
+
+/// Height to look up the validator set at
+#[derive(Debug, Deserialize)]
+pub struct ValidatorsQuery {
+    pub height: Option<u64>,
+}
+
+/// Get the validator set voting at `?height=`, the next block by default. Save it from a node
+/// you trust to bootstrap another node with `--trusted-validators`.
+async fn get_validators(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ValidatorsQuery>,
+) -> Result<Json<ValidatorSet>, (StatusCode, String)> {
+    let height = query
+        .height
+        .unwrap_or_else(|| state.state.get_block_height());
+    state
+        .state
+        .validator_set(height)
+        .map(Json)
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("No validator set at height {}", height),
+        ))
+}
+
+/// A kept snapshot
+#[derive(Debug, Serialize)]
+pub struct SnapshotInfo {
+    pub height: u64,
+    pub root: String,
+}
+
+/// List the snapshots this node can serve, oldest first
+async fn get_snapshots(State(state): State<Arc<AppState>>) -> Json<Vec<SnapshotInfo>> {
+    Json(
+        state
+            .state
+            .snapshot_roots()
+            .into_iter()
+            .map(|(height, root)| SnapshotInfo { height, root })
+            .collect(),
+    )
+}
+
+/// Get the latest snapshot, to bootstrap a node from
+async fn get_latest_snapshot(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Snapshot>, (StatusCode, String)> {
+    state
+        .state
+        .latest_snapshot()
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "No snapshot taken yet".to_string()))
+}
+
+/// Get the snapshot at one height
+async fn get_snapshot(
+    State(state): State<Arc<AppState>>,
+    Path(height): Path<u64>,
+) -> Result<Json<Snapshot>, (StatusCode, String)> {
+    if let Some(snapshot) = state.state.get_snapshot(height) {
+        return Ok(Json(snapshot));
+    }
+    let first = state.state.first_height();
+    if (state.state.origin_height()..first).contains(&height) {
+        return Err((
+            StatusCode::GONE,
+            format!(
+                "State at height {} was pruned, this node keeps snapshots from height {}",
+                height, first
+            ),
+        ));
+    }
+    Err((StatusCode::NOT_FOUND, format!("No snapshot at height {}", height)))
+}
+
+/// LLM spending of the local agents
+#[derive(Debug, Serialize)]
+pub struct LlmUsage {
+    pub total: Usage,
+    pub over_budget: bool,
+    pub token_budget: Option<u64>,
+    pub cost_budget: Option<f64>,
+    /// Spending by agent id
+    pub agents: BTreeMap<String, Usage>,
+}
+
+/// Get how many LLM calls, tokens and dollars the local agents have spent
+async fn get_llm_usage(State(state): State<Arc<AppState>>) -> Json<LlmUsage> {
+    let config = state.llm.config();
+    Json(LlmUsage {
+        total: state.llm.total(),
+        over_budget: state.llm.over_budget(),
+        token_budget: config.token_budget,
+        cost_budget: config.cost_budget,
+        agents: state.llm.usage(),
+    })
+}
+
+/// Filter for journaled LLM calls
+#[derive(Debug, Deserialize)]
+pub struct JournalQuery {
+    pub agent: Option<String>,
+}
+
+/// Get the recorded LLM calls about one block, optionally of one agent only
+async fn get_llm_journal(
+    State(state): State<Arc<AppState>>,
+    Path(block_hash): Path<String>,
+    Query(query): Query<JournalQuery>,
+) -> Result<Json<Vec<JournalEntry>>, (StatusCode, String)> {
+    let journal = state.llm.journal().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "LLM calls are not recorded on this node".to_string(),
+    ))?;
+    let block_hash = block_hash.to_lowercase();
+    if block_hash.len() != 64 || hex::decode(&block_hash).is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Block hashes are 32-byte hex".to_string(),
+        ));
+    }
+
+    journal
+        .entries(&block_hash, query.agent.as_deref())
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// A stored meme
+#[derive(Debug, Serialize)]
+pub struct MemeInfo {
+    pub hash: MemeHash,
+    pub content_type: &'static str,
+    pub size: usize,
+}
+
+fn meme_store(state: &AppState) -> Result<&MemeStore, (StatusCode, String)> {
+    state.memes.as_ref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Memes are not stored on this node".to_string(),
+    ))
+}
+
+/// Store an image or text meme from the request body, returning its hash
+async fn upload_meme(
+    State(state): State<Arc<AppState>>,
+    body: Bytes,
+) -> Result<Json<MemeInfo>, (StatusCode, String)> {
+    let hash = meme_store(&state)?.put(&body).map_err(|e| {
+        let status = match e {
+            MemeError::Empty => StatusCode::BAD_REQUEST,
+            MemeError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, e.to_string())
+    })?;
+
+    Ok(Json(MemeInfo {
+        hash,
+        content_type: memes::content_type(&body),
+        size: body.len(),
+    }))
+}
+
+/// Serve a meme by hash with its sniffed content type
+async fn get_meme(
+    State(state): State<Arc<AppState>>,
+    Path(hash): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let hash: MemeHash = hash
+        .to_lowercase()
+        .parse()
+        .map_err(|e: MemeError| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let data = meme_store(&state)?
+        .get(&hash)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("No meme {}", hash)))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, memes::content_type(&data)),
+            // Content-addressed, so it can never change
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        data,
+    ))
+}
+
+/// Reload agent character configs now and report what changed
+async fn reload_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ReloadEvent>>, (StatusCode, String)> {
+    let reload = state.reload.as_ref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Agent reloading is not enabled on this node".to_string(),
+    ))?;
+
+    let (tx, rx) = oneshot::channel();
+    reload.send(tx).await.map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Agent supervisor is not running".to_string(),
+        )
+    })?;
+    let events = rx.await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Agent supervisor dropped the reload request".to_string(),
+        )
+    })?;
+
+    Ok(Json(events))
+}
+
 /// Stream network events to the web UI
 async fn events_handler(
     State(state): State<Arc<AppState>>,
@@ -158,6 +683,8 @@ async fn events_handler(
                     "Consensus"
                 } else if msg.message.contains("APPROVES") || msg.message.contains("REJECTS") {
                     "Vote"
+                } else if msg.message.contains("AGENT RELOAD") {
+                    "AgentReload"
                 } else {
                     "Drama"
                 };